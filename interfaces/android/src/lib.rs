@@ -9,10 +9,11 @@ pub mod bulletproofs_android {
 
     use jni::JNIEnv;
     use jni::objects::{JClass, JObject, JString, JValue};
-    use jni::sys::{jbyteArray, jboolean};
+    use jni::sys::{jbyteArray, jboolean, jint};
 
     use bulletproofs_gadgets::prove::prove;
     use bulletproofs_gadgets::verify::verify;
+    use bulletproofs_gadgets::generators::preload_generators;
 
     //
     // Signature for the BulletproofWrapper used in the Android side (output of javap -s)
@@ -86,7 +87,13 @@ pub mod bulletproofs_android {
         let witness: String = get_jobject_member_string(&env, data, "getWitness", "()Ljava/lang/String;").unwrap();
         let gadgets: String = get_jobject_member_string(&env, data, "getGadgets", "()Ljava/lang/String;").unwrap();
         let mut commitments = String::new();
-        let proof = prove(Box::leak(name.into_boxed_str()), instance, witness, gadgets, &mut commitments).expect("unable to generate proof from provided Android data");
+        let proof = match prove(Box::leak(name.into_boxed_str()), instance, witness, gadgets, &mut commitments) {
+            Ok(proof) => proof,
+            Err(error) => {
+                env.throw_new("java/lang/IllegalArgumentException", error.to_string()).unwrap();
+                return;
+            }
+        };
 
         let java_commitments: JObject = env.new_string(commitments).unwrap().into();
         env.call_method(data, "setCommitments", "(Ljava/lang/String;)V", &[JValue::from(java_commitments)]).unwrap();
@@ -102,7 +109,22 @@ pub mod bulletproofs_android {
         let gadgets: String = get_jobject_member_string(&env, data, "getGadgets", "()Ljava/lang/String;").unwrap();
         let proof: Vec<u8> = get_jobject_member_bytes(&env, data, "getProof", "()[B").unwrap();
 
-        let verified = verify(Box::leak(name.into_boxed_str()), instance, proof, commitments, gadgets).expect("unable to verify proof from provided Android data");
+        let verified = match verify(Box::leak(name.into_boxed_str()), instance, proof, commitments, gadgets) {
+            Ok(verified) => verified,
+            Err(error) => {
+                env.throw_new("java/lang/IllegalArgumentException", error.to_string()).unwrap();
+                return false as jboolean;
+            }
+        };
         jboolean::try_from(JValue::from(verified)).unwrap()
     }
+
+    #[no_mangle]
+    pub unsafe extern fn Java_com_unholster_examplebulletproofs_RustBulletproofs_extPreloadGenerators(env: JNIEnv, _: JClass, capacity: jint) {
+        if capacity < 0 {
+            env.throw_new("java/lang/IllegalArgumentException", "capacity must not be negative").unwrap();
+            return;
+        }
+        preload_generators(capacity as usize);
+    }
 }
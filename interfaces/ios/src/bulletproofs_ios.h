@@ -10,4 +10,5 @@ struct ProofArtifacts {
 
 const struct ProofArtifacts* c_prove(const char* name, const char* instance, const char* witness, const char* gadgets);
 const bool c_verify(const char* name, const char* instance, const char* gadgets, const char* commitments, const uint8_t* proof, int proof_len);
+bool c_preload_generators(int capacity);
 void free_proof(struct ProofArtifacts* artifacts_pointer);
@@ -1,11 +1,13 @@
 use std::boxed::Box;
 use std::ffi::{CString, CStr};
 use std::mem::forget;
+use std::ptr;
 use std::slice;
-use std::os::raw::{c_char};
+use std::os::raw::{c_char, c_int};
 
 use bulletproofs_gadgets::prove::prove;
 use bulletproofs_gadgets::verify::verify;
+use bulletproofs_gadgets::generators::preload_generators;
 
 #[repr(C)]
 pub struct ProofArtifacts {
@@ -24,7 +26,11 @@ pub extern fn c_prove(name: *const c_char, instance: *const c_char, witness: *co
     let witness_str: String = (unsafe {CStr::from_ptr(witness)}).to_string_lossy().into_owned();
     let gadgets_str: String = (unsafe {CStr::from_ptr(gadgets)}).to_string_lossy().into_owned();
     let mut commitments = String::new();
-    let proof: Vec<u8> = prove(name_str, instance_str, witness_str, gadgets_str, &mut commitments).expect("unable to generate proof from provided iOS data");
+    let proof: Vec<u8> = match prove(name_str, instance_str, witness_str, gadgets_str, &mut commitments) {
+        Ok(proof) => proof,
+        // a null pointer signals that no proof could be generated from the provided data
+        Err(_) => return ptr::null_mut()
+    };
 
     let commitments_c = CString::new(commitments).expect("could not convert coms to a C string");
     let commitments_pointer = commitments_c.as_ptr();
@@ -48,7 +54,17 @@ pub extern fn c_verify(name: *const c_char, instance: *const c_char, gadgets: *c
     let gadgets_str: String = (unsafe {CStr::from_ptr(gadgets)}).to_string_lossy().into_owned();
     let commitments_str: String = (unsafe {CStr::from_ptr(commitments)}).to_string_lossy().into_owned();
     let proof_vec: Vec<u8> = (unsafe {slice::from_raw_parts(proof as *mut u8, proof_len)}).to_vec();
-    verify(name_str, instance_str, proof_vec, commitments_str, gadgets_str).unwrap()
+    verify(name_str, instance_str, proof_vec, commitments_str, gadgets_str).unwrap_or(false)
+}
+
+#[no_mangle]
+pub extern fn c_preload_generators(capacity: c_int) -> bool {
+    // false signals a capacity that is not positive, nothing is preloaded
+    if capacity <= 0 {
+        return false;
+    }
+    preload_generators(capacity as usize);
+    true
 }
 
 #[no_mangle]
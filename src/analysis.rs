@@ -0,0 +1,247 @@
+use bulletproofs::r1cs::Prover;
+use bulletproofs::PedersenGens;
+use merlin::Transcript;
+
+use statement::{Statement, Clause};
+use lalrpop::ast::Var;
+use lalrpop::assignment_parser::Assignments;
+use cs_buffer::{ConstraintSystemBuffer, ProverBuffer, Operation};
+use prove::prove_clauses;
+use error::GadgetError;
+
+use std::fmt;
+
+/// size of an r1cs proof without the inner product proof (version byte, 8 points and 3 scalars)
+const PROOF_BASE_SIZE: usize = 1 + 11 * 32;
+
+/// Size of a part of a statement in the constraint system.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cost {
+    pub multipliers: usize,
+    pub constraints: usize,
+    /// commitments derived by the gadgets
+    pub derived_commitments: usize
+}
+
+/// Cost of a single gadget line, the cost of an OR includes its branches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineCost {
+    /// line of the gadget in the text representation of the statement (1-based)
+    pub line: usize,
+    /// keyword of the gadget, e.g. BOUND
+    pub gadget: &'static str,
+    pub cost: Cost,
+    /// cost of the lines of every branch of an OR, empty for other gadgets
+    pub branches: Vec<Vec<LineCost>>
+}
+
+/// Estimated size of the constraint system and the proof of a statement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    pub lines: Vec<LineCost>,
+    pub total: Cost,
+    /// number of witness and derived commitments
+    pub commitments: usize,
+    /// generator capacity needed to prove or verify
+    pub generators: usize,
+    /// size of the proof in bytes
+    pub proof_size: usize
+}
+
+impl Cost {
+    fn of(operations: &[Operation]) -> Cost {
+        let mut cost = Cost::default();
+        for operation in operations {
+            match operation {
+                Operation::Multiply(_) | Operation::AllocateMultiplier(_) => cost.multipliers += 1,
+                Operation::Constrain(_) => cost.constraints += 1,
+                Operation::Commit(scalars) => cost.derived_commitments += scalars.len()
+            }
+        }
+        cost
+    }
+}
+
+/// Estimate the cost of proving the statement for the given instance, without a witness.
+///
+/// The circuit depends on the length of the witness variables, which are assumed to fit
+/// into a single scalar (32 bytes) except for the paths of a MERKLE_PATH, which span one
+/// scalar per level and one for the index. Longer witnesses, e.g. hash preimages, cost more.
+pub fn analyze(statement: &Statement, instance: String) -> Result<Analysis, GadgetError> {
+    let mut paths = Vec::new();
+    merkle_paths(statement, &mut paths);
+
+    // witnesses are one rather than zero, so that divisions in expressions stay defined
+    let witness: String = statement.vars().into_iter()
+        .filter_map(|var| {
+            let scalars = paths.iter().find(|(path, _)| *path == var).map(|(_, depth)| depth + 1).unwrap_or(1);
+            match var {
+                Var::Witness(name) => Some(format!("{} = 0x{}\n", name, format!("{}01", "00".repeat(31)).repeat(scalars))),
+                _ => None
+            }
+        })
+        .collect();
+
+    let (lines, commitments) = analyze_clauses(statement, 0, &instance, &witness)?;
+    let total = lines.iter().fold(Cost::default(), |total, line| Cost {
+        multipliers: total.multipliers + line.cost.multipliers,
+        constraints: total.constraints + line.cost.constraints,
+        derived_commitments: total.derived_commitments + line.cost.derived_commitments
+    });
+
+    // the inner product proof has two points per halving of the padded multipliers and two scalars
+    let generators = total.multipliers.next_power_of_two();
+    let proof_size = PROOF_BASE_SIZE + (generators.trailing_zeros() as usize + 1) * 2 * 32;
+
+    Ok(Analysis { lines, total, commitments, generators, proof_size })
+}
+
+/// path variables of all MERKLE_PATH gadgets, including those within branches, along with their depth
+fn merkle_paths(statement: &Statement, paths: &mut Vec<(Var, usize)>) {
+    for clause in statement.clauses() {
+        match clause {
+            Clause::MerklePath(_, _, path, depth, _) => paths.push((path.clone(), *depth)),
+            clause => {
+                for branch in clause.branches().into_iter().flatten() {
+                    merkle_paths(branch, paths);
+                }
+            }
+        }
+    }
+}
+
+/// cost of every clause of the statement starting at the given gadget index, and the number of commitments
+fn analyze_clauses(
+    statement: &Statement,
+    index: usize,
+    instance: &str,
+    witness: &str
+) -> Result<(Vec<LineCost>, usize), GadgetError> {
+    let mut assignments = Assignments::new();
+    assignments.parse_instance(String::from(instance))?;
+    assignments.label_gadgets(statement, index)?;
+    assignments.chunk_sha256_witnesses(statement)?;
+
+    // ---------- CREATE PROVER ----------
+    let mut transcript = Transcript::new(b"Analysis");
+    let pc_gens = PedersenGens::default();
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+
+    let mut commitments = String::new();
+    assignments.parse_witness(String::from(witness), &mut prover, &mut commitments)?;
+
+    // ---------- CREATE BUFFER ----------
+    let buffer_gens = PedersenGens::default();
+    let mut buffer_transcript = Transcript::new(b"BufferTranscript");
+    let buffer_prover = Prover::new(&buffer_gens, &mut buffer_transcript);
+    let mut prover_buffer = ProverBuffer::new(buffer_prover);
+    assignments.buffer_commit_wtns(&mut prover_buffer);
+    assignments.buffer_commit_drvd(&mut prover_buffer);
+
+    // ---------- GADGETS ----------
+    let mut lines = Vec::new();
+    let mut index = index;
+    for clause in statement.clauses() {
+        let start = prover_buffer.buffer().len();
+        let single = Statement::new().clause(clause.clone());
+        prove_clauses(&single, index, &mut assignments, &mut prover, &mut prover_buffer, &mut commitments, &Vec::new())?;
+        let cost = Cost::of(&prover_buffer.buffer()[start..]);
+
+        // the first branch starts after the OR, [ and { lines
+        let mut branches = Vec::new();
+        if let Some(statements) = clause.branches() {
+            let mut branch_index = index + 3;
+            for branch in statements {
+                branches.push(analyze_clauses(branch, branch_index, instance, witness)?.0);
+                branch_index += branch.lines() + 2;
+            }
+        }
+
+        lines.push(LineCost { line: index + 1, gadget: clause.op().as_str(), cost, branches });
+        index += clause.lines();
+    }
+
+    Ok((lines, commitments.lines().count()))
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>5}  {:<16} {:>11} {:>11} {:>8}", "line", "gadget", "multipliers", "constraints", "derived")?;
+        write_lines(f, &self.lines, 0)?;
+        writeln!(f, "{:>5}  {:<16} {:>11} {:>11} {:>8}", "", "total", self.total.multipliers, self.total.constraints, self.total.derived_commitments)?;
+        writeln!(f, "commitments: {}", self.commitments)?;
+        writeln!(f, "generators: {}", self.generators)?;
+        writeln!(f, "proof size: {} bytes", self.proof_size)
+    }
+}
+
+/// write the lines, branches of an OR are indented below it
+fn write_lines(f: &mut fmt::Formatter, lines: &[LineCost], depth: usize) -> fmt::Result {
+    for line in lines {
+        let gadget = format!("{}{}", "  ".repeat(depth), line.gadget);
+        writeln!(f, "{:>5}  {:<16} {:>11} {:>11} {:>8}", line.line, gadget, line.cost.multipliers, line.cost.constraints, line.cost.derived_commitments)?;
+        for branch in &line.branches {
+            write_lines(f, branch, depth + 1)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prove::prove;
+
+    #[test]
+    fn test_analyze_bounds_check() {
+        let instance = String::from(include_str!("../tests/resources/bounds_check.inst"));
+        let witness = String::from(include_str!("../tests/resources/bounds_check.wtns"));
+        let gadgets = String::from(include_str!("../tests/resources/bounds_check.gadgets"));
+
+        let analysis = analyze(&gadgets.parse().unwrap(), instance.clone()).unwrap();
+
+        // every max is 30 bytes long: two 240 bit range proofs and the sum constraint
+        let bound = Cost { multipliers: 480, constraints: 963, derived_commitments: 2 };
+        assert_eq!(analysis.lines.len(), 3);
+        assert!(analysis.lines.iter().all(|line| line.gadget == "BOUND" && line.cost == bound));
+        assert_eq!(analysis.total.multipliers, 1440);
+        assert_eq!(analysis.commitments, 9);
+        assert_eq!(analysis.generators, 2048);
+
+        let mut commitments = String::new();
+        let proof = prove("Analysis", instance, witness, gadgets, &mut commitments).unwrap();
+        assert_eq!(analysis.proof_size, proof.len());
+        assert_eq!(analysis.commitments, commitments.lines().count());
+    }
+
+    #[test]
+    fn test_analyze_or_branches() {
+        let instance = String::from(include_str!("../tests/resources/or5.inst"));
+        let statement: Statement = include_str!("../tests/resources/or5.gadgets").parse().unwrap();
+
+        let analysis = analyze(&statement, instance).unwrap();
+        let or = &analysis.lines[1];
+        assert_eq!(or.gadget, "OR");
+        assert_eq!(or.line, 2);
+        assert_eq!(or.branches.len(), 4);
+        assert_eq!(or.branches[0].iter().map(|line| line.line).collect::<Vec<usize>>(), vec![5, 6]);
+        assert_eq!(or.branches[3][1].line, 16);
+
+        // the OR replays every multiplier of its branches, adds a selector bit for every branch
+        // and multiplies every constraint of a branch with its selector
+        let branch_multipliers: usize = or.branches.iter().flatten().map(|line| line.cost.multipliers).sum();
+        let branch_constraints: usize = or.branches.iter().flatten().map(|line| line.cost.constraints).sum();
+        assert_eq!(or.cost.multipliers, branch_multipliers + 4 + branch_constraints);
+        assert_eq!(or.cost.constraints, branch_constraints + 2 * 4 + 1);
+        assert_eq!(analysis.lines[2].line, 19);
+    }
+
+    #[test]
+    fn test_analyze_merkle_path() {
+        let statement: Statement = "MERKLE_PATH[poseidon] I0 W0 W1 3".parse().unwrap();
+        let analysis = analyze(&statement, String::from("I0 = 0x01")).unwrap();
+
+        // the leaf is hashed with one permutation, every level costs a permutation, the swap and the bit
+        assert_eq!(analysis.lines[0].cost, Cost { multipliers: 243 + 3 * (243 + 2), constraints: 1 + 3 + 2, derived_commitments: 1 + 3 });
+    }
+}
@@ -0,0 +1,135 @@
+use curve25519_dalek::ristretto::CompressedRistretto;
+
+use lalrpop::assignment_parser::{read_instance, read_commitments};
+use error::GadgetError;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Public instance variables, the typed equivalent of an `.inst` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Instance {
+    vars: Vec<(String, Vec<u8>)>
+}
+
+/// Named commitments in the order they were committed to, the typed equivalent of a `.coms` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Commitments {
+    coms: Vec<(String, CompressedRistretto)>
+}
+
+/// Serialized r1cs proof, the typed equivalent of a `.proof` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Proof(pub Vec<u8>);
+
+impl Instance {
+    pub fn new(vars: Vec<(String, Vec<u8>)>) -> Instance {
+        let mut instance = Instance { vars: Vec::new() };
+        for (name, bytes) in vars {
+            instance.insert(name, bytes);
+        }
+        instance
+    }
+
+    /// set a variable, later assignments overwrite earlier ones like in an `.inst` file
+    pub fn insert(&mut self, name: String, bytes: Vec<u8>) {
+        match self.vars.binary_search_by(|(key, _)| key.cmp(&name)) {
+            Ok(index) => self.vars[index].1 = bytes,
+            Err(index) => self.vars.insert(index, (name, bytes))
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Vec<u8>> {
+        self.vars.iter().find(|(key, _)| key == name).map(|(_, bytes)| bytes)
+    }
+
+    /// variables sorted by name
+    pub fn vars(&self) -> &Vec<(String, Vec<u8>)> {
+        &self.vars
+    }
+}
+
+impl Commitments {
+    pub fn new(coms: Vec<(String, CompressedRistretto)>) -> Commitments {
+        Commitments { coms }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CompressedRistretto> {
+        self.coms.iter().find(|(key, _)| key == name).map(|(_, com)| com)
+    }
+
+    /// commitments in commit order
+    pub fn coms(&self) -> &Vec<(String, CompressedRistretto)> {
+        &self.coms
+    }
+
+    pub fn len(&self) -> usize {
+        self.coms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coms.is_empty()
+    }
+}
+
+impl FromStr for Instance {
+    type Err = GadgetError;
+
+    fn from_str(instance: &str) -> Result<Instance, GadgetError> {
+        Ok(Instance::new(read_instance(instance)?))
+    }
+}
+
+impl FromStr for Commitments {
+    type Err = GadgetError;
+
+    fn from_str(commitments: &str) -> Result<Commitments, GadgetError> {
+        Ok(Commitments::new(read_commitments(commitments)?))
+    }
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, bytes) in &self.vars {
+            writeln!(f, "{} = 0x{}", name, hex::encode(bytes))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Commitments {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, com) in &self.coms {
+            writeln!(f, "{} = 0x{}", name, hex::encode(com.as_bytes()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_round_trip() {
+        let instance: Instance = "I1 = 0x0102\nI0 = 0xff\nI1 = 0x03".parse().unwrap();
+        assert_eq!(instance.vars(), &vec![(String::from("I0"), vec![0xff]), (String::from("I1"), vec![3])]);
+        assert_eq!(instance.to_string(), "I0 = 0xff\nI1 = 0x03\n");
+        assert_eq!(instance.to_string().parse::<Instance>().unwrap(), instance);
+    }
+
+    #[test]
+    fn test_commitments_keep_order() {
+        let com = hex::encode(CompressedRistretto::default().as_bytes());
+        let text = format!("C1-0 = 0x{}\nC0-0 = 0x{}\n", com, com);
+        let commitments: Commitments = text.parse().unwrap();
+        assert_eq!(commitments.coms()[0].0, "C1-0");
+        assert!(commitments.get("C0-0").is_some());
+        assert_eq!(commitments.to_string(), text);
+
+        assert_eq!(
+            "C0-0 = 0x0102".parse::<Commitments>(),
+            Err(GadgetError::MalformedCommitment(String::from("C0-0")))
+        );
+    }
+}
@@ -4,27 +4,121 @@ use std::io::prelude::*;
 use std::env;
 use std::fs::read_to_string;
 use std::fs::File;
+use std::path::Path;
+use std::process;
 
-use bulletproofs_gadgets::prove::prove;
+use bulletproofs_gadgets::prove::{prove_statement, prove_statement_bundle, check_witness};
+use bulletproofs_gadgets::statement::Statement;
+use bulletproofs_gadgets::lalrpop::statement_parser::parse_statement_in;
+use bulletproofs_gadgets::analysis::analyze;
+use bulletproofs_gadgets::validation::{validate, witness_shape};
+use bulletproofs_gadgets::error::GadgetError;
+use bulletproofs_gadgets::bundle::ProofBundle;
+#[cfg(feature = "serialization")]
+use bulletproofs_gadgets::serialization::Encoding;
 
 const INSTANCE_VARS_EXT: &str = ".inst";
 const WITNESS_VARS_EXT: &str = ".wtns";
 const COMMITMENTS_EXT: &str = ".coms";
 const GADGETS_EXT: &str = ".gadgets";
 const PROOF_EXT: &str = ".proof";
+const BUNDLE_EXT: &str = ".bundle";
+const BUNDLE_FLAG: &str = "--bundle";
+const CHECK_FLAG: &str = "--check";
+const STATS_FLAG: &str = "--stats";
+const LINT_FLAG: &str = "--lint";
+const FORMAT_OPTION: &str = "--format";
 
 fn main() -> std::io::Result<()> {
     let filename = Box::leak(env::args().nth(1).expect("missing argument").into_boxed_str());
+    let format = env::args().skip_while(|arg| arg != FORMAT_OPTION).nth(1);
+    let bundle = format.is_some() || env::args().skip(2).any(|arg| arg == BUNDLE_FLAG);
+    let check = env::args().skip(2).any(|arg| arg == CHECK_FLAG);
+    let stats = env::args().skip(2).any(|arg| arg == STATS_FLAG);
+    let lint = env::args().skip(2).any(|arg| arg == LINT_FLAG);
     let instance = read_to_string(format!("{}{}", filename, INSTANCE_VARS_EXT)).expect("unable to read instance file");
-    let witness = read_to_string(format!("{}{}", filename, WITNESS_VARS_EXT)).expect("unable to read instance file");
     let gadgets = read_to_string(format!("{}{}", filename, GADGETS_EXT)).expect("unable to read instance file");
+
+    // included files are relative to the gadgets file
+    let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+    let statement: Statement = parse_statement_in(&gadgets, directory).unwrap_or_else(exit);
+
+    // the cost of a statement can be estimated without a witness
+    if stats {
+        print!("{}", analyze(&statement, instance).unwrap_or_else(exit));
+        return Ok(());
+    }
+
+    let witness = read_to_string(format!("{}{}", filename, WITNESS_VARS_EXT)).expect("unable to read instance file");
+
+    // only the length of the witness variables is checked
+    if lint {
+        let shape = witness_shape(&witness).unwrap_or_else(exit);
+        let diagnostics = validate(&statement, &instance, &shape).unwrap_or_else(exit);
+        if diagnostics.is_empty() {
+            println!("no problems found");
+            return Ok(());
+        }
+        for diagnostic in diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        process::exit(1);
+    }
+
+    if check {
+        match check_witness(&statement, instance, witness).unwrap_or_else(exit) {
+            Some(unsatisfied) => {
+                eprintln!("{}", unsatisfied);
+                process::exit(1);
+            },
+            None => println!("witness satisfies all constraints")
+        }
+        return Ok(());
+    }
+
+    if bundle {
+        let bundle = prove_statement_bundle(filename, instance, witness, &statement).unwrap_or_else(exit);
+        let (extension, bytes) = encode_bundle(&bundle, format).unwrap_or_else(exit);
+        let mut bundle_file = File::create(format!("{}{}{}", filename, BUNDLE_EXT, extension))?;
+        bundle_file.write_all(&bytes)?;
+        return Ok(());
+    }
+
     let mut commitments = String::new();
+    let proof = prove_statement(filename, instance, witness, &statement, &mut commitments).unwrap_or_else(exit);
+
     let mut commitments_file = File::create(format!("{}{}", filename, COMMITMENTS_EXT))?;
     let mut proof_file = File::create(format!("{}{}", filename, PROOF_EXT))?;
-
-    let proof = prove(filename, instance, witness, gadgets, &mut commitments).expect("unable to generate proof from provided files");
     commitments_file.write_all(&commitments.as_bytes())?;
     proof_file.write_all(&proof)?;
 
     Ok(())
 }
+
+/// encode the bundle in its binary format or the given serialization format
+#[cfg(feature = "serialization")]
+fn encode_bundle(bundle: &ProofBundle, format: Option<String>) -> Result<(&'static str, Vec<u8>), GadgetError> {
+    match format {
+        Some(format) => {
+            let encoding: Encoding = format.parse()?;
+            Ok((encoding.extension(), encoding.encode(bundle)?))
+        },
+        None => Ok(("", bundle.to_bytes()))
+    }
+}
+
+#[cfg(not(feature = "serialization"))]
+fn encode_bundle(bundle: &ProofBundle, format: Option<String>) -> Result<(&'static str, Vec<u8>), GadgetError> {
+    match format {
+        Some(_) => Err(GadgetError::Serialization(String::from("built without the serialization feature"))),
+        None => Ok(("", bundle.to_bytes()))
+    }
+}
+
+/// print the error, a parse error points at the offending token in its file
+fn exit<T>(error: GadgetError) -> T {
+    let filename = env::args().nth(1).unwrap_or_default();
+    eprintln!("unable to generate proof from provided files");
+    eprint!("{}", error.render(&filename));
+    process::exit(1);
+}
@@ -1,25 +1,76 @@
 extern crate bulletproofs_gadgets;
 
-use bulletproofs_gadgets::verify::verify;
+use bulletproofs_gadgets::verify::{verify_statement, verify_statement_bundle};
+use bulletproofs_gadgets::bundle::ProofBundle;
+use bulletproofs_gadgets::lalrpop::statement_parser::parse_statement_in;
+use bulletproofs_gadgets::error::GadgetError;
+#[cfg(feature = "serialization")]
+use bulletproofs_gadgets::serialization::Encoding;
 
 use std::env;
 use std::fs::read;
 use std::fs::read_to_string;
+use std::path::Path;
+use std::process;
 
 const INSTANCE_VARS_EXT: &str = ".inst";
 const COMMITMENTS_EXT: &str = ".coms";
 const GADGETS_EXT: &str = ".gadgets";
 const PROOF_EXT: &str = ".proof";
+const BUNDLE_EXT: &str = ".bundle";
+const BUNDLE_FLAG: &str = "--bundle";
+const FORMAT_OPTION: &str = "--format";
 
 fn main() -> std::io::Result<()> {
-    let filename = Box::leak(env::args().nth(1).expect("missing argument").into_boxed_str());
+    let filename: &'static str = Box::leak(env::args().nth(1).expect("missing argument").into_boxed_str());
+    let format = env::args().skip_while(|arg| arg != FORMAT_OPTION).nth(1);
+    let bundle = format.is_some() || env::args().skip(2).any(|arg| arg == BUNDLE_FLAG);
     let instance = read_to_string(format!("{}{}", filename, INSTANCE_VARS_EXT)).expect("unable to read instance file");
-    let commitments = read_to_string(format!("{}{}", filename, COMMITMENTS_EXT)).expect("unable to read commitments file");
-    let proof = read(format!("{}{}", filename, PROOF_EXT)).expect("unable to read proof file");
     let gadgets = read_to_string(format!("{}{}", filename, GADGETS_EXT)).expect("unable to read gadgets file");
 
-    let verified = verify(filename, instance, proof, commitments, gadgets).expect("unable to verify provided files");
+    // included files are relative to the gadgets file
+    let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+    let statement = parse_statement_in(&gadgets, directory).unwrap_or_else(exit);
+
+    let verified = if bundle {
+        decode_bundle(filename, format)
+            .and_then(|bundle| verify_statement_bundle(filename, instance, &bundle, &statement))
+            .unwrap_or_else(exit)
+    } else {
+        let commitments = read_to_string(format!("{}{}", filename, COMMITMENTS_EXT)).expect("unable to read commitments file");
+        let proof = read(format!("{}{}", filename, PROOF_EXT)).expect("unable to read proof file");
+        verify_statement(filename, instance, proof, commitments, &statement).unwrap_or_else(exit)
+    };
 
     println!("{}", verified);
     Ok(())
 }
+
+/// read the bundle in its binary format or the given serialization format
+#[cfg(feature = "serialization")]
+fn decode_bundle(filename: &str, format: Option<String>) -> Result<ProofBundle, GadgetError> {
+    match format {
+        Some(format) => {
+            let encoding: Encoding = format.parse()?;
+            let bytes = read(format!("{}{}{}", filename, BUNDLE_EXT, encoding.extension())).expect("unable to read bundle file");
+            encoding.decode(&bytes)
+        },
+        None => ProofBundle::from_bytes(&read(format!("{}{}", filename, BUNDLE_EXT)).expect("unable to read bundle file"))
+    }
+}
+
+#[cfg(not(feature = "serialization"))]
+fn decode_bundle(filename: &str, format: Option<String>) -> Result<ProofBundle, GadgetError> {
+    match format {
+        Some(_) => Err(GadgetError::Serialization(String::from("built without the serialization feature"))),
+        None => ProofBundle::from_bytes(&read(format!("{}{}", filename, BUNDLE_EXT)).expect("unable to read bundle file"))
+    }
+}
+
+/// print the error, a parse error points at the offending token in its file
+fn exit<T>(error: GadgetError) -> T {
+    let filename = env::args().nth(1).unwrap_or_default();
+    eprintln!("unable to verify provided files");
+    eprint!("{}", error.render(&filename));
+    process::exit(1);
+}
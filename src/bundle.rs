@@ -0,0 +1,257 @@
+use curve25519_dalek::ristretto::CompressedRistretto;
+use merlin::Transcript;
+
+use statement::Statement;
+use artifacts::Commitments;
+use lalrpop::assignment_parser::Assignments;
+use error::GadgetError;
+
+#[cfg(feature = "serialization")]
+use serde::{Serialize, Deserialize};
+
+/// Identifies a proof bundle file.
+pub const MAGIC: [u8; 4] = *b"BPGB";
+
+/// Version of the binary layout written by `ProofBundle::to_bytes`.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// A proof together with everything needed to check it against a statement.
+///
+/// Layout (integers little endian, strings and byte arrays prefixed with their length):
+/// magic, format version (u16), library version (u16 length), transcript label (u16 length),
+/// generator capacity (u64), statement hash (32 bytes), instance hash (32 bytes),
+/// commitments (u32 count, each u16 length name and 32 bytes), proof (u32 length).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct ProofBundle {
+    /// version of the library that created the proof
+    pub version: String,
+    /// label the transcript was created with
+    pub label: String,
+    /// number of bulletproof generators needed to verify the proof
+    pub generators: usize,
+    #[cfg_attr(feature = "serialization", serde(with = "::serialization::hash"))]
+    pub statement_hash: [u8; 32],
+    #[cfg_attr(feature = "serialization", serde(with = "::serialization::hash"))]
+    pub instance_hash: [u8; 32],
+    /// named commitments, in the order they were committed to
+    pub commitments: Commitments,
+    #[cfg_attr(feature = "serialization", serde(with = "::serialization::bytes"))]
+    pub proof: Vec<u8>
+}
+
+impl ProofBundle {
+    pub fn get_commitment(&self, name: &str) -> Option<&CompressedRistretto> {
+        self.commitments.get(name)
+    }
+
+    /// check that the bundle was created for the given label, statement and instance
+    pub fn check(&self, label: &str, statement: &Statement, instance: &str) -> Result<(), GadgetError> {
+        if self.label != label {
+            return Err(GadgetError::BundleMismatch(format!("bundle was created for label {}", self.label)));
+        }
+        if self.statement_hash != statement_hash(statement) {
+            return Err(GadgetError::BundleMismatch(String::from("statement hash differs")));
+        }
+        if self.instance_hash != instance_hash(instance)? {
+            return Err(GadgetError::BundleMismatch(String::from("instance hash differs")));
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        write_short(&mut bytes, self.version.as_bytes());
+        write_short(&mut bytes, self.label.as_bytes());
+        bytes.extend_from_slice(&(self.generators as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.statement_hash);
+        bytes.extend_from_slice(&self.instance_hash);
+        bytes.extend_from_slice(&(self.commitments.len() as u32).to_le_bytes());
+        for (name, com) in self.commitments.coms() {
+            write_short(&mut bytes, name.as_bytes());
+            bytes.extend_from_slice(com.as_bytes());
+        }
+        bytes.extend_from_slice(&(self.proof.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.proof);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<ProofBundle, GadgetError> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(4)? != MAGIC {
+            return Err(GadgetError::BundleDecoding(String::from("missing magic bytes")));
+        }
+        let format_version = reader.read_u16()?;
+        if format_version != FORMAT_VERSION {
+            return Err(GadgetError::BundleDecoding(format!("unsupported format version {}", format_version)));
+        }
+
+        let version = reader.read_string()?;
+        let label = reader.read_string()?;
+        let generators = reader.read_u64()? as usize;
+        let statement_hash = slice_to_array!(reader.take(32)?, 32);
+        let instance_hash = slice_to_array!(reader.take(32)?, 32);
+
+        let mut coms = Vec::new();
+        for _ in 0..reader.read_u32()? {
+            let name = reader.read_string()?;
+            let com = CompressedRistretto::from_slice(reader.take(32)?);
+            coms.push((name, com));
+        }
+
+        let proof_len = reader.read_u32()? as usize;
+        let proof = reader.take(proof_len)?.to_vec();
+        if !reader.bytes.is_empty() {
+            return Err(GadgetError::BundleDecoding(String::from("trailing bytes")));
+        }
+
+        Ok(ProofBundle {
+            version,
+            label,
+            generators,
+            statement_hash,
+            instance_hash,
+            commitments: Commitments::new(coms),
+            proof
+        })
+    }
+}
+
+/// hash of the normalized text representation of the statement
+pub fn statement_hash(statement: &Statement) -> [u8; 32] {
+    let mut transcript = Transcript::new(b"StatementHash");
+    transcript.append_message(b"statement", statement.to_string().as_bytes());
+
+    let mut hash = [0u8; 32];
+    transcript.challenge_bytes(b"hash", &mut hash);
+    hash
+}
+
+/// hash of the instance variables, independent of their order and formatting
+pub fn instance_hash(instance: &str) -> Result<[u8; 32], GadgetError> {
+    let mut assignments = Assignments::new();
+    assignments.parse_instance(String::from(instance))?;
+
+    let mut transcript = Transcript::new(b"InstanceHash");
+    for (name, bytes) in assignments.get_instance_vars() {
+        transcript.append_message(b"name", name.as_bytes());
+        transcript.append_message(b"value", &bytes);
+    }
+
+    let mut hash = [0u8; 32];
+    transcript.challenge_bytes(b"hash", &mut hash);
+    Ok(hash)
+}
+
+fn write_short(bytes: &mut Vec<u8>, value: &[u8]) {
+    bytes.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(value);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], GadgetError> {
+        if self.bytes.len() < len {
+            return Err(GadgetError::BundleDecoding(String::from("unexpected end of bundle")));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, GadgetError> {
+        Ok(u16::from_le_bytes(slice_to_array!(self.take(2)?, 2)))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, GadgetError> {
+        Ok(u32::from_le_bytes(slice_to_array!(self.take(4)?, 4)))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, GadgetError> {
+        Ok(u64::from_le_bytes(slice_to_array!(self.take(8)?, 8)))
+    }
+
+    fn read_string(&mut self) -> Result<String, GadgetError> {
+        let len = self.read_u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| GadgetError::BundleDecoding(String::from("invalid utf-8 string")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prove::prove_bundle;
+    use verify::verify_bundle;
+
+    const INSTANCE: &str = include_str!("../tests/resources/equality.inst");
+    const WITNESS: &str = include_str!("../tests/resources/equality.wtns");
+    const GADGETS: &str = include_str!("../tests/resources/equality.gadgets");
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let bundle = prove_bundle("ProofBundle", String::from(INSTANCE), String::from(WITNESS), String::from(GADGETS)).unwrap();
+        assert_eq!(bundle.version, env!("CARGO_PKG_VERSION"));
+        assert!(bundle.get_commitment("C0-0").is_some());
+
+        let decoded = ProofBundle::from_bytes(&bundle.to_bytes()).unwrap();
+        assert_eq!(decoded, bundle);
+        assert!(verify_bundle("ProofBundle", String::from(INSTANCE), &decoded, String::from(GADGETS)).unwrap());
+    }
+
+    #[test]
+    fn test_bundle_mismatch() {
+        let bundle = prove_bundle("ProofBundle", String::from(INSTANCE), String::from(WITNESS), String::from(GADGETS)).unwrap();
+        let instance = format!("{}I9 = 0x01\n", INSTANCE);
+        match verify_bundle("ProofBundle", instance, &bundle, String::from(GADGETS)) {
+            Err(GadgetError::BundleMismatch(_)) => (),
+            _ => panic!("expected bundle mismatch")
+        }
+        match verify_bundle("OtherLabel", String::from(INSTANCE), &bundle, String::from(GADGETS)) {
+            Err(GadgetError::BundleMismatch(_)) => (),
+            _ => panic!("expected bundle mismatch")
+        }
+
+        let mut generators = bundle.clone();
+        generators.generators *= 2;
+        match verify_bundle("ProofBundle", String::from(INSTANCE), &generators, String::from(GADGETS)) {
+            Err(GadgetError::BundleMismatch(_)) => (),
+            _ => panic!("expected bundle mismatch")
+        }
+    }
+
+    #[test]
+    fn test_bundle_decoding_errors() {
+        let bundle = ProofBundle {
+            version: String::from("0.2.0"),
+            label: String::from("label"),
+            generators: 16,
+            statement_hash: [1u8; 32],
+            instance_hash: [2u8; 32],
+            commitments: Commitments::default(),
+            proof: vec![3u8; 10]
+        };
+        let bytes = bundle.to_bytes();
+        assert_eq!(ProofBundle::from_bytes(&bytes).unwrap(), bundle);
+
+        assert!(ProofBundle::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(ProofBundle::from_bytes(&[&bytes[..], &[0u8]].concat()).is_err());
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = 0;
+        assert!(ProofBundle::from_bytes(&wrong_magic).is_err());
+
+        let mut wrong_version = bytes;
+        wrong_version[4] = 2;
+        assert_eq!(
+            ProofBundle::from_bytes(&wrong_version),
+            Err(GadgetError::BundleDecoding(String::from("unsupported format version 2")))
+        );
+    }
+}
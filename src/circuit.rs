@@ -0,0 +1,162 @@
+use statement::Statement;
+use lalrpop::ast::Var;
+use prove::prove_prepared;
+use verify::verify_prepared;
+use error::GadgetError;
+use generators::shared_generators;
+use lalrpop::assignment_parser::Assignments;
+
+use bulletproofs::BulletproofGens;
+
+use std::sync::{Arc, Mutex};
+
+/// Shape of the constraint system created for a statement.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layout {
+    /// commitment names in the order they are committed to
+    pub commitments: Vec<String>,
+    /// number of multipliers (bulletproof variables)
+    pub multipliers: usize,
+    /// generator capacity needed to prove or verify
+    pub generators: usize
+}
+
+/// A statement parsed once and reused for any number of proofs and verifications.
+///
+/// The labels of the gadgets and the witnesses committed in chunks are resolved when the circuit
+/// is compiled. The layout depends on the length of the assigned values (a variable longer than
+/// 32 bytes is committed to in multiple parts), it is therefore returned by every proof and the
+/// circuit keeps the layout of the latest one. Its generators are reused by later proofs and
+/// verifications that fit into them, larger ones are taken from the process-wide cache. The prover,
+/// the verifier and their buffers are bound to the transcript of a single proof and are created per call.
+pub struct CompiledCircuit {
+    name: &'static str,
+    statement: Statement,
+    instance_vars: Vec<Var>,
+    witness_vars: Vec<Var>,
+    assignments: Assignments,
+    /// layout of the latest proof or successful verification along with its generators
+    layout: Mutex<Option<(Layout, Arc<BulletproofGens>)>>
+}
+
+impl CompiledCircuit {
+    pub fn new(name: &'static str, statement: Statement) -> Result<CompiledCircuit, GadgetError> {
+        let (instance_vars, witness_vars) = statement.vars().into_iter()
+            .partition(|var| match var {
+                Var::Instance(_) => true,
+                _ => false
+            });
+        let assignments = Assignments::for_statement(&statement)?;
+
+        Ok(CompiledCircuit {
+            name,
+            statement,
+            instance_vars,
+            witness_vars,
+            assignments,
+            layout: Mutex::new(None)
+        })
+    }
+
+    /// parse the gadgets into a circuit
+    pub fn compile(name: &'static str, gadgets: &str) -> Result<CompiledCircuit, GadgetError> {
+        CompiledCircuit::new(name, gadgets.parse()?)
+    }
+
+    pub fn statement(&self) -> &Statement {
+        &self.statement
+    }
+
+    /// instance variables the statement refers to, sorted by name
+    pub fn instance_vars(&self) -> &Vec<Var> {
+        &self.instance_vars
+    }
+
+    /// witness variables the statement refers to, sorted by name
+    pub fn witness_vars(&self) -> &Vec<Var> {
+        &self.witness_vars
+    }
+
+    /// layout of the latest proof or successful verification, none before the first one
+    pub fn layout(&self) -> Option<Layout> {
+        self.layout.lock().unwrap().as_ref().map(|(layout, _)| layout.clone())
+    }
+
+    /// prove the statement, the commitments are written to the given string
+    ///
+    /// Returns the proof along with the layout of the constraint system it was created for.
+    pub fn prove(&self, instance: String, witness: String, commitments: &mut String) -> Result<(Vec<u8>, Layout), GadgetError> {
+        let generators = self.generators();
+        let (proof, layout) = prove_prepared(self.name, self.assignments.clone(), instance, witness, &self.statement, commitments, &|capacity| generators.get(capacity))?;
+        generators.record(self, layout.clone());
+        Ok((proof, layout))
+    }
+
+    pub fn verify(&self, instance: String, commitments: String, proof: Vec<u8>) -> Result<bool, GadgetError> {
+        let generators = self.generators();
+        let (verified, layout) = verify_prepared(self.name, self.assignments.clone(), instance, proof, commitments, &self.statement, &|capacity| generators.get(capacity))?;
+        if verified {
+            generators.record(self, layout);
+        }
+        Ok(verified)
+    }
+
+    fn generators(&self) -> Selection {
+        Selection { recorded: self.layout.lock().unwrap().as_ref().map(|(_, gens)| gens.clone()), used: Mutex::new(None) }
+    }
+}
+
+/// Generators of a single proof or verification, those of the recorded layout if they are large enough.
+struct Selection {
+    recorded: Option<Arc<BulletproofGens>>,
+    used: Mutex<Option<Arc<BulletproofGens>>>
+}
+
+impl Selection {
+    fn get(&self, capacity: usize) -> Arc<BulletproofGens> {
+        let gens = match &self.recorded {
+            Some(gens) if gens.gens_capacity >= capacity => gens.clone(),
+            _ => shared_generators(capacity)
+        };
+        *self.used.lock().unwrap() = Some(gens.clone());
+        gens
+    }
+
+    fn record(self, circuit: &CompiledCircuit, layout: Layout) {
+        if let Some(gens) = self.used.into_inner().unwrap() {
+            *circuit.layout.lock().unwrap() = Some((layout, gens));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compiled_circuit() {
+        let instance = String::from(include_str!("../tests/resources/or5.inst"));
+        let witness = String::from(include_str!("../tests/resources/or5.wtns"));
+        let gadgets = include_str!("../tests/resources/or5.gadgets");
+
+        let circuit = CompiledCircuit::compile("CompiledCircuit", gadgets).unwrap();
+        assert_eq!(circuit.instance_vars().len(), 4);
+        assert_eq!(circuit.witness_vars().len(), 5);
+
+        assert_eq!(circuit.layout(), None);
+
+        let mut commitments = String::new();
+        let (proof, layout) = circuit.prove(instance.clone(), witness.clone(), &mut commitments).unwrap();
+        assert_eq!(layout.commitments.len(), commitments.lines().count());
+        assert_eq!(layout.generators, layout.multipliers.next_power_of_two());
+        assert_eq!(circuit.layout(), Some(layout.clone()));
+
+        let recorded = circuit.generators().recorded.unwrap();
+        for _ in 0..2 {
+            assert!(circuit.verify(instance.clone(), commitments.clone(), proof.clone()).unwrap());
+            assert_eq!(circuit.layout(), Some(layout.clone()));
+            assert!(Arc::ptr_eq(&circuit.generators().recorded.unwrap(), &recorded));
+        }
+        assert!(!circuit.verify(instance, commitments, proof[1..].to_vec()).unwrap_or(false));
+    }
+}
@@ -43,6 +43,18 @@ pub fn commit(prover: &mut Prover<&mut Transcript>, witness: &Vec<u8>) -> (Vec<S
     (scalars, commitments, variables)
 }
 
+/// Commit to a variable length witness in chunks of at most `size` bytes aligned to its end, the last chunk first
+pub fn commit_chunks(prover: &mut Prover<&mut Transcript>, witness: &Vec<u8>, size: usize) -> (Vec<Scalar>, Vec<CompressedRistretto>, Vec<Variable>) {
+    let scalars: Vec<Scalar> = witness.rchunks(size).map(|chunk| be_to_scalar(&chunk.to_vec())).collect();
+
+    let (commitments, variables) = scalars
+        .iter()
+        .map(|scalar| prover.commit(*scalar, Scalar::random(&mut thread_rng())))
+        .unzip();
+
+    (scalars, commitments, variables)
+}
+
 pub fn verifier_commit(verifier: &mut Verifier<&mut Transcript>, commitments: Vec<CompressedRistretto>) -> Vec<Variable> {
     commitments.iter().map(|commitment| verifier.commit(*commitment)).collect()
 }
@@ -19,10 +19,17 @@ pub trait ConstraintSystemBuffer {
     fn buffer_cache(&self) -> &Vec<Vec<Operation>>;
 }
 
+/// Records the operations of the prover and evaluates every constraint on the assignments as it is added.
 pub struct ProverBuffer<'g> {
     prover: Prover<'g, &'g mut Transcript>,
     operation_buffer: Vec<Operation>,
-    cached_buffers: Vec<Vec<Operation>>
+    cached_buffers: Vec<Vec<Operation>>,
+    /// assignments of the committed variables and of the left, right and output variables of the multipliers
+    committed: Vec<Scalar>,
+    multipliers: Vec<(Scalar, Scalar, Scalar)>,
+    /// whether each constraint since the last rewind holds
+    satisfied: Vec<bool>,
+    cached_satisfied: Vec<Vec<bool>>
 }
 
 impl<'g> ProverBuffer<'g> {
@@ -30,16 +37,48 @@ impl<'g> ProverBuffer<'g> {
         ProverBuffer {
             prover: prover,
             operation_buffer: Vec::new(),
-            cached_buffers: Vec::new()
+            cached_buffers: Vec::new(),
+            committed: Vec::new(),
+            multipliers: Vec::new(),
+            satisfied: Vec::new(),
+            cached_satisfied: Vec::new()
         }
     }
 
     pub fn commit(&mut self, witnesses: &Vec<Scalar>) {
         for scalar in witnesses {
             self.prover.commit(*scalar, Scalar::zero());
+            self.committed.push(*scalar);
         }
     }
 
+    /// value of the linear combination for the assignments of the buffer
+    pub fn evaluate(&self, lc: &LinearCombination) -> Scalar {
+        lc.clone().get_terms().into_iter()
+            .map(|(var, coefficient)| coefficient * match var {
+                Variable::Committed(i) => self.committed[i],
+                Variable::MultiplierLeft(i) => self.multipliers[i].0,
+                Variable::MultiplierRight(i) => self.multipliers[i].1,
+                Variable::MultiplierOutput(i) => self.multipliers[i].2,
+                Variable::One() => Scalar::one()
+            })
+            .sum()
+    }
+
+    /// whether each constraint added since the last rewind holds
+    pub fn satisfied(&self) -> &Vec<bool> {
+        &self.satisfied
+    }
+
+    /// whether all constraints of each rewound branch hold
+    pub fn branches_hold(&self) -> Vec<bool> {
+        self.cached_satisfied.iter().map(|satisfied| satisfied.iter().all(|holds| *holds)).collect()
+    }
+
+    fn assign_multiplier(&mut self, left: Scalar, right: Scalar) {
+        self.multipliers.push((left, right, left * right));
+    }
+
     pub fn commit_drvd(&mut self, derived_witnesses: &Vec<(Option<Scalar>, Variable)>) {
         let scalars = derived_witnesses.into_iter().map(|derived| derived.0.unwrap()).collect();
         self.commit(&scalars);
@@ -54,9 +93,13 @@ impl<'g> ProverBuffer<'g> {
             for operation in operations {
                 match operation {
                     Operation::Multiply((left, right)) => {
+                        let (l, r) = (self.evaluate(&left), self.evaluate(&right));
+                        self.assign_multiplier(l, r);
                         self.prover.multiply(left.clone(), right.clone());
                     },
                     Operation::AllocateMultiplier(assignment) => {
+                        let (l, r) = assignment.expect("the prover assigns every multiplier");
+                        self.assign_multiplier(l, r);
                         assert!(self.prover.allocate_multiplier(assignment.clone()).is_ok());
                     },
                     Operation::Constrain(lc) => {
@@ -75,6 +118,8 @@ impl<'g> ConstraintSystemBuffer for ProverBuffer<'g> {
     fn rewind(&mut self) {
         self.cached_buffers.push(self.operation_buffer.clone());
         self.operation_buffer = Vec::new();
+        self.cached_satisfied.push(self.satisfied.clone());
+        self.satisfied = Vec::new();
     }
 
     fn buffer(&self) -> &Vec<Operation> {
@@ -92,6 +137,8 @@ impl<'g> ConstraintSystem for ProverBuffer<'g> {
     }
 
     fn multiply(&mut self, left: LinearCombination, right: LinearCombination) -> (Variable, Variable, Variable) {
+        let (l, r) = (self.evaluate(&left), self.evaluate(&right));
+        self.assign_multiplier(l, r);
         self.operation_buffer.push(Operation::Multiply((left.clone(), right.clone())));
         self.prover.multiply(left, right)
     }
@@ -102,6 +149,7 @@ impl<'g> ConstraintSystem for ProverBuffer<'g> {
 
     fn allocate_multiplier(&mut self, input_assignments: Option<(Scalar, Scalar)>) -> Result<(Variable, Variable, Variable), R1CSError> {
         let (l, r) = input_assignments.ok_or(R1CSError::MissingAssignment)?;
+        self.assign_multiplier(l, r);
         self.operation_buffer.push(Operation::AllocateMultiplier(Some((l.clone(), r.clone()))));
         self.prover.allocate_multiplier(Some((l, r)))
     }
@@ -110,6 +158,8 @@ impl<'g> ConstraintSystem for ProverBuffer<'g> {
     }
 
     fn constrain(&mut self, lc: LinearCombination) {
+        let holds = self.evaluate(&lc) == Scalar::zero();
+        self.satisfied.push(holds);
         self.operation_buffer.push(Operation::Constrain(lc.clone()));
         self.prover.constrain(lc);
     }
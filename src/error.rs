@@ -0,0 +1,261 @@
+use lalrpop_util::ParseError;
+
+use std::error::Error;
+use std::fmt;
+
+/// Error raised by grammar actions: byte offset within the line and a message.
+pub type GrammarError = (usize, &'static str);
+
+/// The kind of text a parse error occurred in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    Gadgets,
+    Instance,
+    Witness,
+    Commitments
+}
+
+/// Source line, offending token and expectations of a parse error, see `GadgetError::render`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseContext {
+    pub input: Input,
+    /// text of the line the error occurred in
+    pub source: String,
+    /// the offending token, none at the end of a line
+    pub token: Option<String>,
+    /// tokens that would have been accepted instead
+    pub expected: Vec<String>,
+    /// likely cause of a common mistake
+    pub hint: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GadgetError {
+    /// a line could not be parsed (line and column are 1-based)
+    Parse { line: usize, column: usize, message: String, context: Box<ParseContext> },
+    /// the first token of a gadgets line is not a known gadget
+    UnknownGadget { line: usize, op: String },
+    /// a referenced instance or witness variable has no assignment
+    MissingVariable(String),
+    /// a commitment required by the verifier was not provided
+    MissingCommitment(String),
+    /// a variable was used where a different kind of variable is expected
+    InvalidVariableType(String),
+    /// an assignment exceeds the size the gadget allows
+    SizeViolation { name: String, max: usize, actual: usize },
+    /// two compared variables span a different number of scalars
+    SizeMismatch(String, String),
+    /// an authentication path does not hold an index and one sibling per level
+    PathLength { name: String, depth: usize, actual: usize },
+    /// a variable committed in chunks for SHA256 is used by another gadget
+    ChunkConflict(String),
+    /// the leaves of a merkle tree or a position within it are not valid
+    InvalidTree(String),
+    /// a variable is assigned more than once
+    DuplicateVariable(String),
+    /// a commitment is not a valid compressed ristretto point
+    MalformedCommitment(String),
+    /// the proof bytes could not be decoded
+    ProofDecoding(String),
+    /// the constraint system could not be proven
+    ProofCreation(String),
+    /// an OR block is not closed before the end of the gadgets
+    UnexpectedEndOfInput,
+    /// the proof bundle is not in a supported format
+    BundleDecoding(String),
+    /// the proof bundle was created for a different statement, instance or label
+    BundleMismatch(String),
+    /// an artifact could not be encoded or decoded
+    Serialization(String),
+    /// two gadgets of a statement have the same label
+    DuplicateLabel(String),
+    /// a statement is not valid, e.g. a label on an OR or an INCLUDE without a directory
+    InvalidStatement(String),
+    /// an expression can not be evaluated for the given assignments, e.g. a division by zero
+    InvalidExpression(String),
+    /// a file included by the gadgets could not be parsed
+    Include { path: String, error: Box<GadgetError> }
+}
+
+impl GadgetError {
+    /// convert a lalrpop error for the given (0-based) line index, locations are byte offsets within the line
+    pub fn from_parse_error<T: fmt::Display>(
+        input: Input,
+        index: usize,
+        source: &str,
+        error: ParseError<usize, T, GrammarError>
+    ) -> GadgetError {
+        let (location, token, expected, message) = match error {
+            ParseError::InvalidToken { location } =>
+                (location, word_at(source, location), Vec::new(), String::from("invalid token")),
+            ParseError::UnrecognizedEOF { location, expected } => {
+                let message = format!("unexpected end of line, expected one of {}", expected.join(", "));
+                (location, None, expected, message)
+            },
+            ParseError::UnrecognizedToken { token: (location, token, _), expected } => {
+                let message = format!("unexpected token {}, expected one of {}", token, expected.join(", "));
+                (location, Some(token.to_string()), expected, message)
+            },
+            ParseError::ExtraToken { token: (location, token, _) } =>
+                (location, Some(token.to_string()), Vec::new(), format!("extra token {}", token)),
+            ParseError::User { error: (location, message) } =>
+                (location, word_at(source, location), Vec::new(), String::from(message))
+        };
+
+        let context = ParseContext { input, source: String::from(source), token, expected, hint: None };
+        GadgetError::parse(index, location, message, context)
+    }
+
+    /// parse error at a byte offset within the line with the given (0-based) index, common mistakes get a hint
+    pub fn parse(index: usize, location: usize, message: String, context: ParseContext) -> GadgetError {
+        let hint = hint(&context, location, &message);
+        GadgetError::Parse { line: index + 1, column: location + 1, message, context: Box::new(ParseContext { hint, ..context }) }
+    }
+
+    /// Render the error like a compiler diagnostic, pointing at the offending token of a parse error.
+    ///
+    /// `basename` is the path of the input files without their extension, e.g. `example`.
+    pub fn render(&self, basename: &str) -> String {
+        self.render_in(&|input: Input| format!("{}{}", basename, input.extension()))
+    }
+
+    fn render_in(&self, file: &dyn Fn(Input) -> String) -> String {
+        match self {
+            GadgetError::Parse { line, column, message, context } => {
+                let number = line.to_string();
+                let gutter = " ".repeat(number.len());
+                let width = context.token.as_ref().map_or(1, |token| token.chars().count().max(1));
+
+                let mut rendered = format!("error: {}\n", message);
+                rendered.push_str(&format!("{}--> {}:{}:{}\n", gutter, file(context.input), line, column));
+                rendered.push_str(&format!("{} |\n{} | {}\n", gutter, number, context.source));
+                rendered.push_str(&format!("{} | {}{}\n", gutter, " ".repeat(column - 1), "^".repeat(width)));
+                if let Some(hint) = &context.hint {
+                    rendered.push_str(&format!("{} = hint: {}\n", gutter, hint));
+                }
+                rendered
+            },
+            GadgetError::UnknownGadget { line, op } => {
+                let number = line.to_string();
+                format!("error: unknown gadget {}\n{}--> {}:{}\n", op, " ".repeat(number.len()), file(Input::Gadgets), line)
+            },
+            // the included file takes the place of the gadgets
+            GadgetError::Include { path, error } => error.render_in(&|_| path.clone()),
+            error => format!("error: {}\n", error)
+        }
+    }
+}
+
+impl Input {
+    /// extension of the files of this input
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Input::Gadgets => ".gadgets",
+            Input::Instance => ".inst",
+            Input::Witness => ".wtns",
+            Input::Commitments => ".coms"
+        }
+    }
+}
+
+/// the word starting at a byte offset of the line
+fn word_at(source: &str, location: usize) -> Option<String> {
+    let word: String = source.get(location..)?.chars()
+        .take_while(|c| !c.is_whitespace() && !"(),;".contains(*c))
+        .collect();
+    if word.is_empty() { None } else { Some(word) }
+}
+
+/// whether the token names a witness variable, e.g. W0 or credit_score
+fn is_witness(token: &str) -> bool {
+    token.chars().next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !(token.starts_with('I') && token.len() > 1 && token[1..].chars().all(|c| c.is_ascii_digit()))
+}
+
+/// explanation of a common mistake that caused the parse error
+fn hint(context: &ParseContext, location: usize, message: &str) -> Option<String> {
+    let token = context.token.as_ref()?;
+    let numbered = |prefix: char| token.starts_with(prefix) && token.len() > 1 && token[1..].chars().all(|c| c.is_ascii_digit());
+
+    if message == "invalid hex string" {
+        return Some(format!("hex literals have an even number of digits, e.g. 0x0{} instead of 0x{}", token, token));
+    }
+
+    match context.input {
+        Input::Gadgets => {
+            // the bounds are the last two operands of BOUND
+            let code = &context.source[..location.min(context.source.len())];
+            let gadget = code.rsplit(':').next().unwrap_or(code).split_whitespace().next();
+            let remaining = context.source.get(location..).unwrap_or("").split_whitespace().count();
+            if gadget != Some("BOUND") || remaining > 2 || !is_witness(token) {
+                None
+            } else if numbered('W') {
+                Some(String::from("the bounds of BOUND are instance variables, e.g. I0 or $max"))
+            } else {
+                Some(format!("the bounds of BOUND are instance variables, named ones start with $, e.g. ${}", token))
+            }
+        },
+        Input::Instance | Input::Witness | Input::Commitments
+            if token.starts_with(|c: char| c.is_ascii_digit()) && token.chars().all(|c| c.is_ascii_hexdigit()) =>
+            Some(format!("assignments are hex literals starting with 0x, e.g. 0x{}", token)),
+        Input::Instance if numbered('W') => Some(String::from("witness variables belong to the witness file")),
+        Input::Instance if is_witness(token) => Some(format!("named instance variables start with $, e.g. ${}", token)),
+        Input::Witness if token.starts_with('$') || numbered('I') =>
+            Some(String::from("instance variables belong to the instance file")),
+        _ => None
+    }
+}
+
+impl fmt::Display for GadgetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GadgetError::Parse { line, column, message, .. } =>
+                write!(f, "parse error at line {}, column {}: {}", line, column, message),
+            GadgetError::UnknownGadget { line, op } =>
+                write!(f, "unknown gadget at line {}: {}", line, op),
+            GadgetError::MissingVariable(name) =>
+                write!(f, "missing var {}", name),
+            GadgetError::MissingCommitment(name) =>
+                write!(f, "missing commitment {}", name),
+            GadgetError::InvalidVariableType(message) =>
+                write!(f, "invalid variable type: {}", message),
+            GadgetError::SizeViolation { name, max, actual } =>
+                write!(f, "var {} is {} bytes long, at most {} bytes are allowed", name, actual, max),
+            GadgetError::SizeMismatch(left, right) =>
+                write!(f, "vars {} and {} span a different number of scalars", left, right),
+            GadgetError::PathLength { name, depth, actual } =>
+                write!(f, "var {} is {} bytes long, expected an index of at most 32 bytes and {} siblings of 32 bytes", name, actual, depth),
+            GadgetError::ChunkConflict(name) =>
+                write!(f, "var {} is committed in chunks of 31 bytes for SHA256 and can not be used by other gadgets", name),
+            GadgetError::InvalidTree(message) =>
+                write!(f, "invalid merkle tree: {}", message),
+            GadgetError::DuplicateVariable(name) =>
+                write!(f, "duplicate assignment of var {}", name),
+            GadgetError::MalformedCommitment(name) =>
+                write!(f, "malformed commitment {}", name),
+            GadgetError::ProofDecoding(message) =>
+                write!(f, "unable to decode proof: {}", message),
+            GadgetError::ProofCreation(message) =>
+                write!(f, "unable to create proof: {}", message),
+            GadgetError::UnexpectedEndOfInput =>
+                write!(f, "unexpected end of input"),
+            GadgetError::BundleDecoding(message) =>
+                write!(f, "unable to decode proof bundle: {}", message),
+            GadgetError::BundleMismatch(message) =>
+                write!(f, "proof bundle does not match: {}", message),
+            GadgetError::Serialization(message) =>
+                write!(f, "serialization error: {}", message),
+            GadgetError::DuplicateLabel(label) =>
+                write!(f, "duplicate gadget label {}", label),
+            GadgetError::InvalidStatement(message) =>
+                write!(f, "invalid statement: {}", message),
+            GadgetError::InvalidExpression(message) =>
+                write!(f, "invalid expression: {}", message),
+            GadgetError::Include { path, error } =>
+                write!(f, "in included file {}: {}", path, error)
+        }
+    }
+}
+
+impl Error for GadgetError {}
@@ -0,0 +1,70 @@
+use statement::{Statement, Clause};
+use cs_buffer::Operation;
+use error::GadgetError;
+
+/// One side of the constraint system a statement is evaluated on, implemented by the prover and the verifier.
+pub trait Evaluator {
+    /// add the constraints of a single gadget, the index determines the names of its derived commitments
+    fn gadget(&mut self, clause: &Clause, index: usize) -> Result<(), GadgetError>;
+
+    /// evaluate `branches` on a fresh evaluator initialized with the given operations and require
+    /// at least k of the rewound branches to hold on this evaluator, an OR has k = 1
+    fn threshold(
+        &mut self,
+        k: usize,
+        initialization: &[Vec<Operation>],
+        branches: &mut dyn FnMut(&mut dyn Evaluator) -> Result<(), GadgetError>
+    ) -> Result<(), GadgetError>;
+
+    /// operations added since the last rewind
+    fn buffer(&self) -> &Vec<Operation>;
+
+    /// store the current operations as a branch and start a new one
+    fn rewind(&mut self);
+}
+
+/// evaluate all clauses of the statement, starting at the given gadget index
+///
+/// Every OR and THRESHOLD collects its branches in a buffer that replays the operations of all enclosing
+/// statements (the initialization), so that variables allocated within a branch match those of
+/// the main constraint system.
+pub fn evaluate(
+    evaluator: &mut dyn Evaluator,
+    statement: &Statement,
+    index: usize,
+    initialization: &[Vec<Operation>]
+) -> Result<(), GadgetError> {
+    let mut index = index;
+    for clause in statement.clauses() {
+        match clause {
+            Clause::Or(branches) => evaluate_threshold(evaluator, 1, branches, index, initialization)?,
+            Clause::Threshold(k, branches) => evaluate_threshold(evaluator, *k, branches, index, initialization)?,
+            _ => evaluator.gadget(clause, index)?
+        }
+        index += clause.lines();
+    }
+    Ok(())
+}
+
+/// evaluate the branches of an OR or THRESHOLD at the given gadget index
+fn evaluate_threshold(
+    evaluator: &mut dyn Evaluator,
+    k: usize,
+    branches: &[Statement],
+    index: usize,
+    initialization: &[Vec<Operation>]
+) -> Result<(), GadgetError> {
+    let mut local_initialization: Vec<Vec<Operation>> = initialization.to_vec();
+    local_initialization.push(evaluator.buffer().clone());
+
+    evaluator.threshold(k, &local_initialization, &mut |branch_evaluator: &mut dyn Evaluator| {
+        // the first branch starts after the OR, [ and { lines
+        let mut branch_index = index + 3;
+        for branch in branches {
+            evaluate(branch_evaluator, branch, branch_index, &local_initialization)?;
+            branch_evaluator.rewind();
+            branch_index += branch.lines() + 2;
+        }
+        Ok(())
+    })
+}
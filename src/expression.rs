@@ -0,0 +1,269 @@
+use bulletproofs::r1cs::{ConstraintSystem, LinearCombination};
+use curve25519_dalek::scalar::Scalar;
+use lalrpop::ast::Var;
+use error::GadgetError;
+use utils::range_proof;
+
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Div};
+
+/// Bits of the quotient, the remainder and the divisor minus the remainder of a division. The
+/// quotient times the divisor plus the remainder stays below the group order and can not wrap.
+const DIVISION_BITS: u8 = 125;
+
+/// Arithmetic over single scalar variables and constants, e.g. `W1*100/W2`.
+///
+/// Expressions are operands of BOUND, LESS_THAN, EQUALS and UNEQUAL. Sums and products with a
+/// constant are linear combinations, a product of two expressions allocates a multiplier and a
+/// division is the integer division, proven with range proofs on the quotient and remainder.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Var(Var),
+    Constant(u64),
+    Add(Box<Expression>, Box<Expression>),
+    Sub(Box<Expression>, Box<Expression>),
+    Mul(Box<Expression>, Box<Expression>),
+    Div(Box<Expression>, Box<Expression>)
+}
+
+/// linear combination of an operand and its assignment, which is only known to the prover
+pub type Operand = (LinearCombination, Option<Scalar>);
+
+impl Expression {
+    /// variables referenced by the expression, in order of appearance
+    pub fn vars(&self) -> Vec<Var> {
+        match self {
+            Expression::Var(var) => vec![var.clone()],
+            Expression::Constant(_) => Vec::new(),
+            Expression::Add(left, right) | Expression::Sub(left, right) |
+            Expression::Mul(left, right) | Expression::Div(left, right) => [left.vars(), right.vars()].concat()
+        }
+    }
+
+    /// expression with every variable replaced
+    pub fn map_vars(&self, f: &dyn Fn(&Var) -> Var) -> Expression {
+        let map = |expression: &Expression| Box::new(expression.map_vars(f));
+        match self {
+            Expression::Var(var) => Expression::Var(f(var)),
+            Expression::Constant(constant) => Expression::Constant(*constant),
+            Expression::Add(left, right) => Expression::Add(map(left), map(right)),
+            Expression::Sub(left, right) => Expression::Sub(map(left), map(right)),
+            Expression::Mul(left, right) => Expression::Mul(map(left), map(right)),
+            Expression::Div(left, right) => Expression::Div(map(left), map(right))
+        }
+    }
+
+    /// add the expression to the constraint system, `operand` resolves its variables
+    pub fn lower(
+        &self,
+        cs: &mut dyn ConstraintSystem,
+        operand: &dyn Fn(&Var) -> Result<Operand, GadgetError>
+    ) -> Result<Operand, GadgetError> {
+        match self {
+            Expression::Var(var) => operand(var),
+            Expression::Constant(constant) => {
+                let constant = Scalar::from(*constant);
+                Ok((constant.into(), Some(constant)))
+            },
+            Expression::Add(left, right) => {
+                let (left, left_assignment) = left.lower(cs, operand)?;
+                let (right, right_assignment) = right.lower(cs, operand)?;
+                Ok((left + right, combine(left_assignment, right_assignment, |left, right| left + right)))
+            },
+            Expression::Sub(left, right) => {
+                let (left, left_assignment) = left.lower(cs, operand)?;
+                let (right, right_assignment) = right.lower(cs, operand)?;
+                Ok((left - right, combine(left_assignment, right_assignment, |left, right| left - right)))
+            },
+            Expression::Mul(left_expression, right_expression) => {
+                let (left, left_assignment) = left_expression.lower(cs, operand)?;
+                let (right, right_assignment) = right_expression.lower(cs, operand)?;
+                let assignment = combine(left_assignment, right_assignment, |left, right| left * right);
+
+                // a product with a constant stays linear
+                match (&**left_expression, &**right_expression) {
+                    (Expression::Constant(constant), _) => Ok((right * Scalar::from(*constant), assignment)),
+                    (_, Expression::Constant(constant)) => Ok((left * Scalar::from(*constant), assignment)),
+                    _ => {
+                        let (_, _, product) = cs.multiply(left, right);
+                        Ok((product.into(), assignment))
+                    }
+                }
+            },
+            Expression::Div(dividend, divisor) => {
+                let (dividend, dividend_assignment) = dividend.lower(cs, operand)?;
+                let (divisor, divisor_assignment) = divisor.lower(cs, operand)?;
+                let assignment = match (dividend_assignment, divisor_assignment) {
+                    (Some(dividend), Some(divisor)) => Some(integer_division(dividend, divisor)?),
+                    _ => None
+                };
+                divide(cs, dividend, divisor, assignment)
+            }
+        }
+    }
+
+    /// binding strength of the outermost operation, used to place parentheses
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Add(..) | Expression::Sub(..) => 1,
+            Expression::Mul(..) | Expression::Div(..) => 2,
+            Expression::Var(_) | Expression::Constant(_) => 3
+        }
+    }
+}
+
+fn combine(left: Option<Scalar>, right: Option<Scalar>, operation: fn(Scalar, Scalar) -> Scalar) -> Option<Scalar> {
+    match (left, right) {
+        (Some(left), Some(right)) => Some(operation(left, right)),
+        _ => None
+    }
+}
+
+/// quotient and remainder of the integer division, both operands have to fit into `DIVISION_BITS`
+/// bits such that the range proofs of `divide` hold
+fn integer_division(dividend: Scalar, divisor: Scalar) -> Result<(Scalar, Scalar, Scalar), GadgetError> {
+    let (dividend_value, divisor_value) = (to_u128(&dividend)?, to_u128(&divisor)?);
+    if divisor_value == 0 {
+        return Err(GadgetError::InvalidExpression(String::from("division by zero")));
+    }
+    Ok((Scalar::from(dividend_value / divisor_value), Scalar::from(dividend_value % divisor_value), divisor))
+}
+
+fn to_u128(scalar: &Scalar) -> Result<u128, GadgetError> {
+    let bytes = scalar.as_bytes();
+    let mut low = [0u8; 16];
+    low.copy_from_slice(&bytes[..16]);
+    let value = u128::from_le_bytes(low);
+    if bytes[16..].iter().any(|byte| *byte != 0) || value >> DIVISION_BITS != 0 {
+        return Err(GadgetError::InvalidExpression(format!("operand of a division exceeds {} bits", DIVISION_BITS)));
+    }
+    Ok(value)
+}
+
+/// constrain dividend = quotient * divisor + remainder with 0 <= remainder < divisor
+///
+/// The assignment is the quotient, the remainder and the divisor.
+fn divide(
+    cs: &mut dyn ConstraintSystem,
+    dividend: LinearCombination,
+    divisor: LinearCombination,
+    assignment: Option<(Scalar, Scalar, Scalar)>
+) -> Result<Operand, GadgetError> {
+    let (quotient, divisor_var, product) = cs.allocate_multiplier(assignment.map(|(quotient, _, divisor)| (quotient, divisor)))
+        .map_err(|error| GadgetError::ProofCreation(error.to_string()))?;
+    cs.constrain(divisor.clone() - divisor_var);
+
+    let remainder: LinearCombination = dividend - product;
+    range_proof(cs, quotient.into(), DIVISION_BITS, assignment.map(|(quotient, _, _)| quotient));
+    range_proof(cs, remainder.clone(), DIVISION_BITS, assignment.map(|(_, remainder, _)| remainder));
+    range_proof(cs, divisor - remainder - Scalar::one(), DIVISION_BITS,
+        assignment.map(|(_, remainder, divisor)| divisor - remainder - Scalar::one()));
+
+    Ok((quotient.into(), assignment.map(|(quotient, _, _)| quotient)))
+}
+
+impl From<Var> for Expression {
+    fn from(var: Var) -> Expression {
+        Expression::Var(var)
+    }
+}
+
+impl From<u64> for Expression {
+    fn from(constant: u64) -> Expression {
+        Expression::Constant(constant)
+    }
+}
+
+impl<E: Into<Expression>> Add<E> for Expression {
+    type Output = Expression;
+
+    fn add(self, right: E) -> Expression {
+        Expression::Add(Box::new(self), Box::new(right.into()))
+    }
+}
+
+impl<E: Into<Expression>> Sub<E> for Expression {
+    type Output = Expression;
+
+    fn sub(self, right: E) -> Expression {
+        Expression::Sub(Box::new(self), Box::new(right.into()))
+    }
+}
+
+impl<E: Into<Expression>> Mul<E> for Expression {
+    type Output = Expression;
+
+    fn mul(self, right: E) -> Expression {
+        Expression::Mul(Box::new(self), Box::new(right.into()))
+    }
+}
+
+impl<E: Into<Expression>> Div<E> for Expression {
+    type Output = Expression;
+
+    fn div(self, right: E) -> Expression {
+        Expression::Div(Box::new(self), Box::new(right.into()))
+    }
+}
+
+/// write the operand of an operation, in parentheses if it binds weaker than required
+fn write_operand(f: &mut fmt::Formatter, operand: &Expression, precedence: u8) -> fmt::Result {
+    if operand.precedence() < precedence {
+        write!(f, "({})", operand)
+    } else {
+        write!(f, "{}", operand)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // operations are left associative, right operands of the same precedence keep their parentheses
+        let (left, operator, right, precedence) = match self {
+            Expression::Var(var) => return write!(f, "{}", var),
+            Expression::Constant(constant) => return write!(f, "{}", constant),
+            Expression::Add(left, right) => (left, " + ", right, 1),
+            Expression::Sub(left, right) => (left, " - ", right, 1),
+            Expression::Mul(left, right) => (left, "*", right, 2),
+            Expression::Div(left, right) => (left, "/", right, 2)
+        };
+        write_operand(f, left, precedence)?;
+        write!(f, "{}", operator)?;
+        write_operand(f, right, precedence + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expression_display() {
+        let w1 = Expression::from(Var::witness(1));
+        let w2 = Expression::from(Var::witness(2));
+
+        assert_eq!((w1.clone() * 100u64 / Var::witness(2)).to_string(), "W1*100/W2");
+        assert_eq!((Expression::from(2u64) * Var::witness(1) - Var::instance(5)).to_string(), "2*W1 - I5");
+        assert_eq!((w1.clone() - (w2.clone() + 1u64)).to_string(), "W1 - (W2 + 1)");
+        assert_eq!(((w1.clone() + w2.clone()) * (w1.clone() / (w2 * 3u64))).to_string(), "(W1 + W2)*(W1/(W2*3))");
+        assert_eq!((w1.clone() - Var::witness(2)).vars(), vec![Var::witness(1), Var::witness(2)]);
+    }
+
+    #[test]
+    fn test_integer_division() {
+        let (quotient, remainder, _) = integer_division(Scalar::from(5000u64), Scalar::from(30u64)).unwrap();
+        assert_eq!((quotient, remainder), (Scalar::from(166u64), Scalar::from(20u64)));
+
+        assert!(integer_division(Scalar::from(1u64), Scalar::zero()).is_err());
+        assert!(integer_division(-Scalar::one(), Scalar::one()).is_err());
+
+        // the quotient is range proven with 125 bits
+        let largest = Scalar::from(u128::max_value() >> 3);
+        assert_eq!(integer_division(largest, Scalar::one()).unwrap().0, largest);
+        assert_eq!(
+            integer_division(Scalar::from(1u128 << 125), Scalar::one()),
+            Err(GadgetError::InvalidExpression(String::from("operand of a division exceeds 125 bits")))
+        );
+        assert!(integer_division(Scalar::from(u128::max_value()), Scalar::from(7u64)).is_err());
+        assert!(integer_division(Scalar::one(), Scalar::from(1u128 << 126)).is_err());
+    }
+}
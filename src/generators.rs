@@ -0,0 +1,74 @@
+use bulletproofs::BulletproofGens;
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+lazy_static! {
+    /// generators created so far, keyed by their capacity
+    static ref GENERATORS: Mutex<BTreeMap<usize, Arc<BulletproofGens>>> = Mutex::new(BTreeMap::new());
+}
+
+/// Generators of at least the given capacity, shared by the whole process.
+///
+/// The smallest cached generators with sufficient capacity are handed out, new generators
+/// are only created (and cached) if none of the cached ones is large enough. They are created
+/// without holding the lock, so other threads keep using the cache in the meantime.
+pub fn shared_generators(capacity: usize) -> Arc<BulletproofGens> {
+    if let Some(gens) = cached_generators(&GENERATORS.lock().unwrap(), capacity) {
+        return gens;
+    }
+
+    let gens = Arc::new(BulletproofGens::new(capacity, 1));
+
+    // another thread may have cached sufficient generators while these were created
+    let mut generators = GENERATORS.lock().unwrap();
+    if let Some(cached) = cached_generators(&generators, capacity) {
+        return cached;
+    }
+    generators.insert(capacity, gens.clone());
+    gens
+}
+
+fn cached_generators(generators: &BTreeMap<usize, Arc<BulletproofGens>>, capacity: usize) -> Option<Arc<BulletproofGens>> {
+    generators.range(capacity..).next().map(|(_, gens)| gens.clone())
+}
+
+/// create the generators of the given capacity ahead of time, e.g. at startup
+pub fn preload_generators(capacity: usize) {
+    shared_generators(capacity);
+}
+
+/// capacities of the cached generators in ascending order
+pub fn cached_capacities() -> Vec<usize> {
+    GENERATORS.lock().unwrap().keys().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // the cache is shared with the other tests, which only request powers of two
+
+    #[test]
+    fn test_shared_generators() {
+        preload_generators(515);
+        assert!(cached_capacities().contains(&515));
+
+        let gens = shared_generators(513);
+        assert_eq!(gens.gens_capacity, 515);
+        assert!(Arc::ptr_eq(&gens, &shared_generators(515)));
+    }
+
+    #[test]
+    fn test_shared_generators_threads() {
+        let gens: Vec<Arc<BulletproofGens>> = (0..4)
+            .map(|_| thread::spawn(|| shared_generators(517)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        assert!(gens.iter().all(|other| Arc::ptr_eq(other, &gens[0])));
+    }
+}
@@ -0,0 +1,70 @@
+use bulletproofs::r1cs::{ConstraintSystem, LinearCombination};
+use curve25519_dalek::scalar::Scalar;
+use gadget::Gadget;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A hash function that can be computed natively and within a constraint system.
+///
+/// Implemented by the hash gadgets, which prove that the hash of a committed preimage is their image.
+pub trait CircuitHash: Gadget {
+    /// selection of the hash function in a statement
+    const FUNCTION: HashFunction;
+
+    /// whether the gadget derives commitments to the padding of the preimage
+    const COMMITTED_PADDING: bool;
+
+    /// gadget proving that the hash of a committed preimage is `image`
+    fn with_image(image: LinearCombination) -> Self;
+
+    /// hash of a preimage outside of the constraint system
+    fn hash(preimage: &Vec<u8>) -> Scalar;
+
+    /// hash of scalars outside of the constraint system, the native equivalent of `sponge`
+    fn native_sponge(preimage: &Vec<Scalar>) -> Scalar;
+
+    /// hash of linear combinations within the constraint system, as used for the inner nodes of a Merkle tree
+    fn sponge(&self, cs: &mut dyn ConstraintSystem, preimage: &Vec<LinearCombination>) -> LinearCombination;
+}
+
+/// Hash function of HASH, MERKLE, MERKLE_PATH and SET_MEMBER, selected in the statement, e.g. `HASH[poseidon]`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashFunction {
+    MiMC,
+    Poseidon
+}
+
+impl HashFunction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashFunction::MiMC => "mimc",
+            HashFunction::Poseidon => "poseidon"
+        }
+    }
+}
+
+/// gadgets without a selection use MiMC
+impl Default for HashFunction {
+    fn default() -> HashFunction {
+        HashFunction::MiMC
+    }
+}
+
+impl fmt::Display for HashFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for HashFunction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<HashFunction, ()> {
+        match s {
+            "mimc" => Ok(HashFunction::MiMC),
+            "poseidon" => Ok(HashFunction::Poseidon),
+            _ => Err(())
+        }
+    }
+}
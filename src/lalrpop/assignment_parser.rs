@@ -1,6 +1,9 @@
 use lalrpop::ast::*;
-use commitments::commit;
+use commitments::{commit, commit_chunks};
+use sha256_hash::sha256_gadget::CHUNK_SIZE;
 use cs_buffer::ProverBuffer;
+use error::{GadgetError, Input};
+use statement::Statement;
 
 use bulletproofs::r1cs::{Verifier, Prover, Variable};
 use curve25519_dalek::ristretto::CompressedRistretto;
@@ -12,11 +15,15 @@ use merlin::Transcript;
 // lalrpop parsers
 lalrpop_mod!(var_grammar, "/lalrpop/var_grammar.rs");
 
+#[derive(Clone)]
 pub struct Assignments {
     commitments: HashMap<String, Variable>,
     witness_vars: HashMap<String, (Vec<Scalar>, Vec<CompressedRistretto>, Vec<Variable>, Vec<u8>)>,
     derived_witnesses: Vec<Scalar>,
-    instance_vars: HashMap<String, Vec<u8>>
+    instance_vars: HashMap<String, Vec<u8>>,
+    labels: HashMap<usize, String>,
+    /// witnesses committed in chunks of `CHUNK_SIZE` bytes for SHA256
+    sha256_witnesses: Vec<String>
 }
 
 impl Assignments {
@@ -25,7 +32,53 @@ impl Assignments {
             commitments: HashMap::new(),
             witness_vars: HashMap::new(),
             derived_witnesses: Vec::new(),
-            instance_vars: HashMap::new()
+            instance_vars: HashMap::new(),
+            labels: HashMap::new(),
+            sha256_witnesses: Vec::new()
+        }
+    }
+
+    /// assignments prepared for the gadgets of a statement, before any instance or witness is parsed
+    pub fn for_statement(statement: &Statement) -> Result<Assignments, GadgetError> {
+        let mut assignments = Assignments::new();
+        assignments.label_gadgets(statement, 0)?;
+        assignments.chunk_sha256_witnesses(statement)?;
+        Ok(assignments)
+    }
+
+    /// name the derived commitments of labeled gadgets after their label instead of their index
+    pub fn label_gadgets(&mut self, statement: &Statement, index: usize) -> Result<(), GadgetError> {
+        for (gadget, label) in statement.gadget_labels(index) {
+            if self.labels.values().any(|existing| existing == &label) {
+                return Err(GadgetError::DuplicateLabel(label));
+            }
+            self.labels.insert(gadget, label);
+        }
+        Ok(())
+    }
+
+    /// commit the witnesses of the SHA256 gadgets in chunks that bind every bit, see `Sha256`
+    ///
+    /// The chunks can not be read by other gadgets, a witness they share is a `ChunkConflict`.
+    pub fn chunk_sha256_witnesses(&mut self, statement: &Statement) -> Result<(), GadgetError> {
+        let sha256 = statement.sha256_witnesses();
+        if let Some(var) = statement.vars_besides_sha256().into_iter().find(|var| sha256.contains(var)) {
+            return Err(GadgetError::ChunkConflict(var.to_string()));
+        }
+
+        for var in sha256 {
+            if let Var::Witness(name) = var {
+                self.sha256_witnesses.push(name);
+            }
+        }
+        Ok(())
+    }
+
+    /// name of a derived commitment, the gadget is identified by its label or else by its index
+    fn derived_name(&self, gadget: usize, subroutine: usize, index: usize) -> String {
+        match self.labels.get(&gadget) {
+            Some(label) => format!("D{}-{}-{}", label, subroutine, index),
+            None => format!("D{}-{}-{}", gadget, subroutine, index)
         }
     }
 
@@ -37,131 +90,145 @@ impl Assignments {
         self.commitments.insert(key, val);
     }
 
-    pub fn get_commitment(&self, var: Var, index: usize) -> Variable {
-        match self.inquire_commitment(var, index) {
-            Ok(commitment) => commitment,
-            Err(message) => panic!("{}", message)
-        }
+    pub fn get_commitment(&self, var: Var, index: usize) -> Result<Variable, GadgetError> {
+        self.inquire_commitment(var, index)
     }
 
-    pub fn get_all_commitments(&self, var: Var) -> Vec<Variable> {
-        let mut variables = Vec::new();
+    pub fn get_all_commitments(&self, var: Var) -> Result<Vec<Variable>, GadgetError> {
+        let mut variables = vec![self.inquire_commitment(var.clone(), 0)?];
 
-        let mut index = 0;
-        while {
-            let result = self.inquire_commitment(var.clone(), index);
-            match result.clone() {
-                Ok(witness) => variables.push(witness),
-                Err(_) => ()
-            }
+        let mut index = 1;
+        while let Ok(witness) = self.inquire_commitment(var.clone(), index) {
+            variables.push(witness);
             index += 1;
+        }
 
-            result.is_ok()
-        } {}
-
-        variables
+        Ok(variables)
     }
 
-    fn inquire_commitment(&self, var: Var, index: usize) -> Result<Variable, String> {
+    fn inquire_commitment(&self, var: Var, index: usize) -> Result<Variable, GadgetError> {
         match var {
             Var::Witness(name) => {
-                let key = format!("C{}-{}", &name[1..name.len()], index);
+                let key = commitment_name(&name, index);
                 match self.commitments.get(&key) {
                     Some(commitment) => Ok(*commitment),
-                    None => Err(format!("missing commitment {}", &key))
+                    None => Err(GadgetError::MissingCommitment(key))
                 }
             }
-            _ => Err(String::from("provided variable is not of type witness"))
+            _ => Err(GadgetError::InvalidVariableType(String::from("provided variable is not of type witness")))
         }
     }
 
     pub fn inquire_derived(&self, gadget: usize, index: usize, subroutine: usize) -> Option<&Variable> {
-        let key = format!("D{}-{}-{}", gadget, subroutine, index);
+        let key = self.derived_name(gadget, subroutine, index);
         self.commitments.get(&key)
     }
 
-    pub fn get_derived(&self, gadget: usize, index: usize, subroutine: usize) -> Variable {
-        let key = format!("D{}-{}-{}", gadget, subroutine, index);
-        *self.commitments.get(&key).expect(&format!("missing commitment {}", &key))
+    pub fn get_derived(&self, gadget: usize, index: usize, subroutine: usize) -> Result<Variable, GadgetError> {
+        let key = self.derived_name(gadget, subroutine, index);
+        match self.commitments.get(&key) {
+            Some(commitment) => Ok(*commitment),
+            None => Err(GadgetError::MissingCommitment(key))
+        }
     }
 
-    pub fn get_instance(&self, var: Var, assertion: Option<&dyn Fn(String, &Vec<u8>)>) -> Vec<u8> {
+    pub fn get_instance(
+        &self,
+        var: Var,
+        assertion: Option<&dyn Fn(String, &Vec<u8>) -> Result<(), GadgetError>>
+    ) -> Result<Vec<u8>, GadgetError> {
         match var {
             Var::Instance(name) => {
-                let error = &format!("missing instance var {}", &name);
-                let assignment = self.instance_vars.get(&name).expect(error).to_vec();
+                let assignment = match self.instance_vars.get(&name) {
+                    Some(assignment) => assignment.to_vec(),
+                    None => return Err(GadgetError::MissingVariable(name))
+                };
                 match assertion {
-                    Some(fnc) => fnc(name, &assignment),
+                    Some(fnc) => fnc(name, &assignment)?,
                     None => ()
                 }
-                assignment
+                Ok(assignment)
             }
-            _ => panic!("provided variable is not of type instance")
+            _ => Err(GadgetError::InvalidVariableType(String::from("provided variable is not of type instance")))
         }
     }
 
     pub fn get_witness(
         &self,
         var: Var,
-        assertion: Option<&dyn Fn(String, &(Vec<Scalar>, Vec<CompressedRistretto>, Vec<Variable>, Vec<u8>))>
-    ) -> (Vec<Scalar>, Vec<CompressedRistretto>, Vec<Variable>, Vec<u8>) {
+        assertion: Option<&dyn Fn(String, &(Vec<Scalar>, Vec<CompressedRistretto>, Vec<Variable>, Vec<u8>)) -> Result<(), GadgetError>>
+    ) -> Result<(Vec<Scalar>, Vec<CompressedRistretto>, Vec<Variable>, Vec<u8>), GadgetError> {
         match var {
             Var::Witness(name) => {
-                let error = &format!("missing witness var {}", &name);
-                let assignment = self.witness_vars.get(&name).expect(error);
+                let assignment = match self.witness_vars.get(&name) {
+                    Some(assignment) => assignment,
+                    None => return Err(GadgetError::MissingVariable(name))
+                };
                 match assertion {
-                    Some(fnc) => fnc(name, &assignment),
+                    Some(fnc) => fnc(name, &assignment)?,
                     None => ()
                 }
-                assignment.clone()
+                Ok(assignment.clone())
             }
-            _ => panic!("provided variable is not of type witness")
+            _ => Err(GadgetError::InvalidVariableType(String::from("provided variable is not of type witness")))
         }
     }
 
     /// read instance variables from string
-    pub fn parse_instance(&mut self, instance: String) -> std::io::Result<()> {
-        let instance_parser = var_grammar::InstanceVarParser::new();
-        for line in instance.lines() {
-            let (name, bytes) = instance_parser.parse(&line).unwrap();
+    pub fn parse_instance(&mut self, instance: String) -> Result<(), GadgetError> {
+        for (name, bytes) in read_instance(&instance)? {
             self.set_instance(name, bytes);
         }
         Ok(())
     }
 
-    /// parse prover commitments from string
-    pub fn parse_commitments(&mut self, commitments: String, verifier: &mut Verifier<&mut Transcript>) -> std::io::Result<()> {
-        let commitment_parser = var_grammar::CommitmentVarParser::new();
-        for line in commitments.lines() {
-            let (name, bytes) = commitment_parser.parse(&line).unwrap();
-            let com = CompressedRistretto::from_slice(&bytes);
-            self.set_commitment(name, verifier.commit(com));
+    /// instance variables sorted by name
+    pub fn get_instance_vars(&self) -> Vec<(String, Vec<u8>)> {
+        let mut instance_vars: Vec<(String, Vec<u8>)> = self.instance_vars.clone().into_iter().collect();
+        instance_vars.sort();
+        instance_vars
+    }
+
+    /// parse prover commitments from string, returns their labels in commit order
+    pub fn parse_commitments(&mut self, commitments: String, verifier: &mut Verifier<&mut Transcript>) -> Result<Vec<String>, GadgetError> {
+        let mut labels = Vec::new();
+        for (name, com) in read_commitments(&commitments)? {
+            self.set_commitment(name.clone(), verifier.commit(com));
+            labels.push(name);
         }
-        Ok(())
+        Ok(labels)
     }
 
     /// commit to vars from witness instance to coms instance
-    pub fn parse_witness(&mut self, witness: String, prover: &mut Prover<&mut Transcript>, commitments: &mut String) -> std::io::Result<()> {
-        let witness_parser = var_grammar::WitnessVarParser::new();
-        for line in witness.lines() {
-            let (name, bytes) = witness_parser.parse(&line).unwrap();
-            let commitment = commit(prover, &bytes);
+    pub fn parse_witness(&mut self, witness: String, prover: &mut Prover<&mut Transcript>, commitments: &mut String) -> Result<(), GadgetError> {
+        for (name, bytes) in read_witness(&witness)? {
+            let commitment = if self.sha256_witnesses.contains(&name) {
+                commit_chunks(prover, &bytes, CHUNK_SIZE)
+            } else {
+                commit(prover, &bytes)
+            };
             self.witness_vars.insert(name.clone(), (commitment.0.clone(), commitment.1.clone(), commitment.2.clone(), bytes));
             for (index, com) in commitment.1.iter().enumerate() {
-                commitments.push_str(&format_com("C", &name[1..name.len()], &index, com));
+                commitments.push_str(&format!("{} = 0x{}\n", commitment_name(&name, index), hex::encode(com.as_bytes())));
             }
         }
         Ok(())
     }
 
-    /// commit all witnesses previously read into the given constraint system (used to create cs buffers)
+    /// commit all witnesses previously read into the given constraint system in the order of the real cs (used to create cs buffers)
     pub fn buffer_commit_wtns(
         &self,
         prover_buffer: &mut ProverBuffer
     ) {
-        for (_, (scalars, _, _, _)) in self.witness_vars.clone() {
-            prover_buffer.commit(&scalars);
-        }
+        let mut witnesses: Vec<(Variable, Scalar)> = self.witness_vars.values()
+            .flat_map(|(scalars, _, vars, _)| vars.iter().cloned().zip(scalars.iter().cloned()))
+            .collect();
+        witnesses.sort_by_key(|(var, _)| match var {
+            Variable::Committed(index) => *index,
+            _ => 0
+        });
+
+        prover_buffer.commit(&witnesses.into_iter().map(|(_, scalar)| scalar).collect());
     }
 
     /// commit the buffer to all previsouly derived witnesses for the variable index to match the real cs
@@ -187,31 +254,186 @@ impl Assignments {
         gadget: usize,
         subroutine: usize,
         commitments: &mut String
-    ) -> std::io::Result<()> {
+    ) -> Result<(), GadgetError> {
         for (index, com) in coms.iter().enumerate() {
-            let identifier = format!("{}-{}", gadget.to_string(), subroutine);
-            commitments.push_str(&format_com("D", &identifier, &index, com));
+            commitments.push_str(&format!("{} = 0x{}\n", self.derived_name(gadget, subroutine, index), hex::encode(com.as_bytes())));
         }
         Ok(())
     }
 }
 
-fn format_com(
-    identifier: &str,
-    gadget_no: &str,
-    com_idx: &usize,
-    com: &CompressedRistretto
-) -> String {
-    format!("{}{}-{} = 0x{}\n", identifier, gadget_no, com_idx, hex::encode(com.as_bytes()))
+/// read named instance variables from string
+pub fn read_instance(instance: &str) -> Result<Vec<(String, Vec<u8>)>, GadgetError> {
+    let instance_parser = var_grammar::InstanceVarParser::new();
+    let mut instance_vars = Vec::new();
+    for (index, line) in instance.lines().enumerate() {
+        let (name, bytes) = instance_parser.parse(&line)
+            .map_err(|error| GadgetError::from_parse_error(Input::Instance, index, line, error))?;
+        instance_vars.push((name, bytes));
+    }
+    Ok(instance_vars)
+}
+
+/// read named witness variables from string
+pub fn read_witness(witness: &str) -> Result<Vec<(String, Vec<u8>)>, GadgetError> {
+    let witness_parser = var_grammar::WitnessVarParser::new();
+    let mut witness_vars = Vec::new();
+    for (index, line) in witness.lines().enumerate() {
+        let (name, bytes) = witness_parser.parse(&line)
+            .map_err(|error| GadgetError::from_parse_error(Input::Witness, index, line, error))?;
+        witness_vars.push((name, bytes));
+    }
+    Ok(witness_vars)
+}
+
+/// read named commitments from string, in the order they were committed to
+pub fn read_commitments(commitments: &str) -> Result<Vec<(String, CompressedRistretto)>, GadgetError> {
+    let commitment_parser = var_grammar::CommitmentVarParser::new();
+    let mut named_commitments = Vec::new();
+    for (index, line) in commitments.lines().enumerate() {
+        let (name, bytes) = commitment_parser.parse(&line)
+            .map_err(|error| GadgetError::from_parse_error(Input::Commitments, index, line, error))?;
+        if bytes.len() != 32 {
+            return Err(GadgetError::MalformedCommitment(name));
+        }
+        let com = CompressedRistretto::from_slice(&bytes);
+        if com.decompress().is_none() {
+            return Err(GadgetError::MalformedCommitment(name));
+        }
+        named_commitments.push((name, com));
+    }
+    Ok(named_commitments)
 }
 
-pub fn assert_32(name: String, assignment: &Vec<u8>) {
-    assert!(assignment.len() <= 32, "instance var {} is longer than 32 bytes", &name);
+/// name of a witness commitment, numbered witnesses drop the W (C3-0) while named ones keep their name (Cscore-0)
+fn commitment_name(witness: &str, index: usize) -> String {
+    match witness.strip_prefix('W') {
+        Some(number) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => format!("C{}-{}", number, index),
+        _ => format!("C{}-{}", witness, index)
+    }
+}
+
+pub fn assert_32(name: String, assignment: &Vec<u8>) -> Result<(), GadgetError> {
+    if assignment.len() > 32 {
+        return Err(GadgetError::SizeViolation { name, max: 32, actual: assignment.len() });
+    }
+    Ok(())
 }
 
 pub fn assert_witness_32(
     name: String,
     assignment: &(Vec<Scalar>, Vec<CompressedRistretto>, Vec<Variable>, Vec<u8>)
-) {
-    assert!(assignment.0.len() == 1, "witness var {} is longer than 32 bytes", &name);
+) -> Result<(), GadgetError> {
+    if assignment.0.len() != 1 {
+        return Err(GadgetError::SizeViolation { name, max: 32, actual: assignment.3.len() });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_COMPRESSED;
+
+    #[test]
+    fn test_parse_instance_invalid_hex() {
+        let mut assignments = Assignments::new();
+        let result = assignments.parse_instance(String::from("I0 = 0x0102\nI1 = 0x123"));
+        match result {
+            Err(GadgetError::Parse { line, column, .. }) => {
+                assert_eq!(line, 2);
+                assert_eq!(column, 8);
+            },
+            _ => panic!("expected parse error")
+        }
+    }
+
+    #[test]
+    fn test_parse_instance_hints() {
+        let error = read_instance("I0 = 0x0102\nI1 = 0x123").unwrap_err();
+        assert_eq!(error.render("example"), "error: invalid hex string\n --> example.inst:2:8\n  |\n2 | I1 = 0x123\n  |        ^^^\n\
+            \x20 = hint: hex literals have an even number of digits, e.g. 0x0123 instead of 0x123\n");
+
+        let hint = |instance: &str| match read_instance(instance) {
+            Err(GadgetError::Parse { context, .. }) => context.hint,
+            _ => panic!("expected parse error")
+        };
+        assert_eq!(hint("I0 = 01"), Some(String::from("assignments are hex literals starting with 0x, e.g. 0x01")));
+        assert_eq!(hint("W0 = 0x01"), Some(String::from("witness variables belong to the witness file")));
+        assert_eq!(hint("limit = 0x01"), Some(String::from("named instance variables start with $, e.g. $limit")));
+        assert_eq!(hint("I0 0x01"), None);
+    }
+
+    #[test]
+    fn test_parse_instance_unexpected_token() {
+        let mut assignments = Assignments::new();
+        let result = assignments.parse_instance(String::from("W0 = 0x01"));
+        match result {
+            Err(GadgetError::Parse { line, column, .. }) => {
+                assert_eq!(line, 1);
+                assert_eq!(column, 1);
+            },
+            _ => panic!("expected parse error")
+        }
+    }
+
+    #[test]
+    fn test_read_named_instance() {
+        let instance = read_instance("public $limit = 0x01\n$min = 0x02\nI0 = 0x03").unwrap();
+        assert_eq!(instance, vec![
+            (String::from("$limit"), vec![1]),
+            (String::from("$min"), vec![2]),
+            (String::from("I0"), vec![3])
+        ]);
+
+        // named instance variables need a leading $, private variables belong to the witness
+        assert!(read_instance("limit = 0x01").is_err());
+        assert!(read_instance("private $limit = 0x01").is_err());
+    }
+
+    #[test]
+    fn test_commitment_name() {
+        assert_eq!(commitment_name("W3", 1), "C3-1");
+        assert_eq!(commitment_name("credit_score", 0), "Ccredit_score-0");
+        assert_eq!(commitment_name("W", 0), "CW-0");
+        assert_eq!(commitment_name("Wage", 0), "CWage-0");
+
+        let commitment = format!("Ccredit_score-0 = 0x{}", hex::encode(RISTRETTO_BASEPOINT_COMPRESSED.as_bytes()));
+        assert_eq!(read_commitments(&commitment).unwrap()[0].0, "Ccredit_score-0");
+    }
+
+    #[test]
+    fn test_get_instance_errors() {
+        let mut assignments = Assignments::new();
+        assignments.parse_instance(String::from("I0 = 0x01\nI1 = 0x".to_owned() + &"ab".repeat(33))).unwrap();
+
+        assert_eq!(assignments.get_instance(Var::Instance(String::from("I0")), Some(&assert_32)), Ok(vec![1]));
+        assert_eq!(
+            assignments.get_instance(Var::Instance(String::from("I2")), None),
+            Err(GadgetError::MissingVariable(String::from("I2")))
+        );
+        assert_eq!(
+            assignments.get_instance(Var::Instance(String::from("I1")), Some(&assert_32)),
+            Err(GadgetError::SizeViolation { name: String::from("I1"), max: 32, actual: 33 })
+        );
+        assert!(assignments.get_instance(Var::Witness(String::from("W0")), None).is_err());
+    }
+
+    #[test]
+    fn test_get_derived_missing() {
+        let assignments = Assignments::new();
+        assert_eq!(assignments.get_derived(3, 1, 0), Err(GadgetError::MissingCommitment(String::from("D3-0-1"))));
+    }
+
+    #[test]
+    fn test_label_gadgets() {
+        let statement: Statement = "EQUALS W0 W1\nfirst: LESS_THAN W0 W1\nsecond: LESS_THAN W1 W2".parse().unwrap();
+        let mut assignments = Assignments::new();
+        assignments.label_gadgets(&statement, 0).unwrap();
+        assert_eq!(assignments.get_derived(2, 1, 0), Err(GadgetError::MissingCommitment(String::from("Dsecond-0-1"))));
+        assert_eq!(assignments.get_derived(0, 1, 0), Err(GadgetError::MissingCommitment(String::from("D0-0-1"))));
+
+        let duplicate: Statement = "first: LESS_THAN W0 W1\nfirst: LESS_THAN W1 W2".parse().unwrap();
+        assert_eq!(Assignments::new().label_gadgets(&duplicate, 0), Err(GadgetError::DuplicateLabel(String::from("first"))));
+    }
 }
@@ -1,10 +1,16 @@
+use expression::Expression;
+
+use std::fmt;
 use std::str::FromStr;
 
 pub enum GadgetOp {
     Or,
+    And,
     Hash,
+    Sha256,
     Bound,
     Merkle,
+    MerklePath,
     LessThan,
     ArrayEnd,
     Equality,
@@ -13,28 +19,35 @@ pub enum GadgetOp {
     CodeBlockEnd,
     SetMembership,
     CodeBlockStart,
+    Threshold,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Var {
     Instance(String),
     Witness(String),
     Commitment(String),
-    Derived(String)
+    Derived(String),
+    /// arithmetic over variables, an operand of BOUND, LESS_THAN, EQUALS and UNEQUAL
+    Expression(Box<Expression>)
 }
 
 impl GadgetOp {
     pub fn as_str(&self) -> &'static str {
         match self {
             GadgetOp::Or => "OR",
+            GadgetOp::And => "AND",
             GadgetOp::Hash => "HASH",
+            GadgetOp::Sha256 => "SHA256",
             GadgetOp::ArrayEnd => "]",
             GadgetOp::Bound => "BOUND",
             GadgetOp::ArrayStart => "[",
             GadgetOp::Merkle => "MERKLE",
+            GadgetOp::MerklePath => "MERKLE_PATH",
             GadgetOp::CodeBlockEnd => "}",
             GadgetOp::Equality => "EQUALS",
             GadgetOp::CodeBlockStart => "{",
+            GadgetOp::Threshold => "THRESHOLD",
             GadgetOp::LessThan => "LESS_THAN",
             GadgetOp::Inequality => "UNEQUAL",
             GadgetOp::SetMembership => "SET_MEMBER"
@@ -63,23 +76,73 @@ impl GadgetOp {
     }
 }
 
+impl Var {
+    pub fn instance(index: usize) -> Var {
+        Var::Instance(format!("I{}", index))
+    }
+
+    pub fn witness(index: usize) -> Var {
+        Var::Witness(format!("W{}", index))
+    }
+
+    /// named instance variable, e.g. `Var::public("limit")` for `$limit`
+    pub fn public(name: &str) -> Var {
+        Var::Instance(format!("${}", name))
+    }
+
+    /// named witness variable, e.g. `Var::private("credit_score")`
+    pub fn private(name: &str) -> Var {
+        Var::Witness(String::from(name))
+    }
+
+    pub fn is_expression(&self) -> bool {
+        match *self {
+            Var::Expression(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// an expression that is a single variable is that variable
+impl From<Expression> for Var {
+    fn from(expression: Expression) -> Var {
+        match expression {
+            Expression::Var(var) => var,
+            expression => Var::Expression(Box::new(expression))
+        }
+    }
+}
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Var::Instance(name) | Var::Witness(name) | Var::Commitment(name) | Var::Derived(name) => write!(f, "{}", name),
+            Var::Expression(expression) => write!(f, "{}", expression)
+        }
+    }
+}
+
 impl FromStr for GadgetOp {
     type Err = ();
     
     fn from_str(s: &str) -> Result<GadgetOp, ()> {
         match s {
             "OR" => Ok(GadgetOp::Or),
+            "AND" => Ok(GadgetOp::And),
             "HASH" => Ok(GadgetOp::Hash),
+            "SHA256" => Ok(GadgetOp::Sha256),
             "]" => Ok(GadgetOp::ArrayEnd),
             "BOUND" => Ok(GadgetOp::Bound),
             "[" => Ok(GadgetOp::ArrayStart),
             "MERKLE" => Ok(GadgetOp::Merkle),
+            "MERKLE_PATH" => Ok(GadgetOp::MerklePath),
             "}" => Ok(GadgetOp::CodeBlockEnd),
             "EQUALS" => Ok(GadgetOp::Equality),
             "{" => Ok(GadgetOp::CodeBlockStart),
             "UNEQUAL" => Ok(GadgetOp::Inequality),
             "LESS_THAN" => Ok(GadgetOp::LessThan),
             "SET_MEMBER" => Ok(GadgetOp::SetMembership),
+            "THRESHOLD" => Ok(GadgetOp::Threshold),
             _ => Err(()),
         }
     }
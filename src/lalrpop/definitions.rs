@@ -0,0 +1,140 @@
+use statement::Statement;
+use lalrpop::ast::Var;
+use lalrpop::statement_parser::{LabeledClause, parse_statement_with};
+use error::{GadgetError, GrammarError};
+
+use lalrpop_util::ParseError;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// Body of a `DEFINE name(params) { .. }`, parameters are named witness or `$`instance variables.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Definition {
+    params: Vec<Var>,
+    body: Statement
+}
+
+/// Definitions and included files, shared by the parsers of a statement and all files it includes.
+///
+/// Grammar actions are run in the order of the gadgets, so a definition is known to every
+/// invocation below it, including those within later definitions.
+#[derive(Debug, Default)]
+pub struct Definitions {
+    definitions: RefCell<HashMap<String, Definition>>,
+    /// number of unlabeled invocations of every definition
+    calls: RefCell<HashMap<String, usize>>,
+    included: RefCell<HashSet<PathBuf>>,
+    include_error: RefCell<Option<GadgetError>>
+}
+
+impl Definitions {
+    /// add a definition, located at the given byte offset
+    pub fn define<T>(
+        &self,
+        location: usize,
+        name: String,
+        params: Vec<Var>,
+        body: Statement
+    ) -> Result<(), ParseError<usize, T, GrammarError>> {
+        let mut unique: Vec<&Var> = params.iter().collect();
+        unique.sort_by_key(|param| param.to_string());
+        unique.dedup();
+        if unique.len() < params.len() {
+            return Err(ParseError::User { error: (location, "duplicate parameter") });
+        }
+
+        let mut definitions = self.definitions.borrow_mut();
+        if definitions.contains_key(&name) {
+            return Err(ParseError::User { error: (location, "duplicate definition") });
+        }
+        definitions.insert(name, Definition { params, body });
+        Ok(())
+    }
+
+    /// clauses of the definition with its parameters replaced by the arguments
+    ///
+    /// The labels within the definition are prefixed by the label of the invocation, such that
+    /// every invocation names its derived commitments differently. An unlabeled invocation is
+    /// labeled by the name of the definition and the number of unlabeled invocations before it,
+    /// e.g. `credential0`.
+    pub fn expand<T>(
+        &self,
+        location: usize,
+        label: Option<String>,
+        name: &str,
+        args: Vec<Var>
+    ) -> Result<Vec<LabeledClause>, ParseError<usize, T, GrammarError>> {
+        let definitions = self.definitions.borrow();
+        let definition = definitions.get(name)
+            .ok_or(ParseError::User { error: (location, "unknown definition") })?;
+        if definition.params.len() != args.len() {
+            return Err(ParseError::User { error: (location, "wrong number of arguments") });
+        }
+
+        // public parameters take instance variables and private parameters witness variables
+        let kinds_match = definition.params.iter().zip(args.iter()).all(|pair| match pair {
+            (Var::Instance(_), Var::Instance(_)) | (Var::Witness(_), Var::Witness(_)) => true,
+            _ => false
+        });
+        if !kinds_match {
+            return Err(ParseError::User { error: (location, "argument does not match the kind of its parameter") });
+        }
+
+        let body = definition.body.map_vars(&|var: &Var| {
+            definition.params.iter().position(|param| param == var).map_or(var.clone(), |position| args[position].clone())
+        });
+        let label = label.unwrap_or_else(|| {
+            let mut calls = self.calls.borrow_mut();
+            let count = calls.entry(String::from(name)).or_insert(0);
+            *count += 1;
+            format!("{}{}", name, *count - 1)
+        });
+        let body = body.prefix_labels(&label);
+        Ok(body.clauses().iter().cloned().zip(body.labels().iter().cloned())
+            .map(|(clause, label)| (label, clause))
+            .collect())
+    }
+
+    /// add the definitions of the file at `directory/path`, a file is only included once
+    ///
+    /// Paths within the included file are relative to its own directory. Without a directory
+    /// no file is read and the INCLUDE is rejected.
+    pub fn include<T>(
+        &self,
+        location: usize,
+        directory: Option<&Path>,
+        path: &str
+    ) -> Result<(), ParseError<usize, T, GrammarError>> {
+        let directory = match directory {
+            Some(directory) => directory,
+            None => {
+                let error = GadgetError::InvalidStatement(String::from("files are only included by parse_statement_in"));
+                *self.include_error.borrow_mut() = Some(GadgetError::Include { path: String::from(path), error: Box::new(error) });
+                return Err(ParseError::User { error: (location, "invalid included file") });
+            }
+        };
+        let file = directory.join(path);
+        if !self.included.borrow_mut().insert(file.canonicalize().unwrap_or(file.clone())) {
+            return Ok(());
+        }
+
+        let gadgets = read_to_string(&file)
+            .map_err(|_| ParseError::User { error: (location, "unable to read the included file") })?;
+        match parse_statement_with(&gadgets, Some(file.parent().unwrap_or(Path::new(""))), self) {
+            Ok(statement) if statement.clauses().is_empty() => Ok(()),
+            Ok(_) => Err(ParseError::User { error: (location, "an included file may only contain definitions") }),
+            Err(error) => {
+                *self.include_error.borrow_mut() = Some(GadgetError::Include { path: String::from(path), error: Box::new(error) });
+                Err(ParseError::User { error: (location, "invalid included file") })
+            }
+        }
+    }
+
+    /// error within the last included file, which replaces the error at the INCLUDE directive
+    pub fn take_include_error(&self) -> Option<GadgetError> {
+        self.include_error.borrow_mut().take()
+    }
+}
@@ -1,2 +1,4 @@
 pub mod ast;
-pub mod assignment_parser;
\ No newline at end of file
+pub mod assignment_parser;
+pub mod statement_parser;
+pub mod definitions;
\ No newline at end of file
@@ -0,0 +1,472 @@
+use statement::{Statement, Clause};
+use lalrpop::ast::Var;
+use lalrpop::definitions::Definitions;
+use error::{GadgetError, GrammarError, Input, ParseContext};
+
+use lalrpop_util::ParseError;
+
+use std::fmt;
+use std::path::Path;
+
+// lalrpop parsers
+lalrpop_mod!(gadget_grammar, "/lalrpop/gadget_grammar.rs");
+
+/// a clause of a statement together with its optional label
+pub type LabeledClause = (Option<String>, Clause);
+
+/// replaces every line break before the text is parsed
+const LINE_BREAK: char = ';';
+
+/// parse a gadgets string into a statement, comments and blank lines are skipped
+///
+/// Gadgets end at a line break, while AND(..) and OR(..) may span any number of lines. The index
+/// of a gadget is derived from the text representation of the parsed statement, so adding or
+/// removing comments and blank lines does not change the names of derived commitments.
+/// Definitions are expanded while parsing. No file is read, an INCLUDE is rejected, see `parse_statement_in`.
+pub fn parse_statement(gadgets: &str) -> Result<Statement, GadgetError> {
+    parse_statement_with(gadgets, None, &Definitions::default())
+}
+
+/// parse a gadgets string whose INCLUDE paths are relative to the given directory
+pub fn parse_statement_in(gadgets: &str, directory: &Path) -> Result<Statement, GadgetError> {
+    parse_statement_with(gadgets, Some(directory), &Definitions::default())
+}
+
+/// parse a gadgets string, adding to and expanding the given definitions
+///
+/// Files are only included if a directory is given.
+pub fn parse_statement_with(gadgets: &str, directory: Option<&Path>, definitions: &Definitions) -> Result<Statement, GadgetError> {
+    gadget_grammar::StatementParser::new().parse(definitions, directory, &tokenizable(gadgets))
+        .map_err(|error| definitions.take_include_error().unwrap_or_else(|| statement_error(gadgets, error)))
+}
+
+/// statement of the given clauses, in the given order
+pub fn conjunction(clauses: Vec<LabeledClause>) -> Statement {
+    clauses.into_iter().fold(Statement::new(), |statement, (label, clause)| labeled(statement.clause(clause), label))
+}
+
+/// THRESHOLD of the given branches, k is located at the given byte offset
+pub fn threshold<T>(location: usize, k: &str, branches: Vec<Statement>) -> Result<Vec<LabeledClause>, ParseError<usize, T, GrammarError>> {
+    match k.parse::<usize>() {
+        Ok(k) if k <= branches.len() => Ok(vec![(None, Clause::Threshold(k, branches))]),
+        _ => Err(ParseError::User { error: (location, "threshold exceeds the number of branches") })
+    }
+}
+
+/// operand that is not a bare instance variable, located at the given byte offset
+pub fn witness_operand<T>(location: usize, var: Var) -> Result<Var, ParseError<usize, T, GrammarError>> {
+    match var {
+        Var::Instance(_) => Err(ParseError::User { error: (location, "expected a witness variable or an expression") }),
+        var => Ok(var)
+    }
+}
+
+/// operands of a comparison of which at least one is no bare instance variable
+///
+/// The location is the byte offset of the right operand.
+pub fn with_witness<T>(location: usize, left: Var, right: Var) -> Result<(Var, Var), ParseError<usize, T, GrammarError>> {
+    match left {
+        Var::Instance(_) => Ok((left, witness_operand(location, right)?)),
+        left => Ok((left, right))
+    }
+}
+
+/// operands of EQUALS and UNEQUAL with a witness on the left hand side, two instances are rejected
+///
+/// The location is the byte offset of the right operand.
+pub fn witness_first<T>(location: usize, left: Var, right: Var) -> Result<(Var, Var), ParseError<usize, T, GrammarError>> {
+    match with_witness(location, left, right)? {
+        (left @ Var::Instance(_), right @ Var::Witness(_)) => Ok((right, left)),
+        operands => Ok(operands)
+    }
+}
+
+fn labeled(statement: Statement, label: Option<String>) -> Statement {
+    match label {
+        // the grammar only labels gadgets, which is never rejected
+        Some(label) => statement.label(&label).expect("labeled gadget"),
+        None => statement
+    }
+}
+
+/// blank out comments and replace line breaks by a token the grammar can match
+///
+/// Every byte keeps its offset, a line break is appended to end the last line.
+fn tokenizable(gadgets: &str) -> String {
+    let mut text = String::with_capacity(gadgets.len() + 1);
+    for line in gadgets.split('\n') {
+        let code = strip_comment(line);
+        text.push_str(code);
+        text.push_str(&" ".repeat(line.len() - code.len()));
+        text.push(LINE_BREAK);
+    }
+    text
+}
+
+/// remove a # or // comment and trailing whitespace from the line
+fn strip_comment(line: &str) -> &str {
+    let end = [line.find('#'), line.find("//")].iter()
+        .flatten()
+        .min()
+        .cloned()
+        .unwrap_or(line.len());
+    line[..end].trim_end()
+}
+
+/// (1-based) line and column of a byte offset
+fn position(gadgets: &str, offset: usize) -> (usize, usize) {
+    let before = &gadgets[..offset.min(gadgets.len())];
+    let line_start = before.rfind('\n').map_or(0, |position| position + 1);
+    (before.matches('\n').count() + 1, before.len() - line_start + 1)
+}
+
+/// convert an error of the statement parser, locations are byte offsets within the whole gadgets
+fn statement_error<T: fmt::Display>(gadgets: &str, error: ParseError<usize, T, GrammarError>) -> GadgetError {
+    let location = match &error {
+        // the appended line break ends every gadget, only an open AND, OR or bracket remains
+        ParseError::UnrecognizedEOF { .. } => return GadgetError::UnexpectedEndOfInput,
+        ParseError::InvalidToken { location } | ParseError::User { error: (location, _) } => *location,
+        ParseError::UnrecognizedToken { token: (location, _, _), .. } | ParseError::ExtraToken { token: (location, _, _) } => *location
+    };
+    let (line, column) = position(gadgets, location);
+    let line_text = gadgets.split('\n').nth(line - 1).unwrap_or("");
+    let line_start = location.min(gadgets.len()) - (column - 1);
+
+    match error {
+        ParseError::UnrecognizedToken { token: (_, token, _), expected } => {
+            let token = token.to_string();
+
+            // an identifier at the start of a line where a gadget is expected, unless it is a misspelled label
+            let first = line_text[..column - 1].trim().is_empty();
+            let identifier = token.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if first && identifier && !line_text.contains(':') && expected.iter().any(|e| e == "\"BOUND\"") {
+                return GadgetError::UnknownGadget { line, op: token };
+            }
+
+            let end_of_line = format!("\"{}\"", LINE_BREAK);
+            let token = if token == LINE_BREAK.to_string() { None } else { Some(token) };
+            let expected: Vec<String> = expected.into_iter()
+                .map(|e| if e == end_of_line { String::from("end of line") } else { e })
+                .collect();
+
+            let unexpected = token.as_ref().map_or(String::from("end of line"), |token| format!("token {}", token));
+            let message = format!("unexpected {}, expected one of {}", unexpected, expected.join(", "));
+            let context = ParseContext { input: Input::Gadgets, source: String::from(line_text), token, expected, hint: None };
+            GadgetError::parse(line - 1, column - 1, message, context)
+        },
+        error => GadgetError::from_parse_error(Input::Gadgets, line - 1, line_text,
+            error.map_location(|location| location.saturating_sub(line_start)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expression::Expression;
+
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_parse_statement_nested_or() {
+        let gadgets = "EQUALS W0 W1\nOR\n[\n{\nOR\n[\n{\nEQUALS W0 W2\n}\n]\n}\n{\nEQUALS W0 W2\n}\n]";
+        let statement = parse_statement(gadgets).unwrap();
+
+        let nested = Statement::new().or(vec![Statement::new().equals(Var::witness(0), Var::witness(2))]);
+        let expected = Statement::new()
+            .equals(Var::witness(0), Var::witness(1))
+            .or(vec![nested, Statement::new().equals(Var::witness(0), Var::witness(2))]);
+        assert_eq!(statement, expected);
+        assert_eq!(statement.lines(), gadgets.lines().count());
+    }
+
+    #[test]
+    fn test_parse_statement_comments() {
+        let gadgets = "# equal witnesses\n\nEQUALS W0 W1  // trailing comment\nOR # no tokens after OR\n[\n  {\t\n\n\
+            \t// indented comment\n    EQUALS W0 W2\n  }\n]\n\n";
+        let statement = parse_statement(gadgets).unwrap();
+        assert_eq!(statement, parse_statement("EQUALS W0 W1\nOR\n[\n{\nEQUALS W0 W2\n}\n]").unwrap());
+        assert_eq!(statement.lines(), 7);
+
+        match parse_statement("# comment\n\nBOUND W1 I0 W2 # comment") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (3, 13)),
+            _ => panic!("expected parse error")
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_named_vars() {
+        let statement = parse_statement("BOUND credit_score $min $max\nEQUALS $limit W0\nSET_MEMBER Wage I0 $limit").unwrap();
+        let expected = Statement::new()
+            .bound(Var::private("credit_score"), Var::public("min"), Var::public("max"))
+            .equals(Var::witness(0), Var::public("limit"))
+            .set_member(Var::private("Wage"), vec![Var::instance(0), Var::public("limit")]);
+        assert_eq!(statement, expected);
+        assert_eq!(statement.to_string().parse::<Statement>().unwrap(), statement);
+
+        match parse_statement("BOUND credit_score $min max") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (1, 25)),
+            _ => panic!("expected parse error")
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_labels() {
+        let statement = parse_statement("age_check: BOUND W1 I0 I1\nOR\n[\n{\n  _equal :EQUALS W0 W1\n}\n]").unwrap();
+        assert_eq!(statement.gadget_labels(0), vec![(0, String::from("age_check")), (4, String::from("_equal"))]);
+
+        match parse_statement("age check: BOUND W1 I0 I1") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (1, 1)),
+            _ => panic!("expected parse error")
+        }
+        match parse_statement("EQUALS W0 W1\nchoice: OR\n[\n]") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (2, 9)),
+            _ => panic!("expected parse error")
+        }
+        match parse_statement("age_check: BOUND W1 I0 W2") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (1, 24)),
+            _ => panic!("expected parse error")
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_expressions() {
+        let blocks = parse_statement("OR\n[\n{\nBOUND W1 I0 I1\nHASH W2 W1\n}\n{\nEQUALS W0 W2\n}\n]").unwrap();
+        assert_eq!(parse_statement("OR(AND(BOUND W1 I0 I1, HASH W2 W1), EQUALS W0 W2)").unwrap(), blocks);
+
+        let gadgets = "OR(  # first branch\n  AND(\n    BOUND W1 I0 I1\n\n    HASH W2 W1,\n  )\n  // second branch\n  EQUALS W0 W2\n)";
+        assert_eq!(parse_statement(gadgets).unwrap(), blocks);
+
+        // an AND is part of the surrounding conjunction, an OR within an OR stays nested
+        let statement = parse_statement("AND(EQUALS W0 W1, first: LESS_THAN W0 W1)\nOR(OR(EQUALS W0 W2, EQUALS W0 W1), EQUALS W0 W2)").unwrap();
+        let expected = Statement::new()
+            .equals(Var::witness(0), Var::witness(1))
+            .less_than(Var::witness(0), Var::witness(1))
+            .label("first").unwrap()
+            .or(vec![
+                Statement::new().or(vec![
+                    Statement::new().equals(Var::witness(0), Var::witness(2)),
+                    Statement::new().equals(Var::witness(0), Var::witness(1))
+                ]),
+                Statement::new().equals(Var::witness(0), Var::witness(2))
+            ]);
+        assert_eq!(statement, expected);
+    }
+
+    #[test]
+    fn test_parse_statement_expression_errors() {
+        assert_eq!(parse_statement("OR(EQUALS W0 W1,\nEQUALS W0 W2"), Err(GadgetError::UnexpectedEndOfInput));
+        match parse_statement("OR(EQUALS W0 W1 EQUALS W0 W2)") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (1, 17)),
+            _ => panic!("expected parse error")
+        }
+        match parse_statement("BOUND W1 I0") {
+            Err(GadgetError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (2, 14));
+                assert!(message.starts_with("unexpected end of line"));
+            },
+            _ => panic!("expected parse error")
+        }
+
+        // a line break ends the set of a SET_MEMBER
+        assert_eq!(
+            parse_statement("SET_MEMBER W0 I0 I1\nFOO W1"),
+            Err(GadgetError::UnknownGadget { line: 2, op: String::from("FOO") })
+        );
+    }
+
+    #[test]
+    fn test_parse_statement_arithmetic() {
+        let statement = parse_statement("LESS_THAN W1 + W2 I0\nEQUALS I5 2*W1 - I5\nBOUND (W1*100)/W2 I1 I2\nUNEQUAL I0 W3").unwrap();
+        let w1 = Expression::from(Var::witness(1));
+        let expected = Statement::new()
+            .less_than((w1.clone() + Var::witness(2)).into(), Var::instance(0))
+            .equals(Var::instance(5), (Expression::from(2u64) * Var::witness(1) - Var::instance(5)).into())
+            .bound((w1 * 100u64 / Var::witness(2)).into(), Var::instance(1), Var::instance(2))
+            .unequal(Var::witness(3), Var::instance(0));
+        assert_eq!(statement, expected);
+        assert_eq!(statement.to_string(), "LESS_THAN W1 + W2 I0\nEQUALS I5 2*W1 - I5\nBOUND W1*100/W2 I1 I2\nUNEQUAL W3 I0\n");
+        assert_eq!(statement.to_string().parse::<Statement>().unwrap(), statement);
+        let names: Vec<String> = statement.vars().iter().map(|var| var.to_string()).collect();
+        assert_eq!(names, vec!["I0", "I1", "I2", "I5", "W1", "W2", "W3"]);
+
+        // parentheses only group, a single variable stays a variable
+        assert_eq!(parse_statement("EQUALS (W0) W1").unwrap(), parse_statement("EQUALS W0 W1").unwrap());
+
+        for (gadgets, column) in vec![("EQUALS I0 I1", 11), ("BOUND I0 I1 I2", 7), ("LESS_THAN I0 $limit", 14)] {
+            match parse_statement(gadgets) {
+                Err(GadgetError::Parse { line, column: found, message, .. }) => {
+                    assert_eq!((line, found), (1, column));
+                    assert_eq!(message, "expected a witness variable or an expression");
+                },
+                _ => panic!("expected parse error")
+            }
+        }
+        match parse_statement("BOUND W1 - -W2 I1 I2") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (1, 12)),
+            _ => panic!("expected parse error")
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_threshold() {
+        let branches = vec![
+            Statement::new().equals(Var::witness(0), Var::witness(1)),
+            Statement::new()
+                .equals(Var::witness(0), Var::witness(2))
+                .less_than(Var::witness(0), Var::witness(1)),
+            Statement::new().unequal(Var::witness(0), Var::witness(2))
+        ];
+        let expected = Statement::new().threshold(2, branches).unwrap();
+
+        let blocks = "THRESHOLD 2\n[\n{\nEQUALS W0 W1\n}\n{\nEQUALS W0 W2\nLESS_THAN W0 W1\n}\n{\nUNEQUAL W0 W2\n}\n]";
+        assert_eq!(parse_statement(blocks).unwrap(), expected);
+        let expression = "THRESHOLD(2,\n  EQUALS W0 W1\n  AND(EQUALS W0 W2, LESS_THAN W0 W1)\n  UNEQUAL W0 W2\n)";
+        assert_eq!(parse_statement(expression).unwrap(), expected);
+
+        match parse_statement("EQUALS W0 W1\nTHRESHOLD(3, EQUALS W0 W1, EQUALS W0 W2)") {
+            Err(GadgetError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (2, 11));
+                assert_eq!(message, "threshold exceeds the number of branches");
+            },
+            _ => panic!("expected parse error")
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_definitions() {
+        let gadgets = "DEFINE credential(attribute, image, $min, $max) {\n    HASH image attribute\n    age: BOUND attribute $min $max\n}\n\
+            alice: CALL credential(W1, W2, I0, I1)\nOR(CALL credential(W3, W4, I0, $max), EQUALS W0 W3)";
+        let expected = Statement::new()
+            .hash(Var::witness(2), Var::witness(1))
+            .bound(Var::witness(1), Var::instance(0), Var::instance(1))
+            .label("alice_age").unwrap()
+            .or(vec![
+                Statement::new()
+                    .hash(Var::witness(4), Var::witness(3))
+                    .bound(Var::witness(3), Var::instance(0), Var::public("max"))
+                    .label("credential0_age").unwrap(),
+                Statement::new().equals(Var::witness(0), Var::witness(3))
+            ]);
+        assert_eq!(parse_statement(gadgets).unwrap(), expected);
+
+        let included = parse_statement_in("INCLUDE \"tests/resources/definitions.gadgets\"\nbob: CALL credential(W1, W2, I0, I1)").unwrap();
+        assert_eq!(included, Statement::new()
+            .hash(Var::witness(2), Var::witness(1))
+            .bound(Var::witness(1), Var::instance(0), Var::instance(1))
+            .label("bob_age").unwrap());
+
+        // a definition may call the definitions above it
+        let nested = "INCLUDE \"tests/resources/definitions.gadgets\"\nDEFINE between(low, value, high) {\n\
+            CALL ordered(low, value)\nCALL ordered(value, high)\n}\nCALL between(W0, W1, W2)";
+        assert_eq!(parse_statement_in(nested, Path::new("")).unwrap(), Statement::new()
+            .less_than(Var::witness(0), Var::witness(1))
+            .less_than(Var::witness(1), Var::witness(2)));
+    }
+
+    #[test]
+    fn test_parse_statement_definition_errors() {
+        let definition = "DEFINE ordered(low, $high) {\nLESS_THAN low $high\n}\n";
+        let errors = vec![
+            ("CALL ordered(W0, I0)\nDEFINE ordered(low, $high) {\nLESS_THAN low $high\n}", (1, 6), "unknown definition"),
+            ("CALL ordered(W0)", (4, 6), "wrong number of arguments"),
+            ("CALL ordered(W0, W1)", (4, 6), "argument does not match the kind of its parameter"),
+            ("DEFINE ordered(low, high) {\n}", (4, 8), "duplicate definition"),
+            ("DEFINE twice(low, low) {\n}", (4, 8), "duplicate parameter"),
+            ("INCLUDE \"tests/resources/missing.gadgets\"", (4, 9), "unable to read the included file"),
+            ("INCLUDE \"tests/resources/or.gadgets\"", (4, 9), "an included file may only contain definitions")
+        ];
+        for (gadgets, position, expected) in errors {
+            let gadgets = if position.0 == 1 { String::from(gadgets) } else { format!("{}{}", definition, gadgets) };
+            match parse_statement_in(&gadgets, Path::new("")) {
+                Err(GadgetError::Parse { line, column, message, .. }) => {
+                    assert_eq!((line, column), position);
+                    assert_eq!(message, expected);
+                },
+                error => panic!("expected parse error, got {:?}", error)
+            }
+        }
+
+        // definitions are only allowed at the top level
+        match parse_statement("OR(EQUALS W0 W1, DEFINE ordered(low, high) {\n}\n)") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (1, 18)),
+            _ => panic!("expected parse error")
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_include_error() {
+        let directory = env::temp_dir().join("bulletproofs_gadgets_include_error");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("library.gadgets"), "DEFINE ordered(low, high) {\n  LESS_THAN low I0 I1\n}").unwrap();
+
+        match parse_statement_in("EQUALS W0 W1\nINCLUDE \"library.gadgets\"", &directory) {
+            Err(GadgetError::Include { path, error }) => {
+                assert_eq!(path, "library.gadgets");
+                match *error {
+                    GadgetError::Parse { line, column, .. } => assert_eq!((line, column), (2, 20)),
+                    error => panic!("expected parse error, got {:?}", error)
+                }
+            },
+            error => panic!("expected include error, got {:?}", error)
+        }
+
+        // without a directory no file is read
+        fs::write(directory.join("ordered.gadgets"), "DEFINE ordered(low, high) {\n  LESS_THAN low high\n}").unwrap();
+        let gadgets = format!("INCLUDE \"{}\"\nCALL ordered(W0, W1)", directory.join("ordered.gadgets").display());
+        assert!(parse_statement_in(&gadgets, Path::new("")).is_ok());
+        match parse_statement(&gadgets) {
+            Err(GadgetError::Include { error, .. }) => match *error {
+                GadgetError::InvalidStatement(_) => (),
+                error => panic!("expected invalid statement, got {:?}", error)
+            },
+            error => panic!("expected include error, got {:?}", error)
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_diagnostics() {
+        let error = parse_statement("EQUALS W0 W1\nage: BOUND W1 I0 W2").unwrap_err();
+        let rendered = error.render("example");
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(lines[0].starts_with("error: unexpected token W2, expected one of"));
+        assert_eq!(&lines[1..], &[
+            " --> example.gadgets:2:18",
+            "  |",
+            "2 | age: BOUND W1 I0 W2",
+            "  |                  ^^",
+            "  = hint: the bounds of BOUND are instance variables, e.g. I0 or $max"
+        ]);
+
+        match parse_statement("BOUND credit_score $min max") {
+            Err(GadgetError::Parse { context, .. }) => {
+                assert_eq!(context.input, Input::Gadgets);
+                assert_eq!(context.token, Some(String::from("max")));
+                assert!(!context.expected.is_empty());
+                assert_eq!(context.hint, Some(String::from("the bounds of BOUND are instance variables, named ones start with $, e.g. $max")));
+            },
+            _ => panic!("expected parse error")
+        }
+        match parse_statement("BOUND W1 I0") {
+            Err(GadgetError::Parse { context, .. }) => assert_eq!((context.token, context.hint), (None, None)),
+            _ => panic!("expected parse error")
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_errors() {
+        assert_eq!(
+            parse_statement("EQUALS W0 W1\nFOO W1"),
+            Err(GadgetError::UnknownGadget { line: 2, op: String::from("FOO") })
+        );
+        assert_eq!(parse_statement("OR\n[\n{\nEQUALS W0 W1\n}"), Err(GadgetError::UnexpectedEndOfInput));
+        match parse_statement("OR\n[\nEQUALS W0 W1\n]") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (3, 1)),
+            _ => panic!("expected parse error")
+        }
+        match parse_statement("BOUND W1 I0 W2") {
+            Err(GadgetError::Parse { line, column, .. }) => assert_eq!((line, column), (1, 13)),
+            _ => panic!("expected parse error")
+        }
+    }
+}
@@ -12,6 +12,14 @@ extern crate hex;
 extern crate regex;
 #[macro_use]
 extern crate lalrpop_util;
+#[macro_use]
+extern crate lazy_static;
+#[cfg(feature = "serialization")]
+extern crate serde;
+#[cfg(feature = "serialization")]
+extern crate serde_json;
+#[cfg(feature = "serialization")]
+extern crate serde_cbor;
 
 //------------------------------------------------------------------------
 // Modules containing macros
@@ -27,6 +35,9 @@ pub mod merkle_tree;
 pub mod commitments;
 pub mod bounds_check;
 pub mod mimc_hash;
+pub mod sha256_hash;
+pub mod poseidon_hash;
+pub mod hash;
 pub mod equality;
 pub mod inequality;
 pub mod less_than;
@@ -36,6 +47,19 @@ pub mod gadget;
 pub mod conversions;
 pub mod cs_buffer;
 pub mod utils;
+pub mod error;
+pub mod statement;
+pub mod expression;
+pub mod evaluator;
+pub mod artifacts;
+pub mod bundle;
+pub mod transcript;
+pub mod circuit;
+pub mod generators;
+pub mod analysis;
+pub mod validation;
+#[cfg(feature = "serialization")]
+pub mod serialization;
 pub mod lalrpop;
 pub mod prove;
 pub mod verify;
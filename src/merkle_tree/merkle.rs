@@ -0,0 +1,244 @@
+use curve25519_dalek::scalar::Scalar;
+use hash::CircuitHash;
+use mimc_hash::mimc_hash_gadget::MimcHash256;
+use statement::{Statement, Tree};
+use lalrpop::ast::Var;
+use conversions::scalar_to_be;
+use error::GadgetError;
+
+use std::marker::PhantomData;
+
+/// A merkle tree outside of the constraint system, hashed like the trees of MERKLE and MERKLE_PATH.
+///
+/// Every leaf is hashed with `H` and every node is the sponge of its two children, as in
+/// `MerkleTree256`. The number of leaves is a power of two, so that every leaf has an
+/// authentication path of the same depth.
+pub struct MerkleTree<H: CircuitHash = MimcHash256> {
+    leaves: Vec<Vec<u8>>,
+    /// hashes of every level, from the hashes of the leaves up to the root
+    levels: Vec<Vec<Scalar>>,
+    hash: PhantomData<H>
+}
+
+/// Gadget and assignments proving that a leaf is in a tree, the lines of the `.gadgets`, `.inst` and `.wtns` files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeEntries {
+    pub statement: Statement,
+    pub instance: String,
+    pub witness: String
+}
+
+impl TreeEntries {
+    fn assign(&mut self, var: &Var, bytes: &Vec<u8>) -> Result<(), GadgetError> {
+        let entry = format!("{} = 0x{}\n", var, hex::encode(bytes));
+        match var {
+            Var::Instance(_) => self.instance.push_str(&entry),
+            Var::Witness(_) => self.witness.push_str(&entry),
+            _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
+        }
+        Ok(())
+    }
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<Vec<u8>>) -> Result<MerkleTree, GadgetError> {
+        MerkleTree::with_hash(leaves)
+    }
+}
+
+impl<H: CircuitHash> MerkleTree<H> {
+    /// merkle tree with another hash function, e.g. `MerkleTree::<PoseidonHash>::with_hash(..)`
+    pub fn with_hash(leaves: Vec<Vec<u8>>) -> Result<MerkleTree<H>, GadgetError> {
+        if leaves.len() < 2 || !leaves.len().is_power_of_two() {
+            return Err(GadgetError::InvalidTree(format!("{} leaves, expected a power of two greater than one", leaves.len())));
+        }
+        if let Some(index) = leaves.iter().position(|leaf| leaf.is_empty()) {
+            return Err(GadgetError::InvalidTree(format!("leaf {} is empty", index)));
+        }
+
+        let mut levels: Vec<Vec<Scalar>> = vec![leaves.iter().map(|leaf| H::hash(leaf)).collect()];
+        while levels.last().unwrap().len() > 1 {
+            let level: Vec<Scalar> = levels.last().unwrap().chunks(2).map(|pair| H::native_sponge(&pair.to_vec())).collect();
+            levels.push(level);
+        }
+
+        Ok(MerkleTree {
+            leaves,
+            levels,
+            hash: PhantomData
+        })
+    }
+
+    pub fn root(&self) -> Scalar {
+        self.levels[self.depth()][0]
+    }
+
+    /// number of levels below the root
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    pub fn leaves(&self) -> &Vec<Vec<u8>> {
+        &self.leaves
+    }
+
+    /// replace the leaf at `index` and rehash the nodes above it
+    pub fn update(&mut self, index: usize, leaf: Vec<u8>) -> Result<(), GadgetError> {
+        self.check_index(index)?;
+        if leaf.is_empty() {
+            return Err(GadgetError::InvalidTree(format!("leaf {} is empty", index)));
+        }
+
+        self.levels[0][index] = H::hash(&leaf);
+        self.leaves[index] = leaf;
+
+        let mut position = index;
+        for level in 0..self.depth() {
+            let left = position & !1;
+            let node = H::native_sponge(&vec![self.levels[level][left], self.levels[level][left + 1]]);
+            position /= 2;
+            self.levels[level + 1][position] = node;
+        }
+        Ok(())
+    }
+
+    /// siblings of the leaf at `index` from the leaf level upwards
+    pub fn siblings(&self, index: usize) -> Result<Vec<Scalar>, GadgetError> {
+        self.check_index(index)?;
+        Ok(self.levels[..self.depth()].iter()
+            .enumerate()
+            .map(|(level, hashes)| hashes[(index >> level) ^ 1])
+            .collect())
+    }
+
+    /// authentication path of MERKLE_PATH, the index of the leaf followed by its siblings
+    pub fn path(&self, index: usize) -> Result<Vec<u8>, GadgetError> {
+        let mut path: Vec<u8> = scalar_to_be(&Scalar::from(index as u64));
+        for sibling in self.siblings(index)? {
+            path.extend(scalar_to_be(&sibling));
+        }
+        Ok(path)
+    }
+
+    /// MERKLE_PATH for the leaf at `index`, which only reveals the root and the depth of the tree
+    pub fn merkle_path(&self, index: usize, root: Var, leaf: Var, path: Var) -> Result<TreeEntries, GadgetError> {
+        let statement = Statement::new()
+            .merkle_path(root.clone(), leaf.clone(), path.clone(), self.depth())?
+            .with_hash(H::FUNCTION)?;
+
+        let authentication_path = self.path(index)?;
+        let mut entries = TreeEntries { statement, instance: String::new(), witness: String::new() };
+        entries.assign(&root, &scalar_to_be(&self.root()))?;
+        entries.assign(&leaf, &self.leaves[index])?;
+        entries.assign(&path, &authentication_path)?;
+        Ok(entries)
+    }
+
+    /// MERKLE spelling out the whole tree, every leaf is assigned to the variable at its position
+    pub fn merkle(&self, root: Var, leaves: Vec<Var>) -> Result<TreeEntries, GadgetError> {
+        if leaves.len() != self.leaves.len() {
+            return Err(GadgetError::InvalidTree(format!("{} variables for {} leaves", leaves.len(), self.leaves.len())));
+        }
+
+        let mut nodes: Vec<Tree> = leaves.iter().cloned().map(Tree::Leaf).collect();
+        while nodes.len() > 1 {
+            nodes = nodes.chunks(2).map(|pair| Tree::node(pair[0].clone(), pair[1].clone())).collect();
+        }
+        let statement = Statement::new().merkle(root.clone(), nodes.remove(0)).with_hash(H::FUNCTION)?;
+
+        let mut entries = TreeEntries { statement, instance: String::new(), witness: String::new() };
+        entries.assign(&root, &scalar_to_be(&self.root()))?;
+        for (var, leaf) in leaves.iter().zip(self.leaves.iter()) {
+            entries.assign(var, leaf)?;
+        }
+        Ok(entries)
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), GadgetError> {
+        if index >= self.leaves.len() {
+            return Err(GadgetError::InvalidTree(format!("leaf {} is out of range of {} leaves", index, self.leaves.len())));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prove::prove;
+    use verify::verify;
+    use poseidon_hash::poseidon_hash_gadget::PoseidonHash;
+
+    fn prove_and_verify(entries: TreeEntries) -> bool {
+        let gadgets = entries.statement.to_string();
+        let mut commitments = String::new();
+        let proof = prove("MerkleTree", entries.instance.clone(), entries.witness, gadgets.clone(), &mut commitments).unwrap();
+        verify("MerkleTree", entries.instance, proof, commitments, gadgets).unwrap()
+    }
+
+    #[test]
+    fn test_merkle_tree_root() {
+        // the roots of tests/resources/merkle_tree.inst
+        let john: Vec<u8> = b"John".to_vec();
+        let doe: Vec<u8> = b"Doe".to_vec();
+
+        let root = |leaves: Vec<Vec<u8>>| hex::encode(scalar_to_be(&MerkleTree::new(leaves).unwrap().root()));
+        assert_eq!(root(vec![john.clone(), john.clone()]), "0e65ad60f91829a76f08c39e4eec78c82dd0686c733ec5afc25ca28ae4628898");
+        assert_eq!(root(vec![doe.clone(), doe.clone()]), "0cdc849ef63dd4e0d3a984a1c8f3bcbe4ae59c378b8d1726181433511a37e8b9");
+        assert_eq!(root(vec![john.clone(), john, doe.clone(), doe]), "0b33a0e69996bf60542d94951136e4246b15591e3e47d7aeb1a7822ee96101c8");
+    }
+
+    #[test]
+    fn test_merkle_tree_update() {
+        let leaves: Vec<Vec<u8>> = (1..9u8).map(|leaf| vec![leaf; 33]).collect();
+        let mut tree = MerkleTree::<PoseidonHash>::with_hash(leaves.clone()).unwrap();
+        tree.update(5, vec![0xff]).unwrap();
+
+        let mut updated = leaves;
+        updated[5] = vec![0xff];
+        assert_eq!(tree.root(), MerkleTree::<PoseidonHash>::with_hash(updated).unwrap().root());
+        assert_eq!(tree.siblings(5).unwrap()[0], PoseidonHash::hash(&vec![5u8; 33]));
+        assert_eq!(tree.path(5).unwrap().len(), 32 * 4);
+    }
+
+    #[test]
+    fn test_merkle_tree_errors() {
+        let leaf: Vec<u8> = b"John".to_vec();
+        assert!(MerkleTree::new(vec![leaf.clone()]).is_err());
+        assert!(MerkleTree::new(vec![leaf.clone(); 3]).is_err());
+        assert!(MerkleTree::new(vec![leaf.clone(), Vec::new()]).is_err());
+
+        let mut tree = MerkleTree::new(vec![leaf.clone(); 4]).unwrap();
+        let root = tree.root();
+        assert!(tree.update(4, leaf.clone()).is_err());
+        assert!(tree.update(1, Vec::new()).is_err());
+        assert_eq!(tree.root(), root);
+
+        assert!(tree.siblings(4).is_err());
+        assert!(tree.merkle_path(4, Var::instance(0), Var::witness(0), Var::witness(1)).is_err());
+        assert!(tree.merkle_path(0, Var::instance(0), Var::Commitment(String::from("C0")), Var::witness(1)).is_err());
+        assert!(tree.merkle(Var::instance(0), vec![Var::witness(0), Var::witness(1)]).is_err());
+    }
+
+    #[test]
+    fn test_merkle_tree_path_prove() {
+        let leaves: Vec<Vec<u8>> = (1..9u8).map(|leaf| vec![leaf; 40]).collect();
+        let tree = MerkleTree::<PoseidonHash>::with_hash(leaves).unwrap();
+
+        let entries = tree.merkle_path(5, Var::instance(0), Var::witness(0), Var::witness(1)).unwrap();
+        assert_eq!(entries.statement.to_string(), "MERKLE_PATH[poseidon] I0 W0 W1 3\n");
+        assert!(entries.witness.starts_with(&format!("W0 = 0x{}\nW1 = 0x", "06".repeat(40))));
+        assert!(prove_and_verify(entries));
+    }
+
+    #[test]
+    fn test_merkle_tree_entries_prove() {
+        let leaves: Vec<Vec<u8>> = vec![b"John".to_vec(), b"Jane".to_vec(), b"Doe".to_vec(), b"Roe".to_vec()];
+        let tree = MerkleTree::<PoseidonHash>::with_hash(leaves).unwrap();
+
+        let entries = tree.merkle(Var::instance(0), vec![Var::witness(0), Var::instance(1), Var::instance(2), Var::instance(3)]).unwrap();
+        assert_eq!(entries.statement.to_string(), "MERKLE[poseidon] I0 ((W0 I1) (I2 I3))\n");
+        assert_eq!(entries.witness, "W0 = 0x4a6f686e\n");
+        assert!(prove_and_verify(entries));
+    }
+}
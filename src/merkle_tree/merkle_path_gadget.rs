@@ -0,0 +1,188 @@
+use bulletproofs::r1cs::{ConstraintSystem, Variable, LinearCombination};
+use curve25519_dalek::scalar::Scalar;
+use gadget::Gadget;
+use hash::CircuitHash;
+use mimc_hash::mimc_hash_gadget::MimcHash256;
+
+/// levels of an authentication path at most, the index of a leaf fits into 64 bits
+pub const MAX_DEPTH: usize = 64;
+
+/// Membership of a leaf in a merkle tree of fixed depth, proven along its authentication path.
+///
+/// The witnesses are the index of the leaf followed by the siblings from the leaf level upwards.
+/// The derived witnesses are the bits of the index, which select at every level whether the
+/// current node is the left or the right child, so neither the position nor the shape is revealed.
+pub struct MerklePath<H: CircuitHash = MimcHash256> {
+    root: LinearCombination,
+    leaf: LinearCombination,
+    hash: H
+}
+
+impl<H: CircuitHash> Gadget for MerklePath<H> {
+    /// bits of the index, one per level starting at the leaf
+    fn preprocess(&self, witnesses: &Vec<Scalar>) -> Vec<Scalar> {
+        let index: &[u8; 32] = match witnesses.first() {
+            Some(index) => index.as_bytes(),
+            None => return Vec::new()
+        };
+
+        (0..witnesses.len() - 1)
+            .map(|level| Scalar::from(((index[level / 8] >> (level % 8)) & 1u8) as u64))
+            .collect()
+    }
+
+    fn assemble(
+        &self,
+        cs: &mut dyn ConstraintSystem,
+        witnesses: &Vec<Variable>,
+        derived_witnesses: &Vec<(Option<Scalar>, Variable)>
+    ) {
+        let (committed_index, siblings) = match witnesses.split_first() {
+            Some(split) => split,
+            None => {
+                // without an index there is no path to the root, 1 = 0 can not be satisfied
+                cs.constrain(Scalar::one().into());
+                return;
+            }
+        };
+
+        let mut node: LinearCombination = self.leaf.clone();
+        let mut index: LinearCombination = Scalar::zero().into();
+        let mut exp_2 = Scalar::one();
+
+        for (sibling, (_, bit)) in siblings.iter().zip(derived_witnesses.iter()) {
+            let sibling: LinearCombination = (*sibling).into();
+            let bit: LinearCombination = (*bit).into();
+
+            // show that either bit or (1 - bit) is 0
+            let one: LinearCombination = Scalar::one().into();
+            let (_, _, should_be_zero) = cs.multiply(one - bit.clone(), bit.clone());
+            cs.constrain(should_be_zero.into());
+
+            // swap = bit * (sibling - node), the node is the right child if the bit is set
+            let (_, _, swap) = cs.multiply(bit.clone(), sibling.clone() - node.clone());
+            let left: LinearCombination = node.clone() + swap;
+            let right: LinearCombination = sibling - swap;
+
+            node = self.hash.sponge(cs, &vec![left, right]);
+
+            index = index + bit * exp_2;
+            exp_2 = exp_2 + exp_2;
+        }
+
+        // constrain index = Sum(b_i * 2^i), the committed index has no more bits than levels
+        cs.constrain(index - *committed_index);
+
+        // constrain node - root = 0 <=> node = root
+        cs.constrain(node - self.root.clone());
+    }
+}
+
+impl MerklePath {
+    pub fn new(root: LinearCombination, leaf: LinearCombination) -> MerklePath {
+        MerklePath::with_hash(root, leaf)
+    }
+}
+
+impl<H: CircuitHash> MerklePath<H> {
+    /// authentication path with another hash function, e.g. `MerklePath::<PoseidonHash>::with_hash(..)`
+    pub fn with_hash(root: LinearCombination, leaf: LinearCombination) -> MerklePath<H> {
+        MerklePath {
+            root,
+            leaf,
+            hash: H::with_image(Scalar::zero().into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use merlin::Transcript;
+    use commitments::{commit_all_single, verifier_commit};
+    use conversions::scalar_to_be;
+    use bulletproofs::{BulletproofGens, PedersenGens};
+    use bulletproofs::r1cs::{Prover, Verifier};
+    use poseidon_hash::poseidon_hash_gadget::PoseidonHash;
+
+    /// root of a tree of the given leaves along with the index and siblings of a leaf
+    fn tree<H: CircuitHash>(leaves: &Vec<Scalar>, index: usize) -> (Scalar, Vec<Vec<u8>>) {
+        let mut path: Vec<Vec<u8>> = vec![scalar_to_be(&Scalar::from(index as u64))];
+        let mut level: Vec<Scalar> = leaves.clone();
+        let mut position = index;
+
+        while level.len() > 1 {
+            path.push(scalar_to_be(&level[position ^ 1]));
+            level = level.chunks(2).map(|pair| H::native_sponge(&pair.to_vec())).collect();
+            position /= 2;
+        }
+
+        (level[0], path)
+    }
+
+    fn prove_and_verify<H: CircuitHash>(root: Scalar, leaf: Scalar, path: &Vec<Vec<u8>>, gens: usize) -> bool {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(gens, 1);
+
+        let mut prover_transcript = Transcript::new(b"MerklePath");
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let (_, leaf_commitments, leaf_vars) = commit_all_single(&mut prover, &vec![scalar_to_be(&leaf)]);
+        let (scalars, witness_commitments, variables) = commit_all_single(&mut prover, path);
+
+        let gadget = MerklePath::<H>::with_hash(root.into(), leaf_vars[0].into());
+        let (derived_commitments, derived_witnesses) = gadget.setup(&mut prover, &scalars);
+        gadget.prove(&mut prover, &variables, &derived_witnesses);
+        let proof = prover.prove(&bp_gens).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"MerklePath");
+        let mut verifier = Verifier::new(&mut verifier_transcript);
+        let leaf_vars: Vec<Variable> = verifier_commit(&mut verifier, leaf_commitments);
+        let witness_vars: Vec<Variable> = verifier_commit(&mut verifier, witness_commitments);
+        let derived_vars: Vec<Variable> = verifier_commit(&mut verifier, derived_commitments);
+
+        let gadget = MerklePath::<H>::with_hash(root.into(), leaf_vars[0].into());
+        gadget.verify(&mut verifier, &witness_vars, &derived_vars);
+        verifier.verify(&proof, &pc_gens, &bp_gens).is_ok()
+    }
+
+    #[test]
+    fn test_merkle_path_gadget() {
+        let leaves: Vec<Scalar> = (0..8u64).map(|leaf| Scalar::from(leaf * 7 + 3)).collect();
+
+        for index in vec![0, 5, 6] {
+            let (root, path) = tree::<PoseidonHash>(&leaves, index);
+            assert!(prove_and_verify::<PoseidonHash>(root, leaves[index], &path, 1024));
+        }
+    }
+
+    #[test]
+    fn test_merkle_path_gadget_wrong_index() {
+        let leaves: Vec<Scalar> = (0..4u64).map(Scalar::from).collect();
+        let (root, mut path) = tree::<PoseidonHash>(&leaves, 2);
+
+        // the siblings of leaf 2 do not lead to the root from position 3
+        path[0] = scalar_to_be(&Scalar::from(3u64));
+        assert!(!prove_and_verify::<PoseidonHash>(root, leaves[2], &path, 1024));
+
+        // an index with more bits than levels is rejected
+        path[0] = scalar_to_be(&Scalar::from(6u64));
+        assert!(!prove_and_verify::<PoseidonHash>(root, leaves[2], &path, 1024));
+    }
+
+    #[test]
+    fn test_merkle_path_gadget_empty_path() {
+        let gadget = MerklePath::<PoseidonHash>::with_hash(Scalar::one().into(), Scalar::one().into());
+        assert!(gadget.preprocess(&Vec::new()).is_empty());
+
+        // the leaf equals the root, but there is no path between them
+        assert!(!prove_and_verify::<PoseidonHash>(Scalar::one(), Scalar::one(), &Vec::new(), 1024));
+    }
+
+    #[test]
+    fn test_merkle_path_gadget_mimc() {
+        let leaves: Vec<Scalar> = (0..4u64).map(Scalar::from).collect();
+        let (root, path) = tree::<MimcHash256>(&leaves, 1);
+        assert!(prove_and_verify::<MimcHash256>(root, leaves[1], &path, 8192));
+    }
+}
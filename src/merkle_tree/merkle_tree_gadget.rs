@@ -1,6 +1,7 @@
 use bulletproofs::r1cs::{ConstraintSystem, Variable, LinearCombination};
 use curve25519_dalek::scalar::Scalar;
 use gadget::Gadget;
+use hash::CircuitHash;
 use mimc_hash::mimc_hash_gadget::MimcHash256;
 use std::fmt;
 
@@ -28,15 +29,16 @@ impl fmt::Display for Pattern {
     }
 }
 
-pub struct MerkleTree256 {
+/// Merkle tree whose nodes are computed with `H`, MiMC by default
+pub struct MerkleTree256<H: CircuitHash = MimcHash256> {
     root: LinearCombination,
     instance_vars: Vec<LinearCombination>,
     witness_vars: Vec<LinearCombination>,
     pattern: Pattern,
-    gadget: MimcHash256
+    hash: H
 }
 
-impl Gadget for MerkleTree256 {
+impl<H: CircuitHash> Gadget for MerkleTree256<H> {
     fn preprocess(&self, _: &Vec<Scalar>) -> Vec<Scalar> {
         Vec::new()
     }
@@ -63,12 +65,24 @@ impl MerkleTree256 {
         witness_vars: Vec<LinearCombination>, 
         pattern: Pattern
     ) -> MerkleTree256 {
+        MerkleTree256::with_hash(root, instance_vars, witness_vars, pattern)
+    }
+}
+
+impl<H: CircuitHash> MerkleTree256<H> {
+    /// merkle tree with another hash function, e.g. `MerkleTree256::<PoseidonHash>::with_hash(..)`
+    pub fn with_hash(
+        root: LinearCombination, 
+        instance_vars: Vec<LinearCombination>, 
+        witness_vars: Vec<LinearCombination>, 
+        pattern: Pattern
+    ) -> MerkleTree256<H> {
         MerkleTree256 {
             root: root,
             instance_vars: instance_vars,
             witness_vars: witness_vars,
             pattern: pattern,
-            gadget: MimcHash256::init()
+            hash: H::with_image(Scalar::zero().into())
         }
     }
 
@@ -103,7 +117,7 @@ impl MerkleTree256 {
             Pattern::I => preimage = vec![self.next_val(i_vars)]
         }
 
-        self.gadget.mimc_sponge(cs, &preimage)
+        self.hash.sponge(cs, &preimage)
     }
 
     fn next_val(&self, values: &mut Vec<LinearCombination>) -> LinearCombination {
@@ -122,6 +136,7 @@ mod tests {
     use bulletproofs::{BulletproofGens, PedersenGens};
     use bulletproofs::r1cs::{Prover, Verifier};
     use conversions::{vars_to_lc, be_to_scalar};
+    use poseidon_hash::poseidon_hash_gadget::PoseidonHash;
 
     const W1: [u8; 32] = [
         0x05, 0x22, 0xa6, 0x4d, 0x7b, 0x93, 0x1e, 0x21, 
@@ -470,6 +485,45 @@ mod tests {
         assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
     }
 
+    #[test]
+    fn test_merkle_tree_gadget_poseidon() {
+        //         1
+        //        / \
+        //     2        3
+        //    / \      / \
+        //   8   9   10   11
+
+        let node = |left: &[u8; 32], right: &[u8; 32]| PoseidonHash::native_sponge(&vec![be_to_scalar(&left.to_vec()), be_to_scalar(&right.to_vec())]);
+        let root: Scalar = PoseidonHash::native_sponge(&vec![node(&W8, &W9), node(&W10, &W11)]);
+
+        let pattern: Pattern = hash!(hash!(W, W), hash!(W, I));
+
+        let mut witnesses: Vec<Vec<u8>> = Vec::new();
+        witnesses.push(W8.to_vec());
+        witnesses.push(W9.to_vec());
+        witnesses.push(W10.to_vec());
+        let instance_vars: Vec<LinearCombination> = vec![be_to_scalar(&W11.to_vec()).into()];
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(1024, 1);
+
+        let mut prover_transcript = Transcript::new(b"MerkleTree");
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let (_, witness_commitments, variables) = commit_all_single(&mut prover, &witnesses);
+        let gadget = MerkleTree256::<PoseidonHash>::with_hash(root.into(), instance_vars, vars_to_lc(&variables), pattern);
+        gadget.prove(&mut prover, &Vec::new(), &Vec::new());
+        let proof = prover.prove(&bp_gens).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"MerkleTree");
+        let mut verifier = Verifier::new(&mut verifier_transcript);
+        let witness_vars: Vec<Variable> = verifier_commit(&mut verifier, witness_commitments);
+        let derived_vars: Vec<Variable> = verifier_commit(&mut verifier, Vec::new());
+
+        gadget.verify(&mut verifier, &witness_vars, &derived_vars);
+        assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+    }
+
     #[test]
     #[ignore]
     fn test_merkle_tree_gadget_512() {
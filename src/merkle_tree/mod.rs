@@ -1,2 +1,4 @@
 #[macro_use]
-pub mod merkle_tree_gadget;
\ No newline at end of file
+pub mod merkle_tree_gadget;
+pub mod merkle_path_gadget;
+pub mod merkle;
\ No newline at end of file
@@ -1,6 +1,6 @@
 use crate::curve25519_dalek::scalar::Scalar;
 use crate::pkcs7;
-use super::mimc_consts::ROUND_CONSTANTS_769;
+use super::mimc_consts::ROUND_CONSTANTS;
 use conversions::{be_to_scalars, le_to_scalar};
 
 /// MiMC block cipher
@@ -8,7 +8,7 @@ fn mimc_encryption(
     p: &Scalar,
     k: &Scalar,
     rounds: usize,
-    constants: &Vec<Scalar>
+    constants: &[Scalar]
 ) -> Scalar {
     let mut state = p.clone();
 
@@ -26,7 +26,7 @@ fn mimc_encryption(
 fn mimc_sponge_1(
     preimage: &Vec<Scalar>,
     rounds: usize,
-    constants: &Vec<Scalar>
+    constants: &[Scalar]
 ) -> Scalar {
     let key_zero = Scalar::zero();
     let mut state = Scalar::zero();
@@ -44,7 +44,7 @@ fn mimc_sponge_1(
 fn mimc_sponge_2(
     preimage: &Vec<Scalar>,
     rounds: usize,
-    constants: &Vec<Scalar>
+    constants: &[Scalar]
 ) -> Scalar {
     let mut key = Scalar::zero();
     let mut state;
@@ -62,16 +62,16 @@ pub fn mimc_hash(preimage: &Vec<u8>) -> Scalar {
     let mut preimage: Vec<Scalar> = be_to_scalars(preimage);
     pad(&mut preimage); // apply PKCS#7 padding
 
+    mimc_sponge(&preimage)
+}
+
+/// MiMCHash-256b of scalars without padding, as used for the nodes of a merkle tree
+pub fn mimc_sponge(preimage: &Vec<Scalar>) -> Scalar {
     // rounds = ceil((rate + capacity) / log_2(3)) = 486
     const NUM_ROUNDS: usize = 486;
-    
-    let mut round_constants: Vec<Scalar> = Vec::new();
-    for constant in ROUND_CONSTANTS_769.iter() {
-            round_constants.push(Scalar::from_bits(*constant));
-    }
 
     // use constants according to n = rate + capacity = 769
-    mimc_sponge_1(&preimage, NUM_ROUNDS, &round_constants)
+    mimc_sponge_1(preimage, NUM_ROUNDS, &ROUND_CONSTANTS)
 }
 
 fn pad(preimage: &mut Vec<Scalar>) {
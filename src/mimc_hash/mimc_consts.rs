@@ -1,3 +1,18 @@
+use curve25519_dalek::scalar::Scalar;
+
+/// MiMC round constants for n = 769 as scalars, converted at compile time
+pub static ROUND_CONSTANTS: [Scalar; 486] = round_constants();
+
+const fn round_constants() -> [Scalar; 486] {
+    let mut constants = [Scalar::from_bits([0u8; 32]); 486];
+    let mut i = 0;
+    while i < 486 {
+        constants[i] = Scalar::from_bits(ROUND_CONSTANTS_769[i]);
+        i += 1;
+    }
+    constants
+}
+
 // MiMC round constants for n = 769
 pub const ROUND_CONSTANTS_769: [[u8; 32]; 486] = [
     [0xbe, 0x89, 0x76, 0x07, 0x95, 0x4a, 0x90, 0xcc, 0xe6, 0x9e, 0xa5, 0x98, 0xd3, 0x8c, 0xae, 0x1c, 0x99, 0xdc, 0xe5, 0x07, 0x8a, 0xda, 0x96, 0x60, 0xca, 0x6c, 0xc4, 0x28, 0xdc, 0x07, 0xce, 0x00],
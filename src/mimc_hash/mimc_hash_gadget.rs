@@ -1,12 +1,14 @@
 use bulletproofs::r1cs::{ConstraintSystem, Variable, LinearCombination};
 use curve25519_dalek::scalar::Scalar;
 use gadget::Gadget;
+use hash::{CircuitHash, HashFunction};
 use conversions::{le_to_scalar, vars_to_lc};
-use super::mimc_consts::ROUND_CONSTANTS_769;
+use super::mimc::{mimc_hash, mimc_sponge};
+use super::mimc_consts::ROUND_CONSTANTS;
 
 pub struct MimcHash256 {
     image: LinearCombination,
-    round_constants: Vec<Scalar>
+    round_constants: &'static [Scalar]
 }
 
 impl Gadget for MimcHash256 {
@@ -50,6 +52,29 @@ impl Gadget for MimcHash256 {
     }
 }
 
+impl CircuitHash for MimcHash256 {
+    const FUNCTION: HashFunction = HashFunction::MiMC;
+
+    /// the padded last block and the padding
+    const COMMITTED_PADDING: bool = true;
+
+    fn with_image(image: LinearCombination) -> MimcHash256 {
+        MimcHash256::new(image)
+    }
+
+    fn hash(preimage: &Vec<u8>) -> Scalar {
+        mimc_hash(preimage)
+    }
+
+    fn native_sponge(preimage: &Vec<Scalar>) -> Scalar {
+        mimc_sponge(preimage)
+    }
+
+    fn sponge(&self, cs: &mut dyn ConstraintSystem, preimage: &Vec<LinearCombination>) -> LinearCombination {
+        self.mimc_sponge(cs, preimage)
+    }
+}
+
 /// MiMCHash-256b, rate = 256, capacity = 513
 impl MimcHash256 {
     // rounds = ceil((rate + capacity) / log_2(3)) = 486
@@ -70,12 +95,8 @@ impl MimcHash256 {
         }
     }
 
-    pub fn get_round_constants() -> Vec<Scalar> {
-        let mut round_constants: Vec<Scalar> = Vec::new();
-        for constant in ROUND_CONSTANTS_769.iter() {
-            round_constants.push(Scalar::from_bits(*constant));
-        }
-        round_constants
+    pub fn get_round_constants() -> &'static [Scalar] {
+        &ROUND_CONSTANTS
     }
 
     fn pad(
@@ -1,7 +1,67 @@
-use bulletproofs::r1cs::{ConstraintSystem, LinearCombination};
+use bulletproofs::r1cs::{ConstraintSystem, LinearCombination, Variable};
 use cs_buffer::{ConstraintSystemBuffer, Operation};
+use curve25519_dalek::scalar::Scalar;
+use error::GadgetError;
 
-pub fn or(main: &mut dyn ConstraintSystem, buffer: &dyn ConstraintSystemBuffer) {
+/// At least one of the branches cached in the buffer holds, see `threshold`.
+pub fn or(main: &mut dyn ConstraintSystem, buffer: &dyn ConstraintSystemBuffer, selection: Option<&[bool]>) -> Result<(), GadgetError> {
+    threshold(main, buffer, 1, selection)
+}
+
+/// At least k of the branches cached in the buffer hold, without revealing which ones.
+///
+/// Every branch gets a secret selector bit and each of its constraints is multiplied by the
+/// selector, so only the constraints of selected branches have to hold. The selectors sum up to
+/// k. This costs one multiplier for every branch and for every constraint within a branch.
+///
+/// The prover passes the branches to select, the verifier `None`. An OR without branches does
+/// not add any constraints, a k above the number of branches is rejected.
+pub fn threshold(
+    main: &mut dyn ConstraintSystem,
+    buffer: &dyn ConstraintSystemBuffer,
+    k: usize,
+    selection: Option<&[bool]>
+) -> Result<(), GadgetError> {
+    let branches = buffer.buffer_cache().len();
+    if branches > 0 && k > branches {
+        return Err(GadgetError::InvalidStatement(format!("threshold {} exceeds the number of branches {}", k, branches)));
+    }
+
+    let constraints_vec = replay(main, buffer);
+    if constraints_vec.is_empty() {
+        return Ok(());
+    }
+
+    let mut selected = LinearCombination::default();
+    for (branch, constraints) in constraints_vec.into_iter().enumerate() {
+        let selector = allocate_bit(main, selection.map(|selection| selection[branch]));
+        selected = selected + selector;
+
+        // a selected branch has to satisfy all of its constraints
+        for constraint in constraints {
+            let (_, _, product) = main.multiply(selector.into(), constraint);
+            main.constrain(product.into());
+        }
+    }
+
+    main.constrain(selected - Scalar::from(k as u64));
+    Ok(())
+}
+
+/// select the first k of the branches that hold, fewer than k branches result in an invalid proof
+pub fn select(holds: &[bool], k: usize) -> Vec<bool> {
+    let mut remaining = k;
+    holds.iter().map(|holds| {
+        let selected = *holds && remaining > 0;
+        if selected {
+            remaining -= 1;
+        }
+        selected
+    }).collect()
+}
+
+/// replay the multipliers of all branches to main, returns the constraints of every branch
+fn replay(main: &mut dyn ConstraintSystem, buffer: &dyn ConstraintSystemBuffer) -> Vec<Vec<LinearCombination>> {
     let mut constraints_vec: Vec<Vec<LinearCombination>> = Vec::new();
     for operations in buffer.buffer_cache() {
         let mut constraints: Vec<LinearCombination> = Vec::new();
@@ -25,45 +85,23 @@ pub fn or(main: &mut dyn ConstraintSystem, buffer: &dyn ConstraintSystemBuffer)
 
         constraints_vec.push(constraints);
     }
-
-    let cartesian_product: Vec<Vec<LinearCombination>> = cartesian_product(constraints_vec);
-    for constraints in cartesian_product {
-        let mut constraint_product: LinearCombination = constraints[0].clone();
-        for i in 1..constraints.len() {
-            let (_, _, product) = main.multiply(constraint_product.clone(), constraints[i].clone());
-            constraint_product = product.into();
-        }
-        main.constrain(constraint_product);
-    }
+    constraints_vec
 }
 
-/// Computes the Cartesian product of lists[0] * lists[1] * ... * lists[n].
-/// from: https://gist.github.com/kylewlacy/115965b40e02a3325558
-fn partial_cartesian<T: Clone>(a: Vec<Vec<T>>, b: Vec<T>) -> Vec<Vec<T>> {
-    a.into_iter().flat_map(|xs| {
-        b.iter().cloned().map(|y| {
-            let mut vec = xs.clone();
-            vec.push(y);
-            vec
-        }).collect::<Vec<_>>()
-    }).collect()
-}
+/// allocate a variable that is either 0 or 1
+fn allocate_bit(main: &mut dyn ConstraintSystem, bit: Option<bool>) -> Variable {
+    let (a, b, o) = main.allocate_multiplier(bit.map(|bit| {
+        let bit: u8 = bit.into();
+        ((1 - bit).into(), bit.into())
+    })).unwrap();
 
-/// Computes the Cartesian product of lists[0] * lists[1] * ... * lists[n].
-/// from: https://gist.github.com/kylewlacy/115965b40e02a3325558
-fn cartesian_product<T: Clone>(lists: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    match lists.split_first() {
-        Some((first, rest)) => {
-            let init: Vec<Vec<T>> = first.iter().cloned().map(|n| vec![n]).collect();
-
-            rest.iter().cloned().fold(init, |vec, list| {
-                partial_cartesian(vec, list)
-            })
-        },
-        None => {
-            vec![]
-        }
-    }
+    // Enforce a * b = 0, so one of (a,b) is zero
+    main.constrain(o.into());
+
+    // Enforce that a = 1 - b, so they both are 1 or 0.
+    main.constrain(a + (b - 1u8));
+
+    b
 }
 
 #[cfg(test)]
@@ -150,7 +188,7 @@ mod tests {
         let (wtns_coms_3, drvd_coms_3) = mimc_hash_prover(&mut prover_main, &mut prover_buffer, image_3, &preimage_3);
         prover_buffer.rewind();
 
-        or(&mut prover_main, &prover_buffer);
+        or(&mut prover_main, &prover_buffer, Some(&[true, false, false])).unwrap();
 
         // Genereate Proof
         let proof = prover_main.prove(&bp_gens).unwrap();
@@ -184,11 +222,103 @@ mod tests {
         mimc_hash_verifier(&mut verifier_buffer, image_3, &witness_vars_3, &derived_vars_3);
         verifier_buffer.rewind();
 
-        or(&mut verifier_main, &verifier_buffer);
+        or(&mut verifier_main, &verifier_buffer, None).unwrap();
 
         assert!(verifier_main.verify(&proof, &pc_gens, &bp_gens).is_ok());
     }
 
+    #[test]
+    fn test_select() {
+        assert_eq!(select(&[false, true, true, true], 2), vec![false, true, true, false]);
+        assert_eq!(select(&[true, false], 2), vec![true, false]);
+        assert_eq!(select(&[true, true], 0), vec![false, false]);
+    }
+
+    #[test]
+    fn test_branches_hold() {
+        let pc_gens = PedersenGens::default();
+        let mut or_transcript = Transcript::new(b"OrTranscript");
+        let mut prover_buffer = ProverBuffer::new(Prover::new(&pc_gens, &mut or_transcript));
+        prover_buffer.commit(&vec![Scalar::from(3u64), Scalar::from(4u64)]);
+
+        // 3 * 4 = 12
+        let (_, _, product) = prover_buffer.multiply(Variable::Committed(0).into(), Variable::Committed(1).into());
+        prover_buffer.constrain(product - Scalar::from(12u64));
+        prover_buffer.rewind();
+
+        // 3 = 4
+        prover_buffer.constrain(Variable::Committed(0) - Variable::Committed(1));
+        prover_buffer.rewind();
+
+        assert_eq!(prover_buffer.branches_hold(), vec![true, false]);
+    }
+
+    #[test]
+    fn test_threshold_selection() {
+        // branch i holds if values[i + 1] equals values[0]
+        let values = [5u64, 5, 6, 7];
+        assert!(threshold_verifies(&values, 1, &[true, false, false]));
+
+        // a selected branch that does not hold fails the proof
+        assert!(!threshold_verifies(&values, 1, &[false, true, false]));
+        assert!(!threshold_verifies(&values, 1, &[true, true, false]));
+
+        // the selectors have to sum up to k
+        assert!(!threshold_verifies(&values, 1, &[false, false, false]));
+        assert!(!threshold_verifies(&values, 2, &[true, false, false]));
+    }
+
+    #[test]
+    fn test_threshold_fewer_branches_hold() {
+        let values = [5u64, 5, 5, 7];
+        assert!(threshold_verifies(&values, 2, &select(&[true, true, false], 2)));
+
+        // only one of the branches holds, no selection proves 2 out of 3
+        let values = [5u64, 5, 6, 7];
+        assert!(!threshold_verifies(&values, 2, &select(&[true, false, false], 2)));
+        for selection in vec![[true, true, false], [true, false, true], [false, true, true]] {
+            assert!(!threshold_verifies(&values, 2, &selection));
+        }
+    }
+
+    /// prove and verify a threshold over branches that constrain values[i + 1] to equal values[0]
+    fn threshold_verifies(values: &[u64], k: usize, selection: &[bool]) -> bool {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 1);
+        let scalars: Vec<Scalar> = values.iter().map(|value| Scalar::from(*value)).collect();
+
+        let mut main_transcript = Transcript::new(b"Threshold");
+        let mut prover_main = Prover::new(&pc_gens, &mut main_transcript);
+        let commitments: Vec<CompressedRistretto> = scalars.iter()
+            .map(|scalar| prover_main.commit(*scalar, Scalar::from(7u64)).0)
+            .collect();
+
+        let mut or_transcript = Transcript::new(b"OrTranscript");
+        let mut prover_buffer = ProverBuffer::new(Prover::new(&pc_gens, &mut or_transcript));
+        prover_buffer.commit(&scalars);
+        for branch in 1..values.len() {
+            prover_buffer.constrain(Variable::Committed(0) - Variable::Committed(branch));
+            prover_buffer.rewind();
+        }
+        threshold(&mut prover_main, &prover_buffer, k, Some(selection)).unwrap();
+        let proof = prover_main.prove(&bp_gens).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"Threshold");
+        let mut verifier_main = Verifier::new(&mut verifier_transcript);
+        for commitment in commitments {
+            verifier_main.commit(commitment);
+        }
+
+        let mut or_transcript = Transcript::new(b"OrTranscript");
+        let mut verifier_buffer = VerifierBuffer::new(Verifier::new(&mut or_transcript));
+        for branch in 1..values.len() {
+            verifier_buffer.constrain(Variable::Committed(0) - Variable::Committed(branch));
+            verifier_buffer.rewind();
+        }
+        threshold(&mut verifier_main, &verifier_buffer, k, None).unwrap();
+        verifier_main.verify(&proof, &pc_gens, &bp_gens).is_ok()
+    }
+
     fn mimc_hash_prover(
         prover_main: &mut Prover<&mut Transcript>,
         prover_buffer: &mut ProverBuffer,
@@ -0,0 +1,3 @@
+pub mod poseidon;
+mod poseidon_consts;
+pub mod poseidon_hash_gadget;
@@ -0,0 +1,114 @@
+use curve25519_dalek::scalar::Scalar;
+use conversions::be_to_scalars;
+use super::poseidon_consts::{ROUND_CONSTANTS, MDS};
+
+/// number of field elements of the state
+pub const WIDTH: usize = 3;
+
+/// elements absorbed per permutation, the remaining element is the capacity
+pub const RATE: usize = 2;
+
+/// rounds with an S-box on every element, half of them before and half after the partial rounds
+pub const FULL_ROUNDS: usize = 8;
+
+/// rounds with an S-box on the first element only
+pub const PARTIAL_ROUNDS: usize = 57;
+
+/// whether the given round applies the S-box to the whole state
+pub fn is_full_round(round: usize) -> bool {
+    round < FULL_ROUNDS / 2 || round >= FULL_ROUNDS / 2 + PARTIAL_ROUNDS
+}
+
+/// x^5 is a permutation of the scalar field, as 5 does not divide l - 1
+fn sbox(x: Scalar) -> Scalar {
+    let x2 = x * x;
+    x2 * x2 * x
+}
+
+/// Poseidon permutation for t = 3 and alpha = 5
+pub fn permutation(state: &mut [Scalar; WIDTH]) {
+    for (round, constants) in ROUND_CONSTANTS.chunks(WIDTH).enumerate() {
+        for (element, constant) in state.iter_mut().zip(constants) {
+            *element += constant;
+        }
+
+        if is_full_round(round) {
+            for element in state.iter_mut() {
+                *element = sbox(*element);
+            }
+        } else {
+            state[0] = sbox(state[0]);
+        }
+
+        let mut mixed = [Scalar::zero(); WIDTH];
+        for (element, row) in mixed.iter_mut().zip(MDS.iter()) {
+            *element = row.iter().zip(state.iter()).fold(Scalar::zero(), |sum, (m, x)| sum + m * x);
+        }
+        *state = mixed;
+    }
+}
+
+/// Poseidon sponge over scalars. The capacity is initialised with the number of scalars,
+/// which are padded with zeros to a positive multiple of the rate.
+pub fn poseidon_sponge(preimage: &Vec<Scalar>) -> Scalar {
+    let mut state = [Scalar::zero(); WIDTH];
+    state[0] = Scalar::from(preimage.len() as u64);
+
+    for block in pad(preimage, Scalar::zero()).chunks(RATE) {
+        for (element, scalar) in state[1..].iter_mut().zip(block) {
+            *element += scalar.reduce();
+        }
+        permutation(&mut state);
+    }
+
+    state[1]
+}
+
+/// Poseidon hash of the scalars the preimage is committed to
+pub fn poseidon_hash(preimage: &Vec<u8>) -> Scalar {
+    poseidon_sponge(&be_to_scalars(preimage))
+}
+
+/// append `zero` until the length is a positive multiple of the rate
+pub fn pad<T: Clone>(preimage: &Vec<T>, zero: T) -> Vec<T> {
+    let mut padded: Vec<T> = preimage.clone();
+    while padded.is_empty() || padded.len() % RATE != 0 {
+        padded.push(zero.clone());
+    }
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conversions::scalar_to_be;
+
+    #[test]
+    fn test_poseidon_constants() {
+        assert_eq!(ROUND_CONSTANTS.len(), (FULL_ROUNDS + PARTIAL_ROUNDS) * WIDTH);
+        assert_eq!(hex::encode(scalar_to_be(&ROUND_CONSTANTS[0])), "02d3c9c8d37dfdbf16ea08e4a9b159c6df311947b1ae6ff864be4803d0f23e31");
+        assert_eq!(MDS[0][0] * Scalar::from(3u64), Scalar::one());
+    }
+
+    #[test]
+    fn test_poseidon_hash() {
+        let vectors: Vec<(Vec<u8>, &str)> = vec![
+            (b"abc".to_vec(), "0b09978cbcb28d96a3aa9fb5bbe2c88cb5ef4e3db8837626fd532286ec88ff69"),
+            (b"The quick brown fox jumps over the lazy dog".to_vec(),
+                "01619c2b33de897f5a4d9974a27da801c938b878effb2cb35106bf6d2d2dc02d"),
+            (Vec::new(), "017b6ffe48d9fc0abe252480f4db6ce2121c1a2956d1580536757c24847da50c")
+        ];
+
+        for (preimage, image) in vectors {
+            assert_eq!(hex::encode(scalar_to_be(&poseidon_hash(&preimage))), image);
+        }
+    }
+
+    #[test]
+    fn test_poseidon_sponge_length() {
+        // the capacity separates a trailing zero from the padding
+        let one = vec![Scalar::one()];
+        assert_ne!(poseidon_sponge(&one), poseidon_sponge(&vec![Scalar::one(), Scalar::zero()]));
+        assert_eq!(pad(&one, Scalar::zero()).len(), RATE);
+    }
+}
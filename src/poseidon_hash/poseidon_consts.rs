@@ -0,0 +1,83 @@
+use curve25519_dalek::scalar::Scalar;
+use super::poseidon::{WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS};
+
+/// bits of the order of the scalar field
+const FIELD_SIZE: usize = 253;
+
+lazy_static! {
+    /// round constants for all rounds, `WIDTH` per round
+    pub static ref ROUND_CONSTANTS: Vec<Scalar> = round_constants();
+
+    /// MDS matrix of the linear layer
+    pub static ref MDS: [[Scalar; WIDTH]; WIDTH] = mds();
+}
+
+/// Grain LFSR in self-shrinking mode, as used by the reference implementation of the Poseidon paper
+struct Grain {
+    state: Vec<bool>
+}
+
+impl Grain {
+    fn new() -> Grain {
+        // prime field (2 bits), x^alpha S-box (4 bits), field size (12 bits), width (12 bits),
+        // full rounds (10 bits), partial rounds (10 bits) and 30 set bits
+        let mut state: Vec<bool> = vec![false, true, false, false, false, false];
+        for (value, bits) in vec![(FIELD_SIZE, 12), (WIDTH, 12), (FULL_ROUNDS, 10), (PARTIAL_ROUNDS, 10)] {
+            state.extend((0..bits).rev().map(|i| (value >> i) & 1 == 1));
+        }
+        state.extend(vec![true; 30]);
+
+        let mut grain = Grain { state };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.remove(0);
+        self.state.push(bit);
+        bit
+    }
+
+    /// output the second bit of a pair if the first one is set
+    fn next_bit(&mut self) -> bool {
+        while !self.step() {
+            self.step();
+        }
+        self.step()
+    }
+
+    /// sample `FIELD_SIZE` bits (most significant first) until they are a canonical scalar
+    fn next_scalar(&mut self) -> Scalar {
+        loop {
+            let mut bytes = [0u8; 32];
+            for i in (0..FIELD_SIZE).rev() {
+                if self.next_bit() {
+                    bytes[i / 8] |= 1 << (i % 8);
+                }
+            }
+            if let Some(scalar) = Scalar::from_canonical_bytes(bytes) {
+                return scalar;
+            }
+        }
+    }
+}
+
+fn round_constants() -> Vec<Scalar> {
+    let mut grain = Grain::new();
+    (0..(FULL_ROUNDS + PARTIAL_ROUNDS) * WIDTH).map(|_| grain.next_scalar()).collect()
+}
+
+/// Cauchy matrix 1 / (x_i + y_j) with x_i = i and y_j = WIDTH + j
+fn mds() -> [[Scalar; WIDTH]; WIDTH] {
+    let mut matrix = [[Scalar::zero(); WIDTH]; WIDTH];
+    for (i, row) in matrix.iter_mut().enumerate() {
+        for (j, element) in row.iter_mut().enumerate() {
+            *element = Scalar::from((i + WIDTH + j) as u64).invert();
+        }
+    }
+    matrix
+}
@@ -0,0 +1,219 @@
+use bulletproofs::r1cs::{ConstraintSystem, Variable, LinearCombination};
+use curve25519_dalek::scalar::Scalar;
+use gadget::Gadget;
+use hash::{CircuitHash, HashFunction};
+use conversions::vars_to_lc;
+use super::poseidon::{WIDTH, RATE, is_full_round, pad, poseidon_hash, poseidon_sponge};
+use super::poseidon_consts::{ROUND_CONSTANTS, MDS};
+
+pub struct PoseidonHash {
+    image: LinearCombination
+}
+
+impl Gadget for PoseidonHash {
+    /// The padding is public, no derived witnesses are needed
+    fn preprocess(&self, _: &Vec<Scalar>) -> Vec<Scalar> {
+        Vec::new()
+    }
+
+    fn assemble(
+        &self,
+        cs: &mut dyn ConstraintSystem,
+        witnesses: &Vec<Variable>,
+        _: &Vec<(Option<Scalar>, Variable)>
+    ) {
+        let hash_lc: LinearCombination = self.poseidon_sponge(cs, &vars_to_lc(witnesses));
+
+        // constrain hash - image = 0 <=> hash = image
+        cs.constrain(hash_lc - self.image.clone());
+    }
+}
+
+impl CircuitHash for PoseidonHash {
+    const FUNCTION: HashFunction = HashFunction::Poseidon;
+
+    const COMMITTED_PADDING: bool = false;
+
+    fn with_image(image: LinearCombination) -> PoseidonHash {
+        PoseidonHash::new(image)
+    }
+
+    fn hash(preimage: &Vec<u8>) -> Scalar {
+        poseidon_hash(preimage)
+    }
+
+    fn native_sponge(preimage: &Vec<Scalar>) -> Scalar {
+        poseidon_sponge(preimage)
+    }
+
+    fn sponge(&self, cs: &mut dyn ConstraintSystem, preimage: &Vec<LinearCombination>) -> LinearCombination {
+        self.poseidon_sponge(cs, preimage)
+    }
+}
+
+/// Poseidon with t = 3, alpha = 5, 8 full and 57 partial rounds (243 multipliers per permutation)
+impl PoseidonHash {
+    pub fn init() -> PoseidonHash {
+        PoseidonHash {
+            image: Scalar::zero().into()
+        }
+    }
+
+    pub fn new(image: LinearCombination) -> PoseidonHash {
+        PoseidonHash {
+            image
+        }
+    }
+
+    pub fn poseidon_sponge(
+        &self,
+        cs: &mut dyn ConstraintSystem,
+        preimage: &Vec<LinearCombination>
+    ) -> LinearCombination {
+        let mut state: Vec<LinearCombination> = vec![Scalar::zero().into(); WIDTH];
+        state[0] = Scalar::from(preimage.len() as u64).into();
+
+        for block in pad(preimage, Scalar::zero().into()).chunks(RATE) {
+            for (element, lc) in state[1..].iter_mut().zip(block) {
+                *element = element.clone() + lc.clone();
+            }
+            state = self.permutation(cs, state);
+        }
+
+        state[1].clone()
+    }
+
+    fn permutation(
+        &self,
+        cs: &mut dyn ConstraintSystem,
+        elements: Vec<LinearCombination>
+    ) -> Vec<LinearCombination> {
+        let mut state = State::new(elements);
+
+        for (round, constants) in ROUND_CONSTANTS.chunks(WIDTH).enumerate() {
+            for (index, constant) in constants.iter().enumerate() {
+                state.add_constant(index, *constant);
+            }
+
+            let sboxes = if is_full_round(round) { WIDTH } else { 1 };
+            for index in 0..sboxes {
+                let x5 = self.sbox(cs, state.lc(index));
+                state.set(index, x5);
+            }
+
+            state.mix();
+        }
+
+        (0..WIDTH).map(|index| state.lc(index)).collect()
+    }
+
+    /// x^5 using three multipliers
+    fn sbox(
+        &self,
+        cs: &mut dyn ConstraintSystem,
+        x: LinearCombination
+    ) -> LinearCombination {
+        let (x, _, x2) = cs.multiply(x.clone(), x);
+        let (_, _, x4) = cs.multiply(x2.into(), x2.into());
+        let (_, _, x5) = cs.multiply(x4.into(), x.into());
+
+        x5.into()
+    }
+}
+
+/// State of a permutation as coefficients of linear combinations (`terms`), the first of which is one.
+/// Applying the MDS matrix to linear combinations directly would grow them exponentially in the partial rounds.
+struct State {
+    terms: Vec<LinearCombination>,
+    coefficients: Vec<Vec<Scalar>>
+}
+
+impl State {
+    fn new(elements: Vec<LinearCombination>) -> State {
+        let mut state = State {
+            terms: vec![Scalar::one().into()],
+            coefficients: vec![vec![Scalar::zero()]; WIDTH]
+        };
+        for (index, element) in elements.into_iter().enumerate() {
+            state.set(index, element);
+        }
+        state
+    }
+
+    fn add_constant(&mut self, index: usize, constant: Scalar) {
+        self.coefficients[index][0] += constant;
+    }
+
+    /// replace the element at `index` by the given linear combination
+    fn set(&mut self, index: usize, lc: LinearCombination) {
+        self.terms.push(lc);
+        for row in self.coefficients.iter_mut() {
+            row.push(Scalar::zero());
+        }
+        let row = &mut self.coefficients[index];
+        row.iter_mut().for_each(|coefficient| *coefficient = Scalar::zero());
+        *row.last_mut().unwrap() = Scalar::one();
+    }
+
+    fn lc(&self, index: usize) -> LinearCombination {
+        self.terms.iter()
+            .zip(self.coefficients[index].iter())
+            .filter(|(_, coefficient)| **coefficient != Scalar::zero())
+            .fold(Scalar::zero().into(), |sum: LinearCombination, (term, coefficient)| sum + term.clone() * *coefficient)
+    }
+
+    /// multiply the state with the MDS matrix
+    fn mix(&mut self) {
+        let terms = self.terms.len();
+        self.coefficients = MDS.iter()
+            .map(|row| (0..terms)
+                .map(|k| row.iter().zip(self.coefficients.iter()).fold(Scalar::zero(), |sum, (m, c)| sum + m * c[k]))
+                .collect())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use merlin::Transcript;
+    use commitments::{commit, verifier_commit};
+    use bulletproofs::{BulletproofGens, PedersenGens};
+    use bulletproofs::r1cs::{Prover, Verifier};
+
+    fn prove_and_verify(preimage: &Vec<u8>, image: Scalar) -> bool {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(1024, 1);
+
+        let mut prover_transcript = Transcript::new(b"PoseidonHash");
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let gadget = PoseidonHash::new(image.into());
+        let (scalars, witness_commitments, variables) = commit(&mut prover, preimage);
+        let (derived_commitments, derived_witnesses) = gadget.setup(&mut prover, &scalars);
+        gadget.prove(&mut prover, &variables, &derived_witnesses);
+        let proof = prover.prove(&bp_gens).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"PoseidonHash");
+        let mut verifier = Verifier::new(&mut verifier_transcript);
+        let witness_vars: Vec<Variable> = verifier_commit(&mut verifier, witness_commitments);
+        let derived_vars: Vec<Variable> = verifier_commit(&mut verifier, derived_commitments);
+
+        gadget.verify(&mut verifier, &witness_vars, &derived_vars);
+        verifier.verify(&proof, &pc_gens, &bp_gens).is_ok()
+    }
+
+    #[test]
+    fn test_poseidon_hash_gadget() {
+        let preimage: Vec<u8> = b"The quick brown fox jumps over the lazy dog".to_vec();
+        assert!(prove_and_verify(&preimage, poseidon_hash(&preimage)));
+        assert!(!prove_and_verify(&preimage, poseidon_hash(&preimage) + Scalar::one()));
+    }
+
+    #[test]
+    fn test_poseidon_hash_gadget_blocks() {
+        // three scalars are padded to two permutations
+        let preimage: Vec<u8> = (0..70).collect();
+        assert!(prove_and_verify(&preimage, poseidon_hash(&preimage)));
+    }
+}
@@ -10,25 +10,35 @@ use merlin::Transcript;
 
 use gadget::Gadget;
 use merkle_tree::merkle_tree_gadget::MerkleTree256;
+use merkle_tree::merkle_path_gadget::{MerklePath, MAX_DEPTH};
 use bounds_check::bounds_check_gadget::BoundsCheck;
 use mimc_hash::mimc_hash_gadget::MimcHash256;
-use mimc_hash::mimc::mimc_hash;
+use poseidon_hash::poseidon_hash_gadget::PoseidonHash;
+use hash::{HashFunction, CircuitHash};
+use sha256_hash::sha256_gadget::{Sha256, Image};
 use equality::equality_gadget::Equality;
 use less_than::less_than_gadget::LessThan;
 use set_membership::set_membership_gadget::SetMembership;
 use inequality::inequality_gadget::Inequality;
 use conversions::{be_to_scalar, be_to_scalars, scalar_to_be};
 use lalrpop::ast::*;
+use statement::{Statement, Clause, Tree};
+use expression::Operand;
 use lalrpop::assignment_parser::*;
 use commitments::commit_single;
 use cs_buffer::{ConstraintSystemBuffer, ProverBuffer, Operation};
-use or::or_conjunction::or;
+use evaluator::{Evaluator, evaluate};
+use or::or_conjunction::{threshold, select};
+use error::GadgetError;
+use bundle::{ProofBundle, statement_hash, instance_hash};
+use transcript::GadgetTranscript;
+use circuit::Layout;
+use generators::shared_generators;
 
-use std::iter::{Peekable, Enumerate};
-use self::math::round;
+use std::fmt;
+use std::sync::Arc;
 
-// lalrpop parsers
-lalrpop_mod!(gadget_grammar, "/lalrpop/gadget_grammar.rs");
+use self::math::round;
 
 fn round_pow2(num: usize) -> usize {
     2_usize.pow(round::ceil((num as f64).log2(), 0) as u32)
@@ -40,15 +50,87 @@ pub fn prove(
     witness: String,
     gadgets: String,
     commitments: &mut String
-) -> std::result::Result<std::vec::Vec<u8>, Box<dyn std::error::Error>> {
+) -> Result<Vec<u8>, GadgetError> {
+    let statement: Statement = gadgets.parse()?;
+    prove_statement(name, instance, witness, &statement, commitments)
+}
+
+pub fn prove_statement(
+    name: &'static str,
+    instance: String,
+    witness: String,
+    statement: &Statement,
+    commitments: &mut String
+) -> Result<Vec<u8>, GadgetError> {
+    let (proof, _) = prove_with(name, instance, witness, statement, commitments, &shared_generators)?;
+    Ok(proof)
+}
+
+/// prove the gadgets and pack the proof together with its commitments into a bundle
+pub fn prove_bundle(
+    name: &'static str,
+    instance: String,
+    witness: String,
+    gadgets: String
+) -> Result<ProofBundle, GadgetError> {
+    let statement: Statement = gadgets.parse()?;
+    prove_statement_bundle(name, instance, witness, &statement)
+}
+
+pub fn prove_statement_bundle(
+    name: &'static str,
+    instance: String,
+    witness: String,
+    statement: &Statement
+) -> Result<ProofBundle, GadgetError> {
+    let instance_hash = instance_hash(&instance)?;
+
+    let mut commitments = String::new();
+    let (proof, layout) = prove_with(name, instance, witness, statement, &mut commitments, &shared_generators)?;
+
+    Ok(ProofBundle {
+        version: String::from(env!("CARGO_PKG_VERSION")),
+        label: String::from(name),
+        generators: layout.generators,
+        statement_hash: statement_hash(statement),
+        instance_hash,
+        commitments: commitments.parse()?,
+        proof
+    })
+}
+
+/// prove with generators of at least the requested capacity, returns the proof and the layout it was created with
+pub fn prove_with(
+    name: &'static str,
+    instance: String,
+    witness: String,
+    statement: &Statement,
+    commitments: &mut String,
+    generators: &dyn Fn(usize) -> Arc<BulletproofGens>
+) -> Result<(Vec<u8>, Layout), GadgetError> {
+    let assignments = Assignments::for_statement(statement)?;
+    prove_prepared(name, assignments, instance, witness, statement, commitments, generators)
+}
+
+/// prove with assignments prepared by `Assignments::for_statement` for the same statement
+pub fn prove_prepared(
+    name: &'static str,
+    mut assignments: Assignments,
+    instance: String,
+    witness: String,
+    statement: &Statement,
+    commitments: &mut String,
+    generators: &dyn Fn(usize) -> Arc<BulletproofGens>
+) -> Result<(Vec<u8>, Layout), GadgetError> {
+    assignments.parse_instance(instance)?;
+
     // ---------- CREATE PROVER ----------
     let mut transcript = Transcript::new(name.as_bytes());
+    transcript.bind_statement(statement, &assignments);
     let pc_gens = PedersenGens::default();
     let mut prover = Prover::new(&pc_gens, &mut transcript);
 
-    let mut assignments = Assignments::new();
-    assignments.parse_instance(instance).expect("unable to parse provided instance");
-    assignments.parse_witness(witness, &mut prover, commitments).expect("unable to parse provided witness");
+    assignments.parse_witness(witness, &mut prover, commitments)?;
 
     // ---------- CREATE BUFFER ----------
     let buffer_gens = PedersenGens::default();
@@ -59,30 +141,86 @@ pub fn prove(
     assignments.buffer_commit_drvd(&mut prover_buffer);
 
     // ---------- GADGETS ----------
-    let mut iter = gadgets.lines().enumerate().into_iter().peekable();
-    while iter.peek().is_some() {
-        let (index, line) = iter.next().unwrap();
-        let line = line;
-
-        let local_initialization = vec![prover_buffer.buffer().into_iter().map(|op| op.clone()).collect()];
-        parse_conjunction(&mut iter, &line, &mut assignments, &mut prover, &mut prover_buffer, commitments, local_initialization);
-        parse_gadget(&line, &mut assignments, &mut prover, &mut prover_buffer, index, commitments);
-    }
+    prove_clauses(statement, 0, &mut assignments, &mut prover, &mut prover_buffer, commitments, &Vec::new())?;
 
-    assign_buffer(&mut prover, &prover_buffer);
+    assign_buffer(&mut prover, prover_buffer.buffer());
 
     // output number of constraints
     println!("{}", prover.num_constraints());
 
     // ---------- CREATE PROOF ----------
-    let bp_gens = BulletproofGens::new(round_pow2(prover.get_num_multiplications()), 1);
-    let proof = prover.prove(&bp_gens).unwrap();
+    let labels: Vec<String> = read_commitments(commitments)?.into_iter().map(|(label, _)| label).collect();
+    prover.transcript().bind_commitments(&labels);
+    let multipliers = prover.get_num_multiplications();
+    let layout = Layout { commitments: labels, multipliers, generators: round_pow2(multipliers) };
+    let bp_gens = generators(layout.generators);
+    let proof = prover.prove(&bp_gens).map_err(|error| GadgetError::ProofCreation(error.to_string()))?;
+
+    Ok((proof.to_bytes(), layout))
+}
+
+/// A constraint of the statement that does not hold for the given assignments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnsatisfiedConstraint {
+    /// line of the gadget in the text representation of the statement (1-based)
+    pub line: usize,
+    /// keyword of the gadget, e.g. BOUND
+    pub gadget: &'static str,
+    /// names of the variables the gadget refers to
+    pub vars: Vec<String>,
+    /// index of the constraint among the constraints of the gadget
+    pub constraint: usize
+}
+
+impl fmt::Display for UnsatisfiedConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "constraint {} of {} {} at line {} is not satisfied", self.constraint, self.gadget, self.vars.join(" "), self.line)
+    }
+}
+
+/// check the witness against the statement without creating a proof, returns the first unsatisfied constraint
+///
+/// Every constraint is evaluated on the assignments as the prover buffer records it. A gadget within
+/// the branches of an OR or THRESHOLD is only reported if fewer than k of the branches hold.
+pub fn check_witness(
+    statement: &Statement,
+    instance: String,
+    witness: String
+) -> Result<Option<UnsatisfiedConstraint>, GadgetError> {
+    let mut assignments = Assignments::for_statement(statement)?;
+    assignments.parse_instance(instance)?;
 
-    Ok(proof.to_bytes())
+    // ---------- CREATE PROVER ----------
+    let mut transcript = Transcript::new(b"CheckWitness");
+    let pc_gens = PedersenGens::default();
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+
+    let mut commitments = String::new();
+    assignments.parse_witness(witness, &mut prover, &mut commitments)?;
+
+    // ---------- CREATE BUFFER ----------
+    let buffer_gens = PedersenGens::default();
+    let mut buffer_transcript = Transcript::new(b"BufferTranscript");
+    let buffer_prover = Prover::new(&buffer_gens, &mut buffer_transcript);
+    let mut prover_buffer = ProverBuffer::new(buffer_prover);
+    assignments.buffer_commit_wtns(&mut prover_buffer);
+    assignments.buffer_commit_drvd(&mut prover_buffer);
+
+    // ---------- GADGETS ----------
+    let mut evaluator = ProverEvaluator {
+        assignments: &mut assignments,
+        prover: &mut prover,
+        prover_buffer: &mut prover_buffer,
+        commitments: &mut commitments,
+        unsatisfied: None
+    };
+    evaluate(&mut evaluator, statement, 0, &Vec::new())?;
+
+    Ok(evaluator.unsatisfied)
 }
 
-fn assign_buffer(main: &mut dyn ConstraintSystem, buffer: &ProverBuffer) {
-    for operation in buffer.buffer() {
+fn assign_buffer(main: &mut dyn ConstraintSystem, operations: &[Operation]) {
+    for operation in operations {
         match operation {
             Operation::Multiply((left, right)) => {
                 main.multiply(left.clone(), right.clone());
@@ -98,48 +236,142 @@ fn assign_buffer(main: &mut dyn ConstraintSystem, buffer: &ProverBuffer) {
     }
 }
 
-fn parse_gadget(
-    line: &str,
+/// prove all clauses of the statement, starting at the given gadget index
+pub fn prove_clauses(
+    statement: &Statement,
+    index: usize,
     assignments: &mut Assignments,
     prover: &mut Prover<&mut Transcript>,
     prover_buffer: &mut ProverBuffer,
-    index: usize,
-    commitments: &mut String
-) {
-    match get_gadget_op(&String::from(line)) {
-        GadgetOp::Bound => bounds_check_gadget(line, assignments, prover, prover_buffer, index, commitments),
-        GadgetOp::Hash => mimc_hash_gadget(line, assignments, prover, prover_buffer, index, commitments),
-        GadgetOp::Merkle => merkle_tree_gadget(line, assignments, prover, prover_buffer, index, commitments),
-        GadgetOp::Equality => equality_gadget(line, assignments, prover_buffer),
-        GadgetOp::LessThan => less_than_gadget(line, assignments, prover, prover_buffer, index, commitments),
-        GadgetOp::Inequality => inequality_gadget(line, assignments, prover, prover_buffer, index, commitments),
-        GadgetOp::SetMembership => set_membership_gadget(line, assignments, prover, prover_buffer, index, commitments),
-        _ => {}
+    commitments: &mut String,
+    initialization: &[Vec<Operation>]
+) -> Result<(), GadgetError> {
+    let mut evaluator = ProverEvaluator { assignments, prover, prover_buffer, commitments, unsatisfied: None };
+    evaluate(&mut evaluator, statement, index, initialization)
+}
+
+/// evaluates the gadgets on the prover, writing the derived commitments along the way
+struct ProverEvaluator<'a, 'b, 'g, 't> {
+    assignments: &'a mut Assignments,
+    prover: &'a mut Prover<'g, &'t mut Transcript>,
+    prover_buffer: &'a mut ProverBuffer<'b>,
+    commitments: &'a mut String,
+    /// first gadget with a constraint that does not hold
+    unsatisfied: Option<UnsatisfiedConstraint>
+}
+
+impl<'a, 'b, 'g, 't> Evaluator for ProverEvaluator<'a, 'b, 'g, 't> {
+    fn gadget(&mut self, clause: &Clause, index: usize) -> Result<(), GadgetError> {
+        let start = self.prover_buffer.satisfied().len();
+        prove_gadget(clause, self.assignments, self.prover, self.prover_buffer, index, self.commitments)?;
+
+        if self.unsatisfied.is_none() {
+            if let Some(constraint) = self.prover_buffer.satisfied()[start..].iter().position(|holds| !holds) {
+                self.unsatisfied = Some(UnsatisfiedConstraint {
+                    line: index + 1,
+                    gadget: clause.op().as_str(),
+                    vars: clause.vars().iter().map(|var| var.to_string()).collect(),
+                    constraint
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn threshold(
+        &mut self,
+        k: usize,
+        initialization: &[Vec<Operation>],
+        branches: &mut dyn FnMut(&mut dyn Evaluator) -> Result<(), GadgetError>
+    ) -> Result<(), GadgetError> {
+        let or_gens = PedersenGens::default();
+        let mut or_transcript = Transcript::new(b"OrTranscript");
+        let or_prover = Prover::new(&or_gens, &mut or_transcript);
+        let mut prover_buffer = ProverBuffer::new(or_prover);
+        self.assignments.buffer_commit_wtns(&mut prover_buffer);
+        self.assignments.buffer_commit_drvd(&mut prover_buffer);
+        prover_buffer.initialize_from(initialization.to_vec());
+
+        let mut evaluator = ProverEvaluator {
+            assignments: &mut *self.assignments,
+            prover: &mut *self.prover,
+            prover_buffer: &mut prover_buffer,
+            commitments: &mut *self.commitments,
+            unsatisfied: None
+        };
+        branches(&mut evaluator)?;
+        let unsatisfied = evaluator.unsatisfied;
+
+        // fewer than k branches hold, the first unsatisfied gadget within them fails the threshold
+        let holds = prover_buffer.branches_hold();
+        if holds.iter().filter(|holds| **holds).count() < k && self.unsatisfied.is_none() {
+            self.unsatisfied = unsatisfied;
+        }
+        add_commitments_to_parent(self.prover_buffer, &prover_buffer);
+        threshold(self.prover_buffer, &prover_buffer, k, Some(&select(&holds, k)))
+    }
+
+    fn buffer(&self) -> &Vec<Operation> {
+        self.prover_buffer.buffer()
+    }
+
+    fn rewind(&mut self) {
+        self.prover_buffer.rewind();
     }
 }
 
-fn parse_conjunction(
-    iter: &mut Peekable<Enumerate<std::str::Lines>>,
-    line: &str,
+fn prove_gadget(
+    clause: &Clause,
     assignments: &mut Assignments,
     prover: &mut Prover<&mut Transcript>,
     prover_buffer: &mut ProverBuffer,
-    commitments: &mut String,
-    initialization: Vec<Vec<Operation>>
-) {
-    match get_gadget_op(&String::from(line)) {
-        GadgetOp::Or => or_conjunction(iter, assignments, prover, prover_buffer, commitments, initialization),
-        _ => {}
+    index: usize,
+    commitments: &mut String
+) -> Result<(), GadgetError> {
+    match clause.clone() {
+        Clause::Bound(var, min, max) => bounds_check_gadget((var, min, max), assignments, prover, prover_buffer, index, commitments),
+        Clause::Hash(image, preimage, HashFunction::MiMC) => hash_gadget::<MimcHash256>((image, preimage), assignments, prover, prover_buffer, index, commitments),
+        Clause::Hash(image, preimage, HashFunction::Poseidon) => hash_gadget::<PoseidonHash>((image, preimage), assignments, prover, prover_buffer, index, commitments),
+        Clause::Sha256(image, preimage) => sha256_gadget((image, preimage), assignments, prover_buffer),
+        Clause::Merkle(root, tree, HashFunction::MiMC) => merkle_tree_gadget::<MimcHash256>((root, tree), assignments, prover, prover_buffer, index, commitments),
+        Clause::Merkle(root, tree, HashFunction::Poseidon) => merkle_tree_gadget::<PoseidonHash>((root, tree), assignments, prover, prover_buffer, index, commitments),
+        Clause::MerklePath(root, leaf, path, depth, HashFunction::MiMC) =>
+            merkle_path_gadget::<MimcHash256>((root, leaf, path, depth), assignments, prover, prover_buffer, index, commitments),
+        Clause::MerklePath(root, leaf, path, depth, HashFunction::Poseidon) =>
+            merkle_path_gadget::<PoseidonHash>((root, leaf, path, depth), assignments, prover, prover_buffer, index, commitments),
+        Clause::Equality(left, right) => equality_gadget((left, right), assignments, prover_buffer),
+        Clause::LessThan(left, right) => less_than_gadget((left, right), assignments, prover, prover_buffer, index, commitments),
+        Clause::Inequality(left, right) => inequality_gadget((left, right), assignments, prover, prover_buffer, index, commitments),
+        Clause::SetMembership(member, set, HashFunction::MiMC) => set_membership_gadget::<MimcHash256>((member, set), assignments, prover, prover_buffer, index, commitments),
+        Clause::SetMembership(member, set, HashFunction::Poseidon) => set_membership_gadget::<PoseidonHash>((member, set), assignments, prover, prover_buffer, index, commitments),
+        Clause::Or(_) | Clause::Threshold(..) => Ok(())
     }
 }
 
-fn get_gadget_op(line: &String) -> GadgetOp {
-    let gadget_op = line.split_whitespace().next().unwrap_or("");
-    let error = format!("unknown gadget: {}", &gadget_op);
-    gadget_op.parse::<GadgetOp>().expect(&error)
+/// linear combination and assignment of a variable or expression that fits into a single scalar,
+/// the multipliers of an expression are allocated in the buffer
+fn scalar_operand(var: Var, assignments: &Assignments, prover_buffer: &mut ProverBuffer) -> Result<Operand, GadgetError> {
+    let scalar = |var: &Var| -> Result<Operand, GadgetError> {
+        match var {
+            Var::Witness(_) => {
+                let (scalars, _, vars, _) = assignments.get_witness(var.clone(), Some(&assert_witness_32))?;
+                Ok((vars[0].into(), Some(scalars[0])))
+            },
+            Var::Instance(_) => {
+                let scalar = be_to_scalar(&assignments.get_instance(var.clone(), Some(&assert_32))?);
+                Ok((scalar.into(), Some(scalar)))
+            },
+            _ => Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
+        }
+    };
+
+    match var {
+        Var::Expression(expression) => expression.lower(prover_buffer, &scalar),
+        var => scalar(&var)
+    }
 }
 
-fn hash_witness(
+fn hash_witness<H: CircuitHash>(
     prover: &mut Prover<&mut Transcript>,
     prover_buffer: &mut ProverBuffer,
     var: Var,
@@ -147,10 +379,10 @@ fn hash_witness(
     index: usize,
     subroutine: usize,
     commitments: &mut String
-) -> (Scalar, Variable) {
+) -> Result<(Scalar, Variable), GadgetError> {
     let mut hash_commitments = Vec::new();
-    let (preimage_scalars, _, preimage_vars, preimage_bytes) = assignments.get_witness(var, None);
-    let image: Scalar = mimc_hash(&preimage_bytes);
+    let (preimage_scalars, _, preimage_vars, preimage_bytes) = assignments.get_witness(var, None)?;
+    let image: Scalar = H::hash(&preimage_bytes);
 
     let (image_scalar, image_com, image_var) = commit_single(prover, &scalar_to_be(&image));
     let image_drvd = vec![(Some(image_scalar), image_var)];
@@ -158,7 +390,7 @@ fn hash_witness(
     assignments.cache_derived_wtns(image_drvd);
     hash_commitments.push(image_com);
 
-    let hash_gadget = MimcHash256::new(image_var.into());
+    let hash_gadget = H::with_image(image_var.into());
 
     let (derived_coms, derived_wtns) = hash_gadget.setup(prover, &preimage_scalars);
     prover_buffer.commit_drvd(&derived_wtns);
@@ -166,57 +398,19 @@ fn hash_witness(
     derived_coms.into_iter().for_each(|com| hash_commitments.push(com));
 
     assignments.cache_derived_wtns(derived_wtns);
-    assignments.parse_derived_witness(hash_commitments.clone(), index, subroutine, commitments).expect("unable to generate commitments");
+    assignments.parse_derived_witness(hash_commitments.clone(), index, subroutine, commitments)?;
 
-    (image_scalar, image_var)
+    Ok((image_scalar, image_var))
 }
 
-fn hash_instance(
+fn hash_instance<H: CircuitHash>(
     var: Var,
     assignments: &Assignments
-) -> (Scalar, LinearCombination) {
-    let instance_var: Vec<u8> = assignments.get_instance(var, None);
-    let image = mimc_hash(&instance_var);
-
-    (image, image.into())
-}
-
-fn or_conjunction(
-    iter: &mut Peekable<Enumerate<std::str::Lines>>,
-    assignments: &mut Assignments,
-    prover: &mut Prover<&mut Transcript>,
-    parent_prover_buffer: &mut ProverBuffer,
-    commitments: &mut String,
-    initialization: Vec<Vec<Operation>>
-) {
-    let or_gens = PedersenGens::default();
-    let mut or_transcript = Transcript::new(b"OrTranscript");
-    let or_prover = Prover::new(&or_gens, &mut or_transcript);
-    let mut prover_buffer = ProverBuffer::new(or_prover);
-    assignments.buffer_commit_wtns(&mut prover_buffer);
-    assignments.buffer_commit_drvd(&mut prover_buffer);
-    prover_buffer.initialize_from(initialization.clone());
-
-    if iter.peek().is_none() {
-        panic!("unexpected end of input");
-    }
+) -> Result<(Scalar, LinearCombination), GadgetError> {
+    let instance_var: Vec<u8> = assignments.get_instance(var, None)?;
+    let image = H::hash(&instance_var);
 
-    while iter.peek().is_some() {
-        let (local_index, line) = iter.next().unwrap();
-        let line = line;
-        let gadget_op = get_gadget_op(&String::from(line));
-        if gadget_op.is_array_end() { break; }
-        if gadget_op.is_block_end() { prover_buffer.rewind(); }
-        else {
-            let mut local_initialization: Vec<Vec<Operation>> = initialization.clone();
-            local_initialization.push(prover_buffer.buffer().into_iter().map(|op| op.clone()).collect());
-            parse_conjunction(iter, &line, assignments, prover, &mut prover_buffer, commitments, local_initialization);
-            parse_gadget(&line, assignments, prover, &mut prover_buffer, local_index, commitments);
-        }
-    }
-
-    add_commitments_to_parent(parent_prover_buffer, &prover_buffer);
-    or(parent_prover_buffer, &prover_buffer);
+    Ok((image, image.into()))
 }
 
 fn add_commitments_to_parent(parent: &mut ProverBuffer, buffer: &ProverBuffer) {
@@ -233,198 +427,287 @@ fn add_commitments_to_parent(parent: &mut ProverBuffer, buffer: &ProverBuffer) {
 }
 
 fn bounds_check_gadget(
-    line: &str,
+    args: (Var, Var, Var),
     assignments: &mut Assignments,
     prover: &mut Prover<&mut Transcript>,
     prover_buffer: &mut ProverBuffer,
     index: usize,
     commitments: &mut String
-) {
-    let bound_parser = gadget_grammar::BoundGadgetParser::new();
-    let (var, min, max) = bound_parser.parse(line).unwrap();
-
-    let var = assignments.get_witness(var, Some(&assert_witness_32));
-    let min: Vec<u8> = assignments.get_instance(min, Some(&assert_32));
-    let max: Vec<u8> = assignments.get_instance(max, Some(&assert_32));
+) -> Result<(), GadgetError> {
+    let (var, min, max) = args;
+
+    let min: Vec<u8> = assignments.get_instance(min, Some(&assert_32))?;
+    let max: Vec<u8> = assignments.get_instance(max, Some(&assert_32))?;
+
+    let (scalars, vars, expression) = if var.is_expression() {
+        let (lc, assignment) = scalar_operand(var, assignments, prover_buffer)?;
+        (assignment.into_iter().collect(), Vec::new(), Some(lc))
+    } else {
+        let (scalars, _, vars, _) = assignments.get_witness(var, Some(&assert_witness_32))?;
+        (scalars, vars, None)
+    };
 
     let gadget = BoundsCheck::new(&min, &max);
 
-    let (derived_coms, derived_wtns) = gadget.setup(prover, &var.0);
+    let (derived_coms, derived_wtns) = gadget.setup(prover, &scalars);
     prover_buffer.commit_drvd(&derived_wtns);
-    gadget.prove(prover_buffer, &var.2, &derived_wtns);
+    gadget.prove(prover_buffer, &vars, &derived_wtns);
+
+    // an expression is no committed variable, tie it to the first derived witness (var - min)
+    if let Some(lc) = expression {
+        prover_buffer.constrain(lc - be_to_scalar(&min) - derived_wtns[0].1);
+    }
 
     assignments.cache_derived_wtns(derived_wtns);
-    assignments.parse_derived_witness(derived_coms, index, 0, commitments).expect("unable to generate commitments");
+    assignments.parse_derived_witness(derived_coms, index, 0, commitments)
 }
 
-fn mimc_hash_gadget(
-    line: &str,
+fn hash_gadget<H: CircuitHash>(
+    args: (Var, Var),
     assignments: &mut Assignments,
     prover: &mut Prover<&mut Transcript>,
     prover_buffer: &mut ProverBuffer,
     index: usize,
     commitments: &mut String
-) {
-    let hash_parser = gadget_grammar::HashGadgetParser::new();
-    let (image, preimage) = hash_parser.parse(line).unwrap();
+) -> Result<(), GadgetError> {
+    let (image, preimage) = args;
 
     let image: LinearCombination = match image {
-        Var::Witness(_) => assignments.get_witness(image, Some(&assert_witness_32)).2[0].into(),
-        Var::Instance(_) => be_to_scalar(&assignments.get_instance(image, Some(&assert_32))).into(),
-        _ => panic!("invalid state")
+        Var::Witness(_) => assignments.get_witness(image, Some(&assert_witness_32))?.2[0].into(),
+        Var::Instance(_) => be_to_scalar(&assignments.get_instance(image, Some(&assert_32))?).into(),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
     };
 
-    let preimage = assignments.get_witness(preimage, None);
+    let preimage = assignments.get_witness(preimage, None)?;
 
-    let gadget = MimcHash256::new(image);
+    let gadget = H::with_image(image);
     let (derived_coms, derived_wtns) = gadget.setup(prover, &preimage.0);
 
     prover_buffer.commit_drvd(&derived_wtns);
     gadget.prove(prover_buffer, &preimage.2, &derived_wtns);
 
     assignments.cache_derived_wtns(derived_wtns);
-    assignments.parse_derived_witness(derived_coms, index, 0, commitments).expect("unable to generate commitments");
+    assignments.parse_derived_witness(derived_coms, index, 0, commitments)
+}
+
+fn sha256_gadget(
+    args: (Var, Var),
+    assignments: &Assignments,
+    prover_buffer: &mut ProverBuffer
+) -> Result<(), GadgetError> {
+    let (image, preimage) = args;
+
+    let image: Image = match image {
+        Var::Witness(_) => {
+            // a committed image spans two chunks of the SHA256 gadget
+            let (_, _, vars, bytes) = assignments.get_witness(image.clone(), None)?;
+            assert_32(image.to_string(), &bytes)?;
+            Image::Committed(vars.into_iter().map(|var| var.into()).collect())
+        },
+        Var::Instance(_) => Image::Public(assignments.get_instance(image, Some(&assert_32))?),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
+    };
+
+    let (_, _, preimage_vars, preimage_bytes) = assignments.get_witness(preimage, None)?;
+
+    let gadget = Sha256::new(image, preimage_vars.len(), Some(preimage_bytes))?;
+    gadget.prove(prover_buffer, &preimage_vars, &Vec::new());
+    Ok(())
 }
 
-fn merkle_tree_gadget(
-    line: &str,
+fn merkle_tree_gadget<H: CircuitHash>(
+    args: (Var, Tree),
     assignments: &mut Assignments,
     prover: &mut Prover<&mut Transcript>,
     prover_buffer: &mut ProverBuffer,
     index: usize,
     commitments: &mut String
-) {
-    let merkle_parser = gadget_grammar::MerkleGadgetParser::new();
-    let (root, instance_vars, witness_vars, pattern) = merkle_parser.parse(line).unwrap();
+) -> Result<(), GadgetError> {
+    let (root, tree) = args;
+    let (instance_vars, witness_vars, pattern) = tree.flatten();
 
     let root: LinearCombination = match root {
-        Var::Witness(_) => assignments.get_witness(root, Some(&assert_witness_32)).2[0].into(),
-        Var::Instance(_) => be_to_scalar(&assignments.get_instance(root, Some(&assert_32))).into(),
-        _ => panic!("invalid state")
+        Var::Witness(_) => assignments.get_witness(root, Some(&assert_witness_32))?.2[0].into(),
+        Var::Instance(_) => be_to_scalar(&assignments.get_instance(root, Some(&assert_32))?).into(),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
     };
 
     let instance_vars: Vec<LinearCombination> = instance_vars.into_iter()
-        .map(|var| mimc_hash(&assignments.get_instance(var.clone(), None)).into()).collect();
+        .map(|var| Ok(H::hash(&assignments.get_instance(var.clone(), None)?).into()))
+        .collect::<Result<_, GadgetError>>()?;
 
     let mut hash_number = 0;
     let mut witness_lcs: Vec<LinearCombination> = Vec::new();
 
     for witness_var in witness_vars {
-        let (_, var) = hash_witness(prover, prover_buffer, witness_var, assignments, index, hash_number, commitments);
+        let (_, var) = hash_witness::<H>(prover, prover_buffer, witness_var, assignments, index, hash_number, commitments)?;
         hash_number += 1;
         witness_lcs.push(var.into());
     }
 
-    let gadget = MerkleTree256::new(root, instance_vars, witness_lcs, pattern.clone());
+    let gadget = MerkleTree256::<H>::with_hash(root, instance_vars, witness_lcs, pattern.clone());
 
     gadget.prove(prover_buffer, &Vec::new(), &Vec::new());
+    Ok(())
+}
+
+fn merkle_path_gadget<H: CircuitHash>(
+    args: (Var, Var, Var, usize),
+    assignments: &mut Assignments,
+    prover: &mut Prover<&mut Transcript>,
+    prover_buffer: &mut ProverBuffer,
+    index: usize,
+    commitments: &mut String
+) -> Result<(), GadgetError> {
+    let (root, leaf, path, depth) = args;
+
+    // an index of at most 32 bytes and one sibling of 32 bytes per level, as reported by the error
+    let (path_scalars, _, path_vars, path_bytes) = assignments.get_witness(path.clone(), None)?;
+    if depth == 0 || depth > MAX_DEPTH || path_bytes.len() <= 32 * depth || path_bytes.len() > 32 * (depth + 1) {
+        return Err(GadgetError::PathLength { name: path.to_string(), depth, actual: path_bytes.len() });
+    }
+
+    let root: LinearCombination = match root {
+        Var::Witness(_) => assignments.get_witness(root, Some(&assert_witness_32))?.2[0].into(),
+        Var::Instance(_) => be_to_scalar(&assignments.get_instance(root, Some(&assert_32))?).into(),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
+    };
+
+    // the leaf is hashed like a witness leaf of a MERKLE, the bits of the index are derived in subroutine 0
+    let (_, leaf_var) = hash_witness::<H>(prover, prover_buffer, leaf, assignments, index, 1, commitments)?;
+
+    // the scalars are in reverse order of the bytes, which hold the index followed by the siblings from the leaf upwards
+    let path_scalars: Vec<Scalar> = path_scalars.into_iter().rev().collect();
+    let path_vars: Vec<Variable> = path_vars.into_iter().rev().collect();
+
+    let gadget = MerklePath::<H>::with_hash(root, leaf_var.into());
+    let (derived_coms, derived_wtns) = gadget.setup(prover, &path_scalars);
+
+    prover_buffer.commit_drvd(&derived_wtns);
+    gadget.prove(prover_buffer, &path_vars, &derived_wtns);
+
+    assignments.cache_derived_wtns(derived_wtns);
+    assignments.parse_derived_witness(derived_coms, index, 0, commitments)
 }
 
 fn equality_gadget(
-    line: &str,
+    args: (Var, Var),
     assignments: &Assignments,
     prover_buffer: &mut ProverBuffer
-) {
-    let equality_parser = gadget_grammar::EqualityGadgetParser::new();
-    let (left, right) = equality_parser.parse(line).unwrap();
+) -> Result<(), GadgetError> {
+    let (left, right) = args;
+
+    if left.is_expression() || right.is_expression() {
+        let (left, _) = scalar_operand(left, assignments, prover_buffer)?;
+        let (right, _) = scalar_operand(right, assignments, prover_buffer)?;
+        prover_buffer.constrain(left - right);
+        return Ok(());
+    }
 
-    let (_, _, left_vars, _)  = assignments.get_witness(left, None);
+    let (_, _, left_vars, _)  = assignments.get_witness(left, None)?;
 
     let right: Vec<LinearCombination> = match right {
-        Var::Witness(_) => assignments.get_witness(right, None).2.into_iter().map(|var| var.into()).collect(),
-        Var::Instance(_) => be_to_scalars(&assignments.get_instance(right, None)).into_iter().map(|scalar| scalar.into()).collect(),
-        _ => panic!("invalid state")
+        Var::Witness(_) => assignments.get_witness(right, None)?.2.into_iter().map(|var| var.into()).collect(),
+        Var::Instance(_) => be_to_scalars(&assignments.get_instance(right, None)?).into_iter().map(|scalar| scalar.into()).collect(),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
     };
 
     let gadget = Equality::new(right);
 
     gadget.prove(prover_buffer, &left_vars, &Vec::new());
+    Ok(())
 }
 
 fn less_than_gadget(
-    line: &str,
+    args: (Var, Var),
     assignments: &mut Assignments,
     prover: &mut Prover<&mut Transcript>,
     prover_buffer: &mut ProverBuffer,
     index: usize,
     commitments: &mut String
-) {
-    let less_than_parser = gadget_grammar::LessThanGadgetParser::new();
-    let (left, right) = less_than_parser.parse(line).unwrap();
+) -> Result<(), GadgetError> {
+    let (left, right) = args;
 
-    let (left_scalars, _, left_vars, _) = assignments.get_witness(left, Some(&assert_witness_32));
-    let (right_scalars, _, right_vars, _) = assignments.get_witness(right, Some(&assert_witness_32));
+    let (left, left_assignment) = scalar_operand(left, assignments, prover_buffer)?;
+    let (right, right_assignment) = scalar_operand(right, assignments, prover_buffer)?;
 
-    let gadget = LessThan::new(left_vars[0].into(), Some(left_scalars[0]), right_vars[0].into(), Some(right_scalars[0]));
+    let gadget = LessThan::new(left, left_assignment, right, right_assignment);
     let (derived_coms, derived_wtns) = gadget.setup(prover, &Vec::new());
 
     prover_buffer.commit_drvd(&derived_wtns);
     gadget.prove(prover_buffer, &Vec::new(), &derived_wtns);
 
     assignments.cache_derived_wtns(derived_wtns);
-    assignments.parse_derived_witness(derived_coms, index, 0, commitments).expect("unable to generate commitments");
+    assignments.parse_derived_witness(derived_coms, index, 0, commitments)
 }
 
 fn inequality_gadget(
-    line: &str,
+    args: (Var, Var),
     assignments: &mut Assignments,
     prover: &mut Prover<&mut Transcript>,
     prover_buffer: &mut ProverBuffer,
     index: usize,
     commitments: &mut String
-) {
-    let inequality_parser = gadget_grammar::InequalityGadgetParser::new();
-    let (left, right) = inequality_parser.parse(line).unwrap();
-
-    let left = assignments.get_witness(left, None);
-
-    let (right_scalars, right_lc) = match right {
-        Var::Witness(_) => {
-            let (scalars, _, vars, _) = assignments.get_witness(right, None);
-            let lcs: Vec<LinearCombination> = vars.into_iter().map(|var| var.into()).collect();
-            (scalars, lcs)
-        },
-        Var::Instance(_) => {
-            let scalars: Vec<Scalar> = be_to_scalars(&assignments.get_instance(right, None));
-            let lcs: Vec<LinearCombination> = scalars.clone().into_iter().map(|scalar| scalar.into()).collect();
-            (scalars, lcs)
-        },
-        _ => panic!("invalid state")
+) -> Result<(), GadgetError> {
+    let (left, right) = args;
+
+    let (left_scalars, left_vars, right_scalars, right_lc) = if left.is_expression() || right.is_expression() {
+        let (left, left_assignment) = scalar_operand(left, assignments, prover_buffer)?;
+        let (right, right_assignment) = scalar_operand(right, assignments, prover_buffer)?;
+
+        // the gadget expects the left hand side to be a variable
+        let (left_var, _, _) = prover_buffer.multiply(left, Scalar::one().into());
+        (left_assignment.into_iter().collect(), vec![left_var], right_assignment.into_iter().collect(), vec![right])
+    } else {
+        let (left_scalars, _, left_vars, _) = assignments.get_witness(left, None)?;
+        let (right_scalars, right_lc) = match right {
+            Var::Witness(_) => {
+                let (scalars, _, vars, _) = assignments.get_witness(right, None)?;
+                let lcs: Vec<LinearCombination> = vars.into_iter().map(|var| var.into()).collect();
+                (scalars, lcs)
+            },
+            Var::Instance(_) => {
+                let scalars: Vec<Scalar> = be_to_scalars(&assignments.get_instance(right, None)?);
+                let lcs: Vec<LinearCombination> = scalars.clone().into_iter().map(|scalar| scalar.into()).collect();
+                (scalars, lcs)
+            },
+            _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
+        };
+        (left_scalars, left_vars, right_scalars, right_lc)
     };
 
     let gadget = Inequality::new(right_lc, Some(right_scalars));
-    let (derived_coms, derived_wtns) = gadget.setup(prover, &left.0);
+    let (derived_coms, derived_wtns) = gadget.setup(prover, &left_scalars);
 
     prover_buffer.commit_drvd(&derived_wtns);
-    gadget.prove(prover_buffer, &left.2, &derived_wtns);
+    gadget.prove(prover_buffer, &left_vars, &derived_wtns);
 
     assignments.cache_derived_wtns(derived_wtns);
-    assignments.parse_derived_witness(derived_coms, index, 0, commitments).expect("unable to generate commitments");
+    assignments.parse_derived_witness(derived_coms, index, 0, commitments)
 }
 
-fn set_membership_gadget(
-    line: &str,
+fn set_membership_gadget<H: CircuitHash>(
+    args: (Var, Vec<Var>),
     assignments: &mut Assignments,
     prover: &mut Prover<&mut Transcript>,
     prover_buffer: &mut ProverBuffer,
     index: usize,
     commitments: &mut String
-) {
-    let set_membership_parser = gadget_grammar::SetMembershipGadgetParser::new();
-    let (member, set) = set_membership_parser.parse(&line).unwrap();
+) -> Result<(), GadgetError> {
+    let (member, set) = args;
 
     let (member_scalars, member_lcs): (Vec<Scalar>, Vec<LinearCombination>) = match member.clone() {
         Var::Witness(_) => {
-            let (witness_scalars, _, witness_vars, _)  = assignments.get_witness(member.clone(), None);
+            let (witness_scalars, _, witness_vars, _)  = assignments.get_witness(member.clone(), None)?;
             let linear_combinations = witness_vars.into_iter().map(|var| var.into()).collect();
             (witness_scalars, linear_combinations)
         },
         Var::Instance(_) => {
-            let member_assignments: Vec<Scalar> = be_to_scalars(&assignments.get_instance(member.clone(), None));
+            let member_assignments: Vec<Scalar> = be_to_scalars(&assignments.get_instance(member.clone(), None)?);
             let linear_combinations = member_assignments.clone().into_iter().map(|scalar| scalar.into()).collect();
             (member_assignments, linear_combinations)
         },
-        _ => panic!("invalid state")
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
     };
 
     let mut member_scalar: Scalar = member_scalars[0];
@@ -442,7 +725,7 @@ fn set_membership_gadget(
         for element in set.clone() {
             match element {
                 Var::Witness(_) => {
-                    let (witness_scalar, _, witness_var, _) = assignments.get_witness(element, None);
+                    let (witness_scalar, _, witness_var, _) = assignments.get_witness(element, None)?;
                     if witness_var.len() == 1 {
                         witness_set_scalars.push(witness_scalar[0]);
                         witness_set_vars.push(witness_var[0]);
@@ -451,7 +734,7 @@ fn set_membership_gadget(
                     }
                 },
                 Var::Instance(_) => {
-                    let instance_scalars = be_to_scalars(&assignments.get_instance(element, None));
+                    let instance_scalars = be_to_scalars(&assignments.get_instance(element, None)?);
                     if instance_scalars.len() == 1 {
                         instance_set_scalars.push(instance_scalars[0]);
                         instance_set_lcs.push(instance_scalars[0].into());
@@ -459,7 +742,7 @@ fn set_membership_gadget(
                         apply_hashing = true;
                     }
                 },
-                _ => panic!("invalid state")
+                _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
             };
         }
     }
@@ -469,12 +752,12 @@ fn set_membership_gadget(
         let mut hash_number = 1;
         let (scalar, lc) = match member {
             Var::Witness(_) => {
-                let (scalar, var) = hash_witness(prover, prover_buffer, member, assignments, index, hash_number, commitments);
+                let (scalar, var) = hash_witness::<H>(prover, prover_buffer, member, assignments, index, hash_number, commitments)?;
                 hash_number += 1;
                 (scalar, var.into())
             },
-            Var::Instance(_) => hash_instance(member, &assignments),
-            _ => panic!("invalid state")
+            Var::Instance(_) => hash_instance::<H>(member, &assignments)?,
+            _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
         };
         member_scalar = scalar;
         member_lc = lc;
@@ -488,17 +771,17 @@ fn set_membership_gadget(
         for element in set {
             match element {
                 Var::Witness(_) => {
-                    let (scalar, var) = hash_witness(prover, prover_buffer, element, assignments, index, hash_number, commitments);
+                    let (scalar, var) = hash_witness::<H>(prover, prover_buffer, element, assignments, index, hash_number, commitments)?;
                     hash_number += 1;
                     witness_set_vars.push(var);
                     witness_set_scalars.push(scalar);
                 },
                 Var::Instance(_) => {
-                    let (scalar, lc) = hash_instance(element, &assignments);
+                    let (scalar, lc) = hash_instance::<H>(element, &assignments)?;
                     instance_set_lcs.push(lc);
                     instance_set_scalars.push(scalar);
                 },
-                _ => panic!("invalid state")
+                _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
             };
         }
     }
@@ -510,5 +793,101 @@ fn set_membership_gadget(
     gadget.prove(prover_buffer, &witness_set_vars, &derived_wtns);
 
     assignments.cache_derived_wtns(derived_wtns);
-    assignments.parse_derived_witness(derived_coms, index, 0, commitments).expect("unable to generate commitments");
+    assignments.parse_derived_witness(derived_coms, index, 0, commitments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use verify::verify_statement;
+
+    #[test]
+    fn test_check_witness() {
+        let instance = String::from(include_str!("../tests/resources/bounds_check.inst"));
+        let witness = String::from(include_str!("../tests/resources/bounds_check.wtns"));
+        let statement: Statement = include_str!("../tests/resources/bounds_check.gadgets").parse().unwrap();
+
+        assert_eq!(check_witness(&statement, instance.clone(), witness.clone()).unwrap(), None);
+
+        // W1 = I3 + 1 is outside of the bounds of the second gadget
+        let witness = witness.replace("W1 = 0x8734e1c38ff58f75", "W1 = 0x216269f5183717177696cf23fcf07d636a48af45d52699d4c5272d600105");
+        let unsatisfied = check_witness(&statement, instance, witness).unwrap().unwrap();
+        assert_eq!(unsatisfied.line, 2);
+        assert_eq!(unsatisfied.gadget, "BOUND");
+        assert_eq!(unsatisfied.vars, vec!["W1", "I2", "I3"]);
+    }
+
+    #[test]
+    fn test_check_witness_branches() {
+        let instance = String::from(include_str!("../tests/resources/bounds_check.inst"));
+        let witness = String::from(include_str!("../tests/resources/bounds_check.wtns"));
+
+        // W2 is outside of the bounds of the second branch
+        let statement = Statement::new()
+            .bound(Var::witness(0), Var::instance(0), Var::instance(1))
+            .or(vec![
+                Statement::new().bound(Var::witness(1), Var::instance(2), Var::instance(3)),
+                Statement::new().bound(Var::witness(2), Var::instance(0), Var::instance(1))
+            ]);
+        assert_eq!(statement.to_string().lines().nth(4), Some("BOUND W1 I2 I3"));
+        assert_eq!(check_witness(&statement, instance.clone(), witness.clone()).unwrap(), None);
+
+        // neither branch holds, the gadget of the first branch is reported
+        let witness = witness.replace("W1 = 0x8734e1c38ff58f75", "W1 = 0x216269f5183717177696cf23fcf07d636a48af45d52699d4c5272d600105");
+        let unsatisfied = check_witness(&statement, instance, witness).unwrap().unwrap();
+        assert_eq!(unsatisfied.line, 5);
+        assert_eq!(unsatisfied.gadget, "BOUND");
+        assert_eq!(unsatisfied.vars, vec!["W1", "I2", "I3"]);
+    }
+
+    #[test]
+    fn test_prove_sha256_chunk_conflict() {
+        let instance = String::from(include_str!("../tests/resources/bounds_check.inst"));
+        let witness = String::from(include_str!("../tests/resources/bounds_check.wtns"));
+
+        // the preimage of SHA256 is committed in chunks the BOUND within the branch can not read
+        let gadgets = String::from("SHA256 I0 W0\nOR(BOUND W0 I0 I1, BOUND W1 I2 I3)");
+        let mut commitments = String::new();
+        assert_eq!(prove("ChunkConflict", instance, witness, gadgets, &mut commitments), Err(GadgetError::ChunkConflict(String::from("W0"))));
+    }
+
+    #[test]
+    fn test_prove_merkle_path_depth() {
+        let instance = String::from(include_str!("../tests/resources/bounds_check.inst"));
+        let witness = String::from(include_str!("../tests/resources/bounds_check.wtns"));
+
+        // the builder rejects all depths but 2, a clause can be added without the check, and W1 is only 8 bytes long
+        for depth in vec![0, 2, MAX_DEPTH + 1, 300] {
+            let statement = Statement::new().clause(Clause::MerklePath(Var::instance(0), Var::witness(0), Var::witness(1), depth, HashFunction::default()));
+            let mut commitments = String::new();
+            match prove_statement("MerklePath", instance.clone(), witness.clone(), &statement, &mut commitments) {
+                Err(GadgetError::PathLength { depth: found, actual, .. }) => assert_eq!((found, actual), (depth, 8)),
+                result => panic!("expected path length error, got {:?}", result)
+            }
+        }
+    }
+
+    #[test]
+    fn test_prove_threshold_exceeds_branches() {
+        let instance = String::from(include_str!("../tests/resources/bounds_check.inst"));
+        let witness = String::from(include_str!("../tests/resources/bounds_check.wtns"));
+
+        // the builder rejects this threshold, a clause can be added without the check
+        let threshold = |k: usize| Statement::new().clause(Clause::Threshold(k, vec![
+            Statement::new().bound(Var::witness(0), Var::instance(0), Var::instance(1)),
+            Statement::new().bound(Var::witness(1), Var::instance(2), Var::instance(3))
+        ]));
+        let mut commitments = String::new();
+        match prove_statement("Threshold", instance.clone(), witness.clone(), &threshold(3), &mut commitments) {
+            Err(GadgetError::InvalidStatement(_)) => (),
+            result => panic!("expected invalid statement, got {:?}", result)
+        }
+
+        let mut commitments = String::new();
+        let proof = prove_statement("Threshold", instance.clone(), witness, &threshold(1), &mut commitments).unwrap();
+        match verify_statement("Threshold", instance, proof, commitments, &threshold(3)) {
+            Err(GadgetError::InvalidStatement(_)) => (),
+            result => panic!("expected invalid statement, got {:?}", result)
+        }
+    }
 }
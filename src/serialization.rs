@@ -0,0 +1,293 @@
+// Serde support for the public artifacts (requires the `serialization` feature).
+//
+// Byte strings are written as `0x` prefixed hex in human readable encodings (JSON) and as raw
+// bytes otherwise (CBOR). Instance variables and commitments are maps keyed by name; the
+// commitment map keeps the order the commitments were made in.
+
+use curve25519_dalek::ristretto::CompressedRistretto;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, DeserializeOwned, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+
+use artifacts::{Instance, Commitments, Proof};
+use error::GadgetError;
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Json,
+    Cbor
+}
+
+impl Encoding {
+    /// file extension used for artifacts in this encoding
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Encoding::Json => ".json",
+            Encoding::Cbor => ".cbor"
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, GadgetError> {
+        match self {
+            Encoding::Json => serde_json::to_vec_pretty(value).map_err(|error| GadgetError::Serialization(error.to_string())),
+            Encoding::Cbor => serde_cbor::to_vec(value).map_err(|error| GadgetError::Serialization(error.to_string()))
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, GadgetError> {
+        match self {
+            Encoding::Json => serde_json::from_slice(bytes).map_err(|error| GadgetError::Serialization(error.to_string())),
+            Encoding::Cbor => serde_cbor::from_slice(bytes).map_err(|error| GadgetError::Serialization(error.to_string()))
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = GadgetError;
+
+    fn from_str(encoding: &str) -> Result<Encoding, GadgetError> {
+        match encoding {
+            "json" => Ok(Encoding::Json),
+            "cbor" => Ok(Encoding::Cbor),
+            _ => Err(GadgetError::Serialization(format!("unknown encoding {}", encoding)))
+        }
+    }
+}
+
+/// `serde(with)` module for byte vectors
+pub mod bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        Bytes(bytes).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        Ok(ByteBuf::deserialize(deserializer)?.0)
+    }
+}
+
+/// `serde(with)` module for 32 byte hashes
+pub mod hash {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        Bytes(hash).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let bytes = ByteBuf::deserialize(deserializer)?.0;
+        if bytes.len() != 32 {
+            return Err(de::Error::invalid_length(bytes.len(), &"32 bytes"));
+        }
+        Ok(slice_to_array!(&bytes, 32))
+    }
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&format!("0x{}", hex::encode(self.0)))
+        } else {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+}
+
+struct ByteBuf(Vec<u8>);
+
+struct ByteBufVisitor;
+
+impl<'de> Visitor<'de> for ByteBufVisitor {
+    type Value = ByteBuf;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a byte string or a 0x prefixed hex string")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<ByteBuf, E> {
+        let value = value.strip_prefix("0x").unwrap_or(value);
+        hex::decode(value).map(ByteBuf).map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<ByteBuf, E> {
+        Ok(ByteBuf(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<ByteBuf, E> {
+        Ok(ByteBuf(value))
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ByteBuf, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(ByteBufVisitor)
+        } else {
+            deserializer.deserialize_byte_buf(ByteBufVisitor)
+        }
+    }
+}
+
+fn serialize_named<S: Serializer, V: AsRef<[u8]>>(entries: &[(String, V)], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(entries.len()))?;
+    for (name, value) in entries {
+        map.serialize_entry(name, &Bytes(value.as_ref()))?;
+    }
+    map.end()
+}
+
+/// read a map of named byte strings, keeping the order of the entries
+fn deserialize_named<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(String, Vec<u8>)>, D::Error> {
+    struct NamedVisitor;
+
+    impl<'de> Visitor<'de> for NamedVisitor {
+        type Value = Vec<(String, Vec<u8>)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a map of names to byte strings")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+            let mut entries = Vec::new();
+            while let Some((name, ByteBuf(bytes))) = access.next_entry()? {
+                entries.push((name, bytes));
+            }
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(NamedVisitor)
+}
+
+impl Serialize for Instance {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_named(self.vars(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Instance {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Instance, D::Error> {
+        let vars = deserialize_named(deserializer)?;
+        if let Some((name, _)) = vars.iter().find(|(name, _)| !is_instance_name(name)) {
+            return Err(de::Error::invalid_value(de::Unexpected::Str(name), &"an instance variable like I0 or $name"));
+        }
+        Ok(Instance::new(vars))
+    }
+}
+
+/// names accepted by `read_instance`: `I` followed by digits or `$` followed by an identifier
+fn is_instance_name(name: &str) -> bool {
+    let identifier = |name: &str| name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let numbered = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_ascii_digit());
+    match name.chars().next() {
+        Some('I') => numbered(&name[1..]),
+        Some('$') => identifier(&name[1..]),
+        _ => false
+    }
+}
+
+impl Serialize for Commitments {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let coms: Vec<(String, [u8; 32])> = self.coms().iter()
+            .map(|(name, com)| (name.clone(), com.to_bytes()))
+            .collect();
+        serialize_named(&coms, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Commitments {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Commitments, D::Error> {
+        let mut coms = Vec::new();
+        for (name, bytes) in deserialize_named(deserializer)? {
+            if bytes.len() != 32 || CompressedRistretto::from_slice(&bytes).decompress().is_none() {
+                return Err(de::Error::custom(GadgetError::MalformedCommitment(name)));
+            }
+            coms.push((name, CompressedRistretto::from_slice(&bytes)));
+        }
+        Ok(Commitments::new(coms))
+    }
+}
+
+impl Serialize for Proof {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Bytes(&self.0).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Proof {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Proof, D::Error> {
+        Ok(Proof(ByteBuf::deserialize(deserializer)?.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bundle::ProofBundle;
+
+    fn commitments() -> Commitments {
+        let com = hex::encode(CompressedRistretto::default().as_bytes());
+        format!("D3-0-0 = 0x{}\nC0-0 = 0x{}\n", com, com).parse().unwrap()
+    }
+
+    #[test]
+    fn test_json_representation() {
+        let instance: Instance = "I1 = 0x0102\nI0 = 0xff".parse().unwrap();
+        let json = String::from_utf8(Encoding::Json.encode(&instance).unwrap()).unwrap();
+        assert_eq!(json, "{\n  \"I0\": \"0xff\",\n  \"I1\": \"0x0102\"\n}");
+        assert_eq!(Encoding::Json.decode::<Instance>(json.as_bytes()).unwrap(), instance);
+
+        let decoded: Commitments = Encoding::Json.decode(&Encoding::Json.encode(&commitments()).unwrap()).unwrap();
+        assert_eq!(decoded, commitments());
+        assert_eq!(decoded.coms()[0].0, "D3-0-0");
+
+        assert!(Encoding::Json.decode::<Commitments>(b"{\"C0-0\": \"0x0102\"}").is_err());
+        assert!(Encoding::Json.decode::<Proof>(b"\"0xzz\"").is_err());
+        assert!(Encoding::Json.decode::<Proof>(b"\"0x0x01\"").is_err());
+    }
+
+    #[test]
+    fn test_instance_names() {
+        let instance: Instance = Encoding::Json.decode(b"{\"I0\": \"0x01\", \"$age_limit\": \"0x12\"}").unwrap();
+        assert_eq!(instance, "I0 = 0x01\n$age_limit = 0x12".parse().unwrap());
+
+        for name in &["W0", "I", "Ix", "$", "$0", "$a-b", "I0 = 0x01"] {
+            let json = format!("{{\"{}\": \"0x01\"}}", name);
+            assert!(Encoding::Json.decode::<Instance>(json.as_bytes()).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let proof = Proof(vec![1, 2, 3]);
+        assert_eq!(Encoding::Cbor.encode(&proof).unwrap(), vec![0x43, 1, 2, 3]);
+        assert_eq!(Encoding::Cbor.decode::<Proof>(&[0x43, 1, 2, 3]).unwrap(), proof);
+
+        let bundle = ProofBundle {
+            version: String::from("0.2.0"),
+            label: String::from("label"),
+            generators: 16,
+            statement_hash: [1u8; 32],
+            instance_hash: [2u8; 32],
+            commitments: commitments(),
+            proof: vec![3u8; 10]
+        };
+        for encoding in &[Encoding::Json, Encoding::Cbor] {
+            let decoded: ProofBundle = encoding.decode(&encoding.encode(&bundle).unwrap()).unwrap();
+            assert_eq!(decoded, bundle);
+        }
+    }
+
+    #[test]
+    fn test_encoding_from_str() {
+        assert_eq!("cbor".parse::<Encoding>(), Ok(Encoding::Cbor));
+        assert!("xml".parse::<Encoding>().is_err());
+    }
+}
@@ -0,0 +1,111 @@
+/// bytes of a block of the padded message
+pub const BLOCK_SIZE: usize = 64;
+
+/// first 32 bits of the fractional parts of the cube roots of the first 64 primes
+pub const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+];
+
+/// first 32 bits of the fractional parts of the square roots of the first 8 primes
+pub const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19
+];
+
+/// SHA-256 (FIPS 180-4), the image is returned in big endian order
+pub fn sha256(preimage: &Vec<u8>) -> Vec<u8> {
+    let mut state = INITIAL_STATE;
+    for block in pad(preimage).chunks(BLOCK_SIZE) {
+        compress(&mut state, block);
+    }
+
+    state.iter().flat_map(|word| word.to_be_bytes().to_vec()).collect()
+}
+
+/// Append a one bit, zeros and the length of the preimage in bits (64 bit big endian),
+/// such that the message fills a whole number of blocks.
+pub fn pad(preimage: &Vec<u8>) -> Vec<u8> {
+    let mut message: Vec<u8> = preimage.clone();
+    message.push(0x80);
+    while message.len() % BLOCK_SIZE != BLOCK_SIZE - 8 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(preimage.len() as u64 * 8).to_be_bytes());
+
+    message
+}
+
+/// number of blocks of the padded preimage of the given length in bytes
+pub fn blocks(length: usize) -> usize {
+    (length + 8) / BLOCK_SIZE + 1
+}
+
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    // message schedule
+    let mut w = [0u32; 64];
+    for (t, bytes) in block.chunks(4).enumerate() {
+        w[t] = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    for t in 16..64 {
+        let s0 = w[t-15].rotate_right(7) ^ w[t-15].rotate_right(18) ^ (w[t-15] >> 3);
+        let s1 = w[t-2].rotate_right(17) ^ w[t-2].rotate_right(19) ^ (w[t-2] >> 10);
+        w[t] = s1.wrapping_add(w[t-7]).wrapping_add(s0).wrapping_add(w[t-16]);
+    }
+
+    let mut v: [u32; 8] = *state;
+    for (constant, word) in ROUND_CONSTANTS.iter().zip(w.iter()) {
+        let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+        let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+        let t1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(*constant).wrapping_add(*word);
+
+        let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+        let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+        let t2 = s0.wrapping_add(maj);
+
+        v = [t1.wrapping_add(t2), v[0], v[1], v[2], v[3].wrapping_add(t1), v[4], v[5], v[6]];
+    }
+
+    for (word, value) in state.iter_mut().zip(v.iter()) {
+        *word = word.wrapping_add(*value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// examples of FIPS 180-4 and the NIST test vectors
+    #[test]
+    fn test_sha256() {
+        let vectors: Vec<(Vec<u8>, &str)> = vec![
+            (b"abc".to_vec(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (Vec::new(), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            (b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".to_vec(),
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"),
+            (b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu".to_vec(),
+                "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1"),
+            (vec![b'a'; 1_000_000], "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0")
+        ];
+
+        for (preimage, image) in vectors {
+            assert_eq!(hex::encode(sha256(&preimage)), image);
+        }
+    }
+
+    #[test]
+    fn test_sha256_padding() {
+        for length in vec![0, 3, 55, 56, 63, 64, 119, 120] {
+            let padded = pad(&vec![0xff; length]);
+            assert_eq!(padded.len(), blocks(length) * BLOCK_SIZE);
+            assert_eq!(padded[length], 0x80);
+            assert_eq!(&padded[padded.len() - 8..], &(length as u64 * 8).to_be_bytes());
+        }
+        assert_eq!((blocks(55), blocks(56)), (1, 2));
+    }
+}
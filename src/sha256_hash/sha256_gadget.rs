@@ -0,0 +1,515 @@
+use bulletproofs::r1cs::{ConstraintSystem, Variable, LinearCombination};
+use curve25519_dalek::scalar::Scalar;
+use gadget::Gadget;
+use error::GadgetError;
+use super::sha256::{BLOCK_SIZE, ROUND_CONSTANTS, INITIAL_STATE, blocks, pad};
+
+/// bytes of a commitment to the preimage or image (see `commitments::commit_chunks`), the value of
+/// a chunk stays below the group order so that every bit is bound by its commitment
+pub const CHUNK_SIZE: usize = 31;
+
+/// bytes of the digest
+const DIGEST_SIZE: usize = 32;
+
+/// Image of a SHA-256 gadget.
+pub enum Image {
+    /// up to 32 bytes in big endian order, every bit of the digest is constrained
+    Public(Vec<u8>),
+    /// a witness committed in chunks of `CHUNK_SIZE` bytes, the last chunk first
+    Committed(Vec<LinearCombination>)
+}
+
+/// Gadget proving that the committed preimage hashes to the image with SHA-256 (FIPS 180-4)
+///
+/// The preimage is committed in chunks of 31 bytes aligned to its end, such that the leading chunk
+/// holds 1 to 31 bytes. Only the number of chunks is public: one of 31 selector bits picks the
+/// length of the preimage, which places its padding, ties the bits of the message to the chunks
+/// and selects the state after the last block of the padded message.
+pub struct Sha256 {
+    image: Image,
+    /// number of committed chunks of the preimage
+    chunks: usize,
+    /// the preimage, only known to the prover
+    preimage: Option<Vec<u8>>
+}
+
+impl Gadget for Sha256 {
+    fn preprocess(&self, _: &Vec<Scalar>) -> Vec<Scalar> {
+        Vec::new()
+    }
+
+    fn assemble(
+        &self,
+        cs: &mut dyn ConstraintSystem,
+        witnesses: &Vec<Variable>,
+        _: &Vec<(Option<Scalar>, Variable)>
+    ) {
+        let powers: Vec<Scalar> = (0..8 * CHUNK_SIZE).scan(Scalar::one(), |power, _| {
+            let current = *power;
+            *power = current + current;
+            Some(current)
+        }).collect();
+
+        // candidate lengths of the preimage, longest first
+        let lengths: Vec<usize> = (0..CHUNK_SIZE).map(|zeros| CHUNK_SIZE * self.chunks - zeros).collect();
+        let length: Option<usize> = self.preimage.as_ref().map(|preimage| preimage.len());
+        let selectors: Vec<Variable> = lengths.iter()
+            .map(|candidate| allocate_bit(cs, length.map(|length| length == *candidate)))
+            .collect();
+        cs.constrain(selectors.iter().fold(LinearCombination::from(-Scalar::one()), |sum, selector| sum + *selector));
+
+        // the padded message fills the blocks of the longest candidate
+        let padded: Option<Vec<u8>> = self.preimage.as_ref().map(|preimage| {
+            let mut padded = pad(preimage);
+            padded.resize(blocks(lengths[0]) * BLOCK_SIZE, 0);
+            padded
+        });
+
+        let message = message(cs, &lengths, &selectors, padded.as_ref());
+        link(cs, witnesses, &lengths, &selectors, &message, &powers);
+        let digest = digest(cs, &lengths, &selectors, &message, padded.as_ref());
+
+        match &self.image {
+            Image::Public(image) => {
+                for (i, bit) in digest.iter().enumerate() {
+                    cs.constrain(bit.clone() - Scalar::from((image[i / 8] >> (7 - i % 8)) & 1));
+                }
+            },
+            Image::Committed(chunks) => {
+                // the bits of every chunk, counted from the end of the digest
+                let mut values: Vec<LinearCombination> = vec![Scalar::zero().into(); (DIGEST_SIZE + CHUNK_SIZE - 1) / CHUNK_SIZE];
+                for (i, bit) in digest.iter().enumerate() {
+                    let t = DIGEST_SIZE - 1 - i / 8;
+                    values[t / CHUNK_SIZE] = values[t / CHUNK_SIZE].clone() + bit.clone() * powers[8 * (t % CHUNK_SIZE) + 7 - i % 8];
+                }
+
+                // a missing chunk of a shorter image is zero, as is any chunk beyond the digest
+                for j in 0..values.len().max(chunks.len()) {
+                    let value: LinearCombination = values.get(j).cloned().unwrap_or(Scalar::zero().into());
+                    let chunk: LinearCombination = chunks.get(j).cloned().unwrap_or(Scalar::zero().into());
+                    cs.constrain(value - chunk);
+                }
+            }
+        }
+    }
+}
+
+impl Sha256 {
+    /// # Arguments
+    /// * `image` - a public image shorter than 32 bytes is padded with leading zeros
+    /// * `chunks` - number of commitments to the preimage, at least one
+    /// * `preimage` - the preimage on the prover side, none on the verifier side
+    pub fn new(image: Image, chunks: usize, preimage: Option<Vec<u8>>) -> Result<Sha256, GadgetError> {
+        if chunks == 0 {
+            return Err(GadgetError::InvalidStatement(String::from("the preimage of SHA256 is not committed in any chunk")));
+        }
+
+        let image = match image {
+            Image::Public(ref bytes) if bytes.len() > DIGEST_SIZE => {
+                return Err(GadgetError::SizeViolation { name: String::from("image"), max: DIGEST_SIZE, actual: bytes.len() });
+            },
+            Image::Public(bytes) => {
+                let mut padded: Vec<u8> = vec![0; DIGEST_SIZE - bytes.len()];
+                padded.extend(bytes);
+                Image::Public(padded)
+            },
+            image => image
+        };
+
+        Ok(Sha256 { image, chunks, preimage })
+    }
+}
+
+/// a 32 bit word as its bits (least significant first) and its value, which only the prover knows
+#[derive(Clone)]
+struct Word {
+    bits: Vec<LinearCombination>,
+    value: Option<u32>
+}
+
+impl Word {
+    fn constant(value: u32) -> Word {
+        Word {
+            bits: (0..32).map(|i| Scalar::from((value >> i) & 1).into()).collect(),
+            value: Some(value)
+        }
+    }
+
+    fn rotate_right(&self, n: usize) -> Word {
+        Word {
+            bits: (0..32).map(|i| self.bits[(i + n) % 32].clone()).collect(),
+            value: self.value.map(|value| value.rotate_right(n as u32))
+        }
+    }
+
+    fn shift_right(&self, n: usize) -> Word {
+        Word {
+            bits: (0..32).map(|i| if i + n < 32 { self.bits[i + n].clone() } else { Scalar::zero().into() }).collect(),
+            value: self.value.map(|value| value >> n)
+        }
+    }
+
+    fn lc(&self) -> LinearCombination {
+        self.bits.iter().enumerate()
+            .fold(Scalar::zero().into(), |sum: LinearCombination, (i, bit)| sum + bit.clone() * Scalar::from(1u64 << i))
+    }
+}
+
+/// bit of the padded message of the given length, none within the preimage or after the last block
+fn padding_bit(length: usize, position: usize) -> Option<bool> {
+    let byte = position / 8;
+    let end = blocks(length) * BLOCK_SIZE;
+    if byte < length || byte >= end {
+        None
+    } else if byte == length {
+        Some(position % 8 == 0)
+    } else if byte < end - 8 {
+        Some(false)
+    } else {
+        let bit_length = length as u64 * 8;
+        Some((bit_length >> (63 - (position - (end - 8) * 8))) & 1 == 1)
+    }
+}
+
+/// allocate the bits of the padded message (most significant bit of a byte first) and constrain
+/// the padding of the selected length
+fn message(
+    cs: &mut dyn ConstraintSystem,
+    lengths: &[usize],
+    selectors: &[Variable],
+    padded: Option<&Vec<u8>>
+) -> Vec<Variable> {
+    let size = blocks(lengths[0]) * BLOCK_SIZE * 8;
+    let message: Vec<Variable> = (0..size)
+        .map(|position| allocate_bit(cs, padded.map(|padded| (padded[position / 8] >> (7 - position % 8)) & 1 == 1)))
+        .collect();
+
+    for (position, bit) in message.iter().enumerate() {
+        let expected: Vec<Option<bool>> = lengths.iter().map(|length| padding_bit(*length, position)).collect();
+        if expected.iter().all(Option::is_none) {
+            continue;
+        }
+
+        // the bit is free for the selectors of a length it is no padding of, set for those of a one bit
+        let mut free: LinearCombination = Scalar::zero().into();
+        let mut one: LinearCombination = Scalar::zero().into();
+        for (selector, expected) in selectors.iter().zip(expected.iter()) {
+            match expected {
+                None => free = free + *selector,
+                Some(true) => one = one + *selector,
+                Some(false) => ()
+            }
+        }
+
+        // constrain bit * (1 - free) = one
+        if expected.iter().any(Option::is_none) {
+            let (_, _, product) = cs.multiply((*bit).into(), LinearCombination::from(Scalar::one()) - free);
+            cs.constrain(product - one);
+        } else {
+            cs.constrain(one - *bit);
+        }
+    }
+
+    message
+}
+
+/// constrain every chunk to the bytes of the preimage of the selected length it holds
+fn link(
+    cs: &mut dyn ConstraintSystem,
+    chunks: &[Variable],
+    lengths: &[usize],
+    selectors: &[Variable],
+    message: &[Variable],
+    powers: &[Scalar]
+) {
+    for (j, chunk) in chunks.iter().enumerate() {
+        let mut value: LinearCombination = (*chunk).into();
+        for (selector, length) in selectors.iter().zip(lengths.iter()) {
+            // the t-th byte from the end of the chunk
+            let end = length - CHUNK_SIZE * j;
+            let mut bytes: LinearCombination = Scalar::zero().into();
+            for t in 0..CHUNK_SIZE.min(end) {
+                for k in 0..8 {
+                    bytes = bytes + message[8 * (end - 1 - t) + k] * powers[8 * t + 7 - k];
+                }
+            }
+
+            let (_, _, product) = cs.multiply((*selector).into(), bytes);
+            value = value - product;
+        }
+        cs.constrain(value);
+    }
+}
+
+/// bits of the digest in big endian order, the state after the last block of the selected length
+fn digest(
+    cs: &mut dyn ConstraintSystem,
+    lengths: &[usize],
+    selectors: &[Variable],
+    message: &[Variable],
+    padded: Option<&Vec<u8>>
+) -> Vec<LinearCombination> {
+    let mut state: Vec<Word> = INITIAL_STATE.iter().map(|word| Word::constant(*word)).collect();
+    let mut states: Vec<Vec<Word>> = Vec::new();
+
+    for block in 0..message.len() / (BLOCK_SIZE * 8) {
+        let words: Vec<Word> = (0..16).map(|t| {
+            let start = BLOCK_SIZE * block + 4 * t;
+            Word {
+                bits: (0..32).map(|i| message[8 * start + 31 - i].into()).collect(),
+                value: padded.map(|padded| u32::from_be_bytes([padded[start], padded[start + 1], padded[start + 2], padded[start + 3]]))
+            }
+        }).collect();
+
+        state = compress(cs, &state, words);
+        states.push(state.clone());
+    }
+
+    let bits = |state: &Vec<Word>| -> Vec<LinearCombination> {
+        state.iter().flat_map(|word| word.bits.iter().rev().cloned()).collect()
+    };
+    let digest = bits(&states[states.len() - 1]);
+
+    // a shorter preimage may end one block earlier
+    let shorter: Vec<&Variable> = selectors.iter().zip(lengths.iter())
+        .filter(|(_, length)| blocks(**length) < states.len())
+        .map(|(selector, _)| selector)
+        .collect();
+    if shorter.is_empty() {
+        return digest;
+    }
+
+    let shorter: LinearCombination = shorter.iter().fold(Scalar::zero().into(), |sum: LinearCombination, selector| sum + **selector);
+    let previous = bits(&states[states.len() - 2]);
+    digest.into_iter().zip(previous.into_iter()).map(|(bit, previous)| {
+        let (_, _, product) = cs.multiply(shorter.clone(), previous - bit.clone());
+        bit + product
+    }).collect()
+}
+
+/// compression function, returns the state after the block
+fn compress(cs: &mut dyn ConstraintSystem, state: &[Word], block: Vec<Word>) -> Vec<Word> {
+    // message schedule
+    let mut w: Vec<Word> = block;
+    for t in 16..64 {
+        let s0 = xor3(cs, &w[t-15].rotate_right(7), &w[t-15].rotate_right(18), &w[t-15].shift_right(3));
+        let s1 = xor3(cs, &w[t-2].rotate_right(17), &w[t-2].rotate_right(19), &w[t-2].shift_right(10));
+        let word = add(cs, &[s1, w[t-7].clone(), s0, w[t-16].clone()], 0);
+        w.push(word);
+    }
+
+    let mut v: Vec<Word> = state.to_vec();
+    for (constant, word) in ROUND_CONSTANTS.iter().zip(w.into_iter()) {
+        let s1 = xor3(cs, &v[4].rotate_right(6), &v[4].rotate_right(11), &v[4].rotate_right(25));
+        let ch = choose(cs, &v[4], &v[5], &v[6]);
+        let s0 = xor3(cs, &v[0].rotate_right(2), &v[0].rotate_right(13), &v[0].rotate_right(22));
+        let maj = majority(cs, &v[0], &v[1], &v[2]);
+
+        // a = t1 + t2 and e = d + t1 are each decomposed once
+        let t1: Vec<Word> = vec![v[7].clone(), s1, ch, word];
+        let mut a: Vec<Word> = t1.clone();
+        a.push(s0);
+        a.push(maj);
+        let mut e: Vec<Word> = t1;
+        e.push(v[3].clone());
+
+        let a = add(cs, &a, *constant);
+        let e = add(cs, &e, *constant);
+        v = vec![a, v[0].clone(), v[1].clone(), v[2].clone(), e, v[4].clone(), v[5].clone(), v[6].clone()];
+    }
+
+    state.iter().zip(v.into_iter()).map(|(h, v)| add(cs, &[h.clone(), v], 0)).collect()
+}
+
+fn combine(left: Option<u32>, right: Option<u32>, operation: fn(u32, u32) -> u32) -> Option<u32> {
+    match (left, right) {
+        (Some(left), Some(right)) => Some(operation(left, right)),
+        _ => None
+    }
+}
+
+/// a ^ b bitwise, x + y - 2xy
+fn xor(cs: &mut dyn ConstraintSystem, a: &Word, b: &Word) -> Word {
+    let bits = a.bits.iter().zip(b.bits.iter()).map(|(x, y)| {
+        let (_, _, xy) = cs.multiply(x.clone(), y.clone());
+        x.clone() + y.clone() - xy * Scalar::from(2u8)
+    }).collect();
+
+    Word { bits, value: combine(a.value, b.value, |a, b| a ^ b) }
+}
+
+fn xor3(cs: &mut dyn ConstraintSystem, a: &Word, b: &Word, c: &Word) -> Word {
+    let ab = xor(cs, a, b);
+    xor(cs, &ab, c)
+}
+
+/// (e & f) ^ (!e & g) bitwise, g + e(f - g)
+fn choose(cs: &mut dyn ConstraintSystem, e: &Word, f: &Word, g: &Word) -> Word {
+    let bits = e.bits.iter().zip(f.bits.iter()).zip(g.bits.iter()).map(|((x, y), z)| {
+        let (_, _, product) = cs.multiply(x.clone(), y.clone() - z.clone());
+        z.clone() + product
+    }).collect();
+
+    let value = match (e.value, f.value, g.value) {
+        (Some(e), Some(f), Some(g)) => Some((e & f) ^ (!e & g)),
+        _ => None
+    };
+    Word { bits, value }
+}
+
+/// (a & b) ^ (a & c) ^ (b & c) bitwise, ab + c(a ^ b)
+fn majority(cs: &mut dyn ConstraintSystem, a: &Word, b: &Word, c: &Word) -> Word {
+    let bits = a.bits.iter().zip(b.bits.iter()).zip(c.bits.iter()).map(|((x, y), z)| {
+        let (_, _, xy) = cs.multiply(x.clone(), y.clone());
+        let (_, _, rest) = cs.multiply(z.clone(), x.clone() + y.clone() - xy * Scalar::from(2u8));
+        xy + rest
+    }).collect();
+
+    let value = match (a.value, b.value, c.value) {
+        (Some(a), Some(b), Some(c)) => Some((a & b) ^ (a & c) ^ (b & c)),
+        _ => None
+    };
+    Word { bits, value }
+}
+
+/// sum of the words and a constant modulo 2^32
+fn add(cs: &mut dyn ConstraintSystem, words: &[Word], constant: u32) -> Word {
+    let sum: LinearCombination = words.iter().fold(Scalar::from(constant).into(), |sum: LinearCombination, word| sum + word.lc());
+    let value: Option<u64> = words.iter().try_fold(constant as u64, |sum, word| word.value.map(|value| sum + value as u64));
+
+    // the carry is at most the number of words
+    let carry = 64 - (words.len() as u64).leading_zeros() as usize;
+    let bits = decompose(cs, sum, value, 32 + carry);
+
+    Word {
+        bits: bits[..32].iter().map(|bit| (*bit).into()).collect(),
+        value: value.map(|value| value as u32)
+    }
+}
+
+/// bits of the n bit value of the linear combination, least significant first
+fn decompose(cs: &mut dyn ConstraintSystem, lc: LinearCombination, value: Option<u64>, n: usize) -> Vec<Variable> {
+    let mut remainder = lc;
+    let mut bits = Vec::new();
+    for i in 0..n {
+        let bit = allocate_bit(cs, value.map(|value| (value >> i) & 1 == 1));
+        remainder = remainder - bit * Scalar::from(1u64 << i);
+        bits.push(bit);
+    }
+
+    // constrain lc = Sum(b_i * 2^i, i = 0..n-1)
+    cs.constrain(remainder);
+    bits
+}
+
+/// allocate a variable that is either 0 or 1 (see `utils::range_proof`)
+fn allocate_bit(cs: &mut dyn ConstraintSystem, bit: Option<bool>) -> Variable {
+    let (a, b, o) = cs.allocate_multiplier(bit.map(|bit| {
+        if bit { (Scalar::zero(), Scalar::one()) } else { (Scalar::one(), Scalar::zero()) }
+    })).unwrap();
+
+    // a * b = 0 and a = 1 - b
+    cs.constrain(o.into());
+    cs.constrain(a + (b - 1u8));
+    b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha256_hash::sha256::sha256;
+    use merlin::Transcript;
+    use commitments::{commit_chunks, verifier_commit};
+    use bulletproofs::{BulletproofGens, PedersenGens};
+    use bulletproofs::r1cs::{Prover, Verifier};
+
+    /// prove the preimage with a public image after committing to `committed`, and verify with the given image
+    fn prove_public(committed: &Vec<u8>, preimage: &Vec<u8>, image: Vec<u8>, verifier_image: Vec<u8>) -> bool {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32768, 1);
+
+        let mut prover_transcript = Transcript::new(b"Sha256");
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+        let (_, witness_commitments, variables) = commit_chunks(&mut prover, committed, CHUNK_SIZE);
+        let gadget = Sha256::new(Image::Public(image), variables.len(), Some(preimage.clone())).unwrap();
+        gadget.prove(&mut prover, &variables, &Vec::new());
+        let proof = prover.prove(&bp_gens).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"Sha256");
+        let mut verifier = Verifier::new(&mut verifier_transcript);
+        let witness_vars: Vec<Variable> = verifier_commit(&mut verifier, witness_commitments);
+        let gadget = Sha256::new(Image::Public(verifier_image), witness_vars.len(), None).unwrap();
+        gadget.verify(&mut verifier, &witness_vars, &Vec::new());
+        verifier.verify(&proof, &pc_gens, &bp_gens).is_ok()
+    }
+
+    /// prove the preimage with the committed image
+    fn prove_committed(preimage: &Vec<u8>, image: &Vec<u8>) -> bool {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32768, 1);
+
+        let mut prover_transcript = Transcript::new(b"Sha256");
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+        let (_, image_commitments, image_vars) = commit_chunks(&mut prover, image, CHUNK_SIZE);
+        let (_, witness_commitments, variables) = commit_chunks(&mut prover, preimage, CHUNK_SIZE);
+        let image_lcs: Vec<LinearCombination> = image_vars.into_iter().map(|var| var.into()).collect();
+        let gadget = Sha256::new(Image::Committed(image_lcs), variables.len(), Some(preimage.clone())).unwrap();
+        gadget.prove(&mut prover, &variables, &Vec::new());
+        let proof = prover.prove(&bp_gens).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"Sha256");
+        let mut verifier = Verifier::new(&mut verifier_transcript);
+        let image_vars: Vec<Variable> = verifier_commit(&mut verifier, image_commitments);
+        let witness_vars: Vec<Variable> = verifier_commit(&mut verifier, witness_commitments);
+        let image_lcs: Vec<LinearCombination> = image_vars.into_iter().map(|var| var.into()).collect();
+        let gadget = Sha256::new(Image::Committed(image_lcs), witness_vars.len(), None).unwrap();
+        gadget.verify(&mut verifier, &witness_vars, &Vec::new());
+        verifier.verify(&proof, &pc_gens, &bp_gens).is_ok()
+    }
+
+    #[test]
+    fn test_sha256_gadget_public() {
+        let preimage: Vec<u8> = b"abc".to_vec();
+        let image: Vec<u8> = sha256(&preimage);
+        assert!(prove_public(&preimage, &preimage, image.clone(), image.clone()));
+
+        let mut other: Vec<u8> = image.clone();
+        other[31] ^= 1;
+        assert!(!prove_public(&preimage, &preimage, image, other));
+    }
+
+    #[test]
+    fn test_sha256_gadget_binds_preimage() {
+        // the most significant bit of a full chunk is bound by its commitment
+        let committed: Vec<u8> = vec![0x7f; CHUNK_SIZE];
+        let mut preimage: Vec<u8> = committed.clone();
+        preimage[0] ^= 0x80;
+
+        let image: Vec<u8> = sha256(&preimage);
+        assert!(prove_public(&preimage, &preimage, image.clone(), image.clone()));
+        assert!(!prove_public(&committed, &preimage, image.clone(), image));
+    }
+
+    #[test]
+    fn test_sha256_gadget_committed() {
+        let preimage: Vec<u8> = b"The quick brown fox".to_vec();
+        let image: Vec<u8> = sha256(&preimage);
+        assert!(prove_committed(&preimage, &image));
+
+        // every bit of the committed image is constrained
+        let mut other: Vec<u8> = image.clone();
+        other[0] ^= 0x80;
+        assert!(!prove_committed(&preimage, &other));
+    }
+
+    #[test]
+    fn test_sha256_gadget_invalid() {
+        let image: Vec<u8> = vec![0; DIGEST_SIZE + 1];
+        assert_eq!(
+            Sha256::new(Image::Public(image), 1, None).err(),
+            Some(GadgetError::SizeViolation { name: String::from("image"), max: DIGEST_SIZE, actual: DIGEST_SIZE + 1 })
+        );
+        assert!(Sha256::new(Image::Public(sha256(&b"abc".to_vec())), 0, None).is_err());
+        assert!(Sha256::new(Image::Committed(Vec::new()), 0, Some(Vec::new())).is_err());
+    }
+}
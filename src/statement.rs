@@ -0,0 +1,809 @@
+use lalrpop::ast::{GadgetOp, Var};
+use lalrpop::statement_parser::parse_statement;
+use merkle_tree::merkle_tree_gadget::Pattern;
+use merkle_tree::merkle_path_gadget::MAX_DEPTH;
+use hash::HashFunction;
+use error::GadgetError;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A combination of gadgets, the typed equivalent of a `.gadgets` file.
+///
+/// Gadgets are indexed by the line they occupy in the text representation of the statement,
+/// which determines the names of the derived commitments. A statement built through this API
+/// therefore produces the same commitment layout as its `Display` output passed to the parser.
+/// Labeled gadgets name their derived commitments after the label instead, which keeps them
+/// stable when lines are inserted before the gadget.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Statement {
+    clauses: Vec<Clause>,
+    labels: Vec<Option<String>>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Clause {
+    /// BOUND var min max
+    Bound(Var, Var, Var),
+    /// HASH image preimage
+    Hash(Var, Var, HashFunction),
+    /// SHA256 image preimage
+    Sha256(Var, Var),
+    /// MERKLE root tree
+    Merkle(Var, Tree, HashFunction),
+    /// MERKLE_PATH root leaf path depth
+    MerklePath(Var, Var, Var, usize, HashFunction),
+    /// EQUALS left right
+    Equality(Var, Var),
+    /// LESS_THAN left right
+    LessThan(Var, Var),
+    /// UNEQUAL left right
+    Inequality(Var, Var),
+    /// SET_MEMBER member set
+    SetMembership(Var, Vec<Var>, HashFunction),
+    /// OR [ { .. } { .. } ] or OR(.., ..)
+    Or(Vec<Statement>),
+    /// THRESHOLD k [ { .. } { .. } ] or THRESHOLD(k, .., ..)
+    Threshold(usize, Vec<Statement>)
+}
+
+/// Shape of a merkle tree, leaves are instance or witness variables.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tree {
+    Node(Box<Tree>, Box<Tree>),
+    Leaf(Var)
+}
+
+impl Statement {
+    pub fn new() -> Statement {
+        Statement {
+            clauses: Vec::new(),
+            labels: Vec::new()
+        }
+    }
+
+    pub fn bound(self, var: Var, min: Var, max: Var) -> Statement {
+        self.clause(Clause::Bound(var, min, max))
+    }
+
+    pub fn hash(self, image: Var, preimage: Var) -> Statement {
+        self.clause(Clause::Hash(image, preimage, HashFunction::default()))
+    }
+
+    pub fn sha256(self, image: Var, preimage: Var) -> Statement {
+        self.clause(Clause::Sha256(image, preimage))
+    }
+
+    pub fn merkle(self, root: Var, tree: Tree) -> Statement {
+        self.clause(Clause::Merkle(root, tree, HashFunction::default()))
+    }
+
+    /// the leaf is in a tree of the given depth, the path holds its index and the siblings from the leaf upwards
+    pub fn merkle_path(self, root: Var, leaf: Var, path: Var, depth: usize) -> Result<Statement, GadgetError> {
+        if depth == 0 || depth > MAX_DEPTH {
+            return Err(GadgetError::InvalidStatement(String::from("the depth of a MERKLE_PATH is between 1 and 64")));
+        }
+        Ok(self.clause(Clause::MerklePath(root, leaf, path, depth, HashFunction::default())))
+    }
+
+    pub fn equals(self, left: Var, right: Var) -> Statement {
+        let (left, right) = witness_first(left, right);
+        self.clause(Clause::Equality(left, right))
+    }
+
+    pub fn less_than(self, left: Var, right: Var) -> Statement {
+        self.clause(Clause::LessThan(left, right))
+    }
+
+    pub fn unequal(self, left: Var, right: Var) -> Statement {
+        let (left, right) = witness_first(left, right);
+        self.clause(Clause::Inequality(left, right))
+    }
+
+    pub fn set_member(self, member: Var, set: Vec<Var>) -> Statement {
+        self.clause(Clause::SetMembership(member, set, HashFunction::default()))
+    }
+
+    /// at least one of the given statements holds
+    pub fn or(self, branches: Vec<Statement>) -> Statement {
+        self.clause(Clause::Or(branches))
+    }
+
+    /// at least k of the given statements hold
+    pub fn threshold(self, k: usize, branches: Vec<Statement>) -> Result<Statement, GadgetError> {
+        if k > branches.len() {
+            return Err(GadgetError::InvalidStatement(String::from("a THRESHOLD can not exceed the number of its branches")));
+        }
+        Ok(self.clause(Clause::Threshold(k, branches)))
+    }
+
+    /// the gadgets of the given statement hold as well, a statement is a conjunction of its clauses
+    pub fn and(mut self, statement: Statement) -> Statement {
+        self.clauses.extend(statement.clauses);
+        self.labels.extend(statement.labels);
+        self
+    }
+
+    pub fn clause(mut self, clause: Clause) -> Statement {
+        self.clauses.push(clause);
+        self.labels.push(None);
+        self
+    }
+
+    /// label the last gadget, its derived commitments are named after the label
+    pub fn label(mut self, label: &str) -> Result<Statement, GadgetError> {
+        match self.clauses.last() {
+            Some(Clause::Or(_)) => return Err(GadgetError::InvalidStatement(String::from("an OR can not be labeled"))),
+            Some(Clause::Threshold(..)) => return Err(GadgetError::InvalidStatement(String::from("a THRESHOLD can not be labeled"))),
+            Some(_) => *self.labels.last_mut().unwrap() = Some(String::from(label)),
+            None => return Err(GadgetError::InvalidStatement(String::from("there is no gadget to label")))
+        }
+        Ok(self)
+    }
+
+    /// hash the last gadget, a HASH, MERKLE, MERKLE_PATH or SET_MEMBER, with the given function instead of MiMC
+    pub fn with_hash(mut self, hash: HashFunction) -> Result<Statement, GadgetError> {
+        match self.clauses.last_mut() {
+            Some(Clause::Hash(_, _, function)) | Some(Clause::Merkle(_, _, function)) |
+            Some(Clause::MerklePath(_, _, _, _, function)) | Some(Clause::SetMembership(_, _, function)) => *function = hash,
+            _ => return Err(GadgetError::InvalidStatement(String::from("the last gadget is no HASH, MERKLE, MERKLE_PATH or SET_MEMBER")))
+        }
+        Ok(self)
+    }
+
+    pub fn clauses(&self) -> &Vec<Clause> {
+        &self.clauses
+    }
+
+    /// label of every clause, in the order of the clauses
+    pub fn labels(&self) -> &Vec<Option<String>> {
+        &self.labels
+    }
+
+    /// labels of all gadgets including those within OR and THRESHOLD branches, along with the index of their line
+    pub fn gadget_labels(&self, index: usize) -> Vec<(usize, String)> {
+        let mut labels = Vec::new();
+        let mut index = index;
+        for (clause, label) in self.clauses.iter().zip(self.labels.iter()) {
+            if let Some(label) = label {
+                labels.push((index, label.clone()));
+            }
+            if let Some(branches) = clause.branches() {
+                // the first branch starts after the OR, [ and { lines
+                let mut branch_index = index + 3;
+                for branch in branches {
+                    labels.extend(branch.gadget_labels(branch_index));
+                    branch_index += branch.lines() + 2;
+                }
+            }
+            index += clause.lines();
+        }
+        labels
+    }
+
+    /// number of lines the statement occupies in its text representation
+    pub fn lines(&self) -> usize {
+        self.clauses.iter().map(|clause| clause.lines()).sum()
+    }
+
+    /// all variables referenced by the statement, sorted by name and without duplicates
+    pub fn vars(&self) -> Vec<Var> {
+        let mut vars: Vec<Var> = self.clauses.iter().flat_map(|clause| clause.vars()).collect();
+        vars.sort_by_key(|var| var.to_string());
+        vars.dedup();
+        vars
+    }
+
+    /// witness variables of all SHA256 gadgets including those within branches, sorted by name and without duplicates
+    pub fn sha256_witnesses(&self) -> Vec<Var> {
+        let mut vars: Vec<Var> = self.clauses.iter()
+            .flat_map(|clause| match clause {
+                Clause::Sha256(..) => clause.vars(),
+                clause => clause.branches().into_iter().flatten().flat_map(|branch| branch.sha256_witnesses()).collect()
+            })
+            .filter(|var| match var {
+                Var::Witness(_) => true,
+                _ => false
+            })
+            .collect();
+        vars.sort_by_key(|var| var.to_string());
+        vars.dedup();
+        vars
+    }
+
+    /// variables of all gadgets but SHA256 including those within branches, in order of appearance
+    pub fn vars_besides_sha256(&self) -> Vec<Var> {
+        self.clauses.iter()
+            .flat_map(|clause| match (clause, clause.branches()) {
+                (Clause::Sha256(..), _) => Vec::new(),
+                (_, Some(branches)) => branches.iter().flat_map(|branch| branch.vars_besides_sha256()).collect(),
+                (clause, None) => clause.vars()
+            })
+            .collect()
+    }
+
+    /// statement with every variable replaced, e.g. the parameters of a definition by its arguments
+    pub fn map_vars(&self, f: &dyn Fn(&Var) -> Var) -> Statement {
+        Statement {
+            clauses: self.clauses.iter().map(|clause| clause.map_vars(f)).collect(),
+            labels: self.labels.clone()
+        }
+    }
+
+    /// statement with the label of every gadget, including those within branches, prefixed by `prefix_`
+    pub fn prefix_labels(&self, prefix: &str) -> Statement {
+        let clauses = self.clauses.iter().map(|clause| match clause {
+            Clause::Or(branches) => Clause::Or(branches.iter().map(|branch| branch.prefix_labels(prefix)).collect()),
+            Clause::Threshold(k, branches) =>
+                Clause::Threshold(*k, branches.iter().map(|branch| branch.prefix_labels(prefix)).collect()),
+            clause => clause.clone()
+        }).collect();
+        let labels = self.labels.iter()
+            .map(|label| label.as_ref().map(|label| format!("{}_{}", prefix, label)))
+            .collect();
+        Statement { clauses, labels }
+    }
+}
+
+impl Clause {
+    /// number of lines the clause occupies in its text representation
+    pub fn lines(&self) -> usize {
+        match self {
+            // OR, [, { and } around every branch, ]
+            Clause::Or(branches) | Clause::Threshold(_, branches) =>
+                3 + branches.iter().map(|branch| branch.lines() + 2).sum::<usize>(),
+            _ => 1
+        }
+    }
+
+    /// branches of an OR or THRESHOLD
+    pub fn branches(&self) -> Option<&Vec<Statement>> {
+        match self {
+            Clause::Or(branches) | Clause::Threshold(_, branches) => Some(branches),
+            _ => None
+        }
+    }
+
+    /// gadget the clause is written as
+    pub fn op(&self) -> GadgetOp {
+        match self {
+            Clause::Bound(..) => GadgetOp::Bound,
+            Clause::Hash(..) => GadgetOp::Hash,
+            Clause::Sha256(..) => GadgetOp::Sha256,
+            Clause::Merkle(..) => GadgetOp::Merkle,
+            Clause::MerklePath(..) => GadgetOp::MerklePath,
+            Clause::Equality(..) => GadgetOp::Equality,
+            Clause::LessThan(..) => GadgetOp::LessThan,
+            Clause::Inequality(..) => GadgetOp::Inequality,
+            Clause::SetMembership(..) => GadgetOp::SetMembership,
+            Clause::Or(_) => GadgetOp::Or,
+            Clause::Threshold(..) => GadgetOp::Threshold
+        }
+    }
+
+    /// variables referenced by the clause, in order of appearance
+    pub fn vars(&self) -> Vec<Var> {
+        let operands = match self {
+            Clause::Bound(var, min, max) => vec![var.clone(), min.clone(), max.clone()],
+            Clause::Hash(left, right, _) | Clause::Sha256(left, right) | Clause::Equality(left, right) |
+            Clause::LessThan(left, right) | Clause::Inequality(left, right) => vec![left.clone(), right.clone()],
+            Clause::Merkle(root, tree, _) => {
+                let mut vars = vec![root.clone()];
+                tree.leaves(&mut vars);
+                vars
+            },
+            Clause::MerklePath(root, leaf, path, _, _) => vec![root.clone(), leaf.clone(), path.clone()],
+            Clause::SetMembership(member, set, _) => [&[member.clone()], &set[..]].concat(),
+            Clause::Or(branches) | Clause::Threshold(_, branches) =>
+                branches.iter().flat_map(|branch| branch.vars()).collect()
+        };
+
+        // expressions refer to the variables they consist of
+        operands.into_iter().flat_map(|var| match var {
+            Var::Expression(expression) => expression.vars(),
+            var => vec![var]
+        }).collect()
+    }
+
+    /// clause with every variable replaced, variables within expressions, trees and branches included
+    pub fn map_vars(&self, f: &dyn Fn(&Var) -> Var) -> Clause {
+        let map = |var: &Var| match var {
+            Var::Expression(expression) => Var::Expression(Box::new(expression.map_vars(f))),
+            var => f(var)
+        };
+        match self {
+            Clause::Bound(var, min, max) => Clause::Bound(map(var), map(min), map(max)),
+            Clause::Hash(image, preimage, hash) => Clause::Hash(map(image), map(preimage), *hash),
+            Clause::Sha256(image, preimage) => Clause::Sha256(map(image), map(preimage)),
+            Clause::Merkle(root, tree, hash) => Clause::Merkle(map(root), tree.map_vars(f), *hash),
+            Clause::MerklePath(root, leaf, path, depth, hash) =>
+                Clause::MerklePath(map(root), map(leaf), map(path), *depth, *hash),
+            Clause::Equality(left, right) => Clause::Equality(map(left), map(right)),
+            Clause::LessThan(left, right) => Clause::LessThan(map(left), map(right)),
+            Clause::Inequality(left, right) => Clause::Inequality(map(left), map(right)),
+            Clause::SetMembership(member, set, hash) =>
+                Clause::SetMembership(map(member), set.iter().map(|var| map(var)).collect(), *hash),
+            Clause::Or(branches) => Clause::Or(branches.iter().map(|branch| branch.map_vars(f)).collect()),
+            Clause::Threshold(k, branches) => Clause::Threshold(*k, branches.iter().map(|branch| branch.map_vars(f)).collect())
+        }
+    }
+}
+
+impl Tree {
+    pub fn node<L: Into<Tree>, R: Into<Tree>>(left: L, right: R) -> Tree {
+        Tree::Node(Box::new(left.into()), Box::new(right.into()))
+    }
+
+    /// split the tree into its instance leaves, witness leaves and hashing pattern
+    pub fn flatten(&self) -> (Vec<Var>, Vec<Var>, Pattern) {
+        match self {
+            Tree::Node(left, right) => {
+                let (i1, w1, p1) = left.flatten();
+                let (i2, w2, p2) = right.flatten();
+                ([&i1[..], &i2[..]].concat(), [&w1[..], &w2[..]].concat(), hash!(p1, p2))
+            },
+            Tree::Leaf(var @ Var::Instance(_)) => (vec![var.clone()], Vec::new(), Pattern::I),
+            Tree::Leaf(var) => (Vec::new(), vec![var.clone()], Pattern::W)
+        }
+    }
+
+    /// tree of the same shape with every leaf replaced
+    pub fn map_vars(&self, f: &dyn Fn(&Var) -> Var) -> Tree {
+        match self {
+            Tree::Node(left, right) => Tree::node(left.map_vars(f), right.map_vars(f)),
+            Tree::Leaf(var) => Tree::Leaf(f(var))
+        }
+    }
+
+    fn leaves(&self, vars: &mut Vec<Var>) {
+        match self {
+            Tree::Node(left, right) => {
+                left.leaves(vars);
+                right.leaves(vars);
+            },
+            Tree::Leaf(var) => vars.push(var.clone())
+        }
+    }
+}
+
+impl From<Var> for Tree {
+    fn from(var: Var) -> Tree {
+        Tree::Leaf(var)
+    }
+}
+
+/// EQUALS and UNEQUAL expect the witness on the left hand side
+fn witness_first(left: Var, right: Var) -> (Var, Var) {
+    match (left, right) {
+        (left @ Var::Instance(_), right @ Var::Witness(_)) => (right, left),
+        (left, right) => (left, right)
+    }
+}
+
+impl FromStr for Statement {
+    type Err = GadgetError;
+
+    fn from_str(gadgets: &str) -> Result<Statement, GadgetError> {
+        parse_statement(gadgets)
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (clause, label) in self.clauses.iter().zip(self.labels.iter()) {
+            if let Some(label) = label {
+                write!(f, "{}: ", label)?;
+            }
+            write!(f, "{}", clause)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Clause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Clause::Bound(var, min, max) =>
+                writeln!(f, "{} {} {} {}", GadgetOp::Bound.as_str(), var, min, max),
+            Clause::Hash(image, preimage, hash) =>
+                writeln!(f, "{}{} {} {}", GadgetOp::Hash.as_str(), selection(hash), image, preimage),
+            Clause::Sha256(image, preimage) =>
+                writeln!(f, "{} {} {}", GadgetOp::Sha256.as_str(), image, preimage),
+            Clause::Merkle(root, tree, hash) =>
+                writeln!(f, "{}{} {} {}", GadgetOp::Merkle.as_str(), selection(hash), root, tree),
+            Clause::MerklePath(root, leaf, path, depth, hash) =>
+                writeln!(f, "{}{} {} {} {} {}", GadgetOp::MerklePath.as_str(), selection(hash), root, leaf, path, depth),
+            Clause::Equality(left, right) =>
+                writeln!(f, "{} {} {}", GadgetOp::Equality.as_str(), left, right),
+            Clause::LessThan(left, right) =>
+                writeln!(f, "{} {} {}", GadgetOp::LessThan.as_str(), left, right),
+            Clause::Inequality(left, right) =>
+                writeln!(f, "{} {} {}", GadgetOp::Inequality.as_str(), left, right),
+            Clause::SetMembership(member, set, hash) => {
+                write!(f, "{}{} {}", GadgetOp::SetMembership.as_str(), selection(hash), member)?;
+                for element in set {
+                    write!(f, " {}", element)?;
+                }
+                writeln!(f)
+            },
+            Clause::Or(branches) => {
+                writeln!(f, "{}", GadgetOp::Or.as_str())?;
+                write_branches(f, branches)
+            },
+            Clause::Threshold(k, branches) => {
+                writeln!(f, "{} {}", GadgetOp::Threshold.as_str(), k)?;
+                write_branches(f, branches)
+            }
+        }
+    }
+}
+
+/// `[poseidon]` after the gadget keyword, nothing for the default MiMC
+fn selection(hash: &HashFunction) -> String {
+    if *hash == HashFunction::default() {
+        String::new()
+    } else {
+        format!("[{}]", hash)
+    }
+}
+
+fn write_branches(f: &mut fmt::Formatter, branches: &[Statement]) -> fmt::Result {
+    writeln!(f, "{}", GadgetOp::ArrayStart.as_str())?;
+    for branch in branches {
+        writeln!(f, "{}", GadgetOp::CodeBlockStart.as_str())?;
+        write!(f, "{}", branch)?;
+        writeln!(f, "{}", GadgetOp::CodeBlockEnd.as_str())?;
+    }
+    writeln!(f, "{}", GadgetOp::ArrayEnd.as_str())
+}
+
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tree::Node(left, right) => write!(f, "({} {})", left, right),
+            Tree::Leaf(var) => write!(f, "{}", var)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prove::{prove, prove_statement};
+    use verify::verify;
+    use hash::CircuitHash;
+    use conversions::scalar_to_be;
+    use poseidon_hash::poseidon::poseidon_hash;
+    use poseidon_hash::poseidon_hash_gadget::PoseidonHash;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn example() -> Statement {
+        Statement::new()
+            .equals(Var::witness(0), Var::witness(1))
+            .or(vec![
+                Statement::new()
+                    .bound(Var::witness(1), Var::instance(0), Var::instance(1))
+                    .hash(Var::witness(2), Var::witness(1)),
+                Statement::new()
+                    .merkle(Var::instance(5), Tree::node(
+                        Tree::node(Var::witness(1), Var::instance(3)),
+                        Tree::node(Var::instance(6), Var::witness(4))
+                    ))
+                    .unequal(Var::witness(3), Var::instance(4))
+            ])
+            .set_member(Var::witness(0), vec![Var::instance(0), Var::instance(1), Var::witness(1), Var::instance(7)])
+            .less_than(Var::witness(4), Var::witness(0))
+    }
+
+    #[test]
+    fn test_statement_display() {
+        let expected = "EQUALS W0 W1\nOR\n[\n{\nBOUND W1 I0 I1\nHASH W2 W1\n}\n{\n\
+            MERKLE I5 ((W1 I3) (I6 W4))\nUNEQUAL W3 I4\n}\n]\nSET_MEMBER W0 I0 I1 W1 I7\nLESS_THAN W4 W0\n";
+        assert_eq!(example().to_string(), expected);
+        assert_eq!(example().lines(), 14);
+    }
+
+    #[test]
+    fn test_statement_parse() {
+        let gadgets = "EQUALS W0 W1\nOR \n[\n{\nBOUND W1 I0 I1\nHASH W2 W1\n}\n{\n\
+            MERKLE I5 ((W1 I3) (I6 W4))\nUNEQUAL W3 I4\n}\n]\nSET_MEMBER W0 I0 I1 W1 I7\nLESS_THAN W4 W0";
+        assert_eq!(gadgets.parse::<Statement>().unwrap(), example());
+        assert_eq!(example().to_string().parse::<Statement>().unwrap(), example());
+    }
+
+    #[test]
+    fn test_statement_witness_first() {
+        let statement = Statement::new().equals(Var::instance(1), Var::witness(2));
+        assert_eq!(statement.clauses()[0], Clause::Equality(Var::witness(2), Var::instance(1)));
+    }
+
+    #[test]
+    fn test_statement_vars() {
+        let names: Vec<String> = example().vars().iter().map(|var| var.to_string()).collect();
+        assert_eq!(names, vec!["I0", "I1", "I3", "I4", "I5", "I6", "I7", "W0", "W1", "W2", "W3", "W4"]);
+    }
+
+    #[test]
+    fn test_tree_flatten() {
+        let tree = Tree::node(Tree::node(Var::witness(1), Var::instance(3)), Var::witness(4));
+        let (instance_vars, witness_vars, pattern) = tree.flatten();
+        assert_eq!(instance_vars, vec![Var::instance(3)]);
+        assert_eq!(witness_vars, vec![Var::witness(1), Var::witness(4)]);
+        assert_eq!(pattern.to_string(), "H(H(W I) W)");
+    }
+
+    #[test]
+    fn test_statement_prove() {
+        let instance = String::from(include_str!("../tests/resources/or5.inst"));
+        let witness = String::from(include_str!("../tests/resources/or5.wtns"));
+        let gadgets = String::from(include_str!("../tests/resources/or5.gadgets"));
+
+        let set = vec![Var::instance(0), Var::instance(1), Var::witness(1), Var::instance(7)];
+        let statement = Statement::new()
+            .set_member(Var::witness(0), set.clone())
+            .or(vec![
+                Statement::new()
+                    .bound(Var::witness(1), Var::instance(0), Var::instance(1))
+                    .hash(Var::witness(2), Var::witness(1)),
+                Statement::new().set_member(Var::witness(0), set),
+                Statement::new().equals(Var::witness(0), Var::witness(2)),
+                Statement::new()
+                    .unequal(Var::witness(3), Var::instance(4))
+                    .unequal(Var::witness(0), Var::instance(7))
+            ])
+            .less_than(Var::witness(4), Var::witness(0));
+        assert_eq!(gadgets.parse::<Statement>().unwrap(), statement);
+
+        let mut commitments = String::new();
+        let proof = prove_statement("StatementBuilder", instance.clone(), witness, &statement, &mut commitments).unwrap();
+        assert!(verify("StatementBuilder", instance, proof, commitments, gadgets).unwrap());
+    }
+
+    #[test]
+    fn test_statement_comments_prove() {
+        let instance = String::from(include_str!("../tests/resources/less_than.inst"));
+        let witness = String::from(include_str!("../tests/resources/less_than.wtns"));
+        let gadgets = String::from(include_str!("../tests/resources/less_than.gadgets"));
+        let annotated = format!("# derived commitments keep their names\n\n{}", gadgets.replace("W3", "W3 // second"));
+
+        let mut commitments = String::new();
+        let proof = prove("Comments", instance.clone(), witness, annotated, &mut commitments).unwrap();
+        assert!(commitments.contains("D1-0-0"));
+        assert!(verify("Comments", instance, proof, commitments, gadgets).unwrap());
+    }
+
+    #[test]
+    fn test_statement_named_vars_prove() {
+        let instance = String::from("public $min = 0x0a\npublic $max = 0x64");
+        let witness = String::from("private credit_score = 0x32\nW0 = 0x32");
+        let gadgets = String::from("BOUND credit_score $min $max\nEQUALS credit_score W0");
+
+        let mut commitments = String::new();
+        let proof = prove("NamedVars", instance.clone(), witness, gadgets.clone(), &mut commitments).unwrap();
+        assert!(commitments.contains("Ccredit_score-0"));
+        assert!(commitments.contains("C0-0"));
+        assert!(verify("NamedVars", instance, proof, commitments, gadgets).unwrap());
+    }
+
+    #[test]
+    fn test_statement_arithmetic_prove() {
+        let instance = String::from("I0 = 0x64\nI1 = 0x64\nI2 = 0x03e8\nI3 = 0x14");
+        let witness = String::from("W1 = 0x32\nW2 = 0x0a\nW3 = 0x50\nW4 = 0x05");
+        let gadgets = String::from("LESS_THAN W1 + W2 I0\nEQUALS W3 2*W1 - I3\nBOUND W1*100/W2 I1 I2\nEQUALS W4 W1/W2\nUNEQUAL W1 + W2 I0");
+
+        let mut commitments = String::new();
+        let proof = prove("Arithmetic", instance.clone(), witness.clone(), gadgets.clone(), &mut commitments).unwrap();
+        assert!(verify("Arithmetic", instance.clone(), proof, commitments, gadgets.clone()).unwrap());
+
+        // 2*50 - 20 is not 81
+        let witness = witness.replace("W3 = 0x50", "W3 = 0x51");
+        let mut commitments = String::new();
+        if let Ok(proof) = prove("Arithmetic", instance.clone(), witness, gadgets.clone(), &mut commitments) {
+            assert_ne!(verify("Arithmetic", instance, proof, commitments, gadgets), Ok(true));
+        }
+    }
+
+    #[test]
+    fn test_statement_sha256_prove() {
+        // the 448 bit message of FIPS 180-4 is padded to two blocks
+        let instance = String::from("I0 = 0x248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+        let witness = format!("W0 = 0x{}", hex::encode("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"));
+        let gadgets = String::from("SHA256 I0 W0");
+        assert_eq!(gadgets.parse::<Statement>().unwrap(), Statement::new().sha256(Var::instance(0), Var::witness(0)));
+
+        let mut commitments = String::new();
+        let proof = prove("Sha256", instance.clone(), witness, gadgets.clone(), &mut commitments).unwrap();
+        assert!(verify("Sha256", instance, proof, commitments, gadgets).unwrap());
+    }
+
+    #[test]
+    fn test_statement_hash_selection() {
+        let statement = Statement::new()
+            .hash(Var::instance(0), Var::witness(0))
+            .with_hash(HashFunction::Poseidon).unwrap()
+            .merkle(Var::instance(1), Tree::node(Var::witness(1), Var::instance(2)))
+            .set_member(Var::witness(2), vec![Var::instance(3)])
+            .with_hash(HashFunction::Poseidon).unwrap();
+
+        assert_eq!(statement.to_string(), "HASH[poseidon] I0 W0\nMERKLE I1 (W1 I2)\nSET_MEMBER[poseidon] W2 I3\n");
+        assert_eq!("HASH[poseidon] I0 W0\nMERKLE[mimc] I1 (W1 I2)\nSET_MEMBER[poseidon] W2 I3".parse::<Statement>().unwrap(), statement);
+        assert!("HASH[sha256] I0 W0".parse::<Statement>().is_err());
+    }
+
+    #[test]
+    fn test_statement_poseidon_prove() {
+        let leaf: Vec<u8> = vec![0x01, 0x02];
+        let member: Vec<u8> = vec![0xaa; 40];
+        let root = PoseidonHash::native_sponge(&vec![poseidon_hash(&vec![0x03]), poseidon_hash(&leaf)]);
+
+        let instance = format!("I0 = 0x{}\nI1 = 0x{}\nI2 = 0x{}\nI3 = 0x01\nI4 = 0x{}",
+            hex::encode(scalar_to_be(&poseidon_hash(&b"preimage".to_vec()))), hex::encode(scalar_to_be(&root)),
+            hex::encode(&leaf), hex::encode(&member));
+        let witness = format!("W0 = 0x{}\nW1 = 0x03\nW2 = 0x{}", hex::encode("preimage"), hex::encode(&member));
+        let gadgets = String::from("HASH[poseidon] I0 W0\nMERKLE[poseidon] I1 (W1 I2)\nSET_MEMBER[poseidon] W2 I3 I4");
+
+        let mut commitments = String::new();
+        let proof = prove("Poseidon", instance.clone(), witness.clone(), gadgets.clone(), &mut commitments).unwrap();
+        assert!(verify("Poseidon", instance.clone(), proof, commitments, gadgets).unwrap());
+
+        // MiMC yields other images
+        let gadgets = String::from("HASH I0 W0");
+        let mut commitments = String::new();
+        let proof = prove("Poseidon", instance.clone(), witness, gadgets.clone(), &mut commitments).unwrap();
+        assert!(!verify("Poseidon", instance, proof, commitments, gadgets).unwrap());
+    }
+
+    #[test]
+    fn test_statement_merkle_path_prove() {
+        // tree of depth 2 over the hashes of four leaves, the witness is the third one
+        let leaves: Vec<Scalar> = (0..4u8).map(|leaf| poseidon_hash(&vec![leaf; 40])).collect();
+        let nodes: Vec<Scalar> = leaves.chunks(2).map(|pair| PoseidonHash::native_sponge(&pair.to_vec())).collect();
+        let root = PoseidonHash::native_sponge(&nodes);
+        let path = [vec![0x02], scalar_to_be(&leaves[3]), scalar_to_be(&nodes[0])].concat();
+
+        let instance = format!("I0 = 0x{}", hex::encode(scalar_to_be(&root)));
+        let witness = format!("W0 = 0x{}\nW1 = 0x{}", hex::encode(vec![2u8; 40]), hex::encode(&path));
+        let gadgets = String::from("MERKLE_PATH[poseidon] I0 W0 W1 2");
+        let statement = Statement::new().merkle_path(Var::instance(0), Var::witness(0), Var::witness(1), 2).unwrap()
+            .with_hash(HashFunction::Poseidon).unwrap();
+        assert_eq!(gadgets.parse::<Statement>().unwrap(), statement);
+        assert_eq!(statement.to_string(), "MERKLE_PATH[poseidon] I0 W0 W1 2\n");
+        assert!("MERKLE_PATH I0 W0 W1 0".parse::<Statement>().is_err());
+
+        let mut commitments = String::new();
+        let proof = prove("MerklePath", instance.clone(), witness.clone(), gadgets.clone(), &mut commitments).unwrap();
+        assert!(verify("MerklePath", instance.clone(), proof, commitments, gadgets.clone()).unwrap());
+
+        // the path does not lead from a different leaf to the root
+        let witness = witness.replacen("W0 = 0x02", "W0 = 0x03", 1);
+        let mut commitments = String::new();
+        let proof = prove("MerklePath", instance.clone(), witness, gadgets.clone(), &mut commitments).unwrap();
+        assert!(!verify("MerklePath", instance, proof, commitments, gadgets).unwrap());
+    }
+
+    #[test]
+    fn test_statement_definitions_prove() {
+        let instance = String::from("I0 = 0x01\nI1 = 0x64");
+        let witness = String::from("W1 = 0x32\nW2 = 0x0a");
+        let gadgets = String::from("DEFINE at_most(value, $max) {\n  limit: BOUND value I0 $max\n}\n\
+            alice: CALL at_most(W1, I1)\nbob: CALL at_most(W2, I1)");
+        assert_eq!(gadgets.parse::<Statement>().unwrap().to_string(), "alice_limit: BOUND W1 I0 I1\nbob_limit: BOUND W2 I0 I1\n");
+
+        let mut commitments = String::new();
+        let proof = prove("Definitions", instance.clone(), witness, gadgets.clone(), &mut commitments).unwrap();
+        assert!(commitments.contains("Dalice_limit-0-0"));
+        assert!(commitments.contains("Dbob_limit-0-0"));
+        assert!(verify("Definitions", instance, proof, commitments, gadgets).unwrap());
+    }
+
+    #[test]
+    fn test_statement_unlabeled_definitions_prove() {
+        let instance = String::from("I0 = 0x01\nI1 = 0x64");
+        let witness = String::from("W1 = 0x32\nW2 = 0x0a");
+        let gadgets = String::from("DEFINE at_most(value, $max) {\n  limit: BOUND value I0 $max\n}\n\
+            CALL at_most(W1, I1)\nCALL at_most(W2, I1)");
+        assert_eq!(gadgets.parse::<Statement>().unwrap().to_string(), "at_most0_limit: BOUND W1 I0 I1\nat_most1_limit: BOUND W2 I0 I1\n");
+
+        let mut commitments = String::new();
+        let proof = prove("Definitions", instance.clone(), witness, gadgets.clone(), &mut commitments).unwrap();
+        assert!(commitments.contains("Dat_most0_limit-0-0"));
+        assert!(commitments.contains("Dat_most1_limit-0-0"));
+        assert!(verify("Definitions", instance, proof, commitments, gadgets).unwrap());
+    }
+
+    #[test]
+    fn test_statement_and() {
+        let statement = Statement::new()
+            .equals(Var::witness(0), Var::witness(1))
+            .and(Statement::new().less_than(Var::witness(0), Var::witness(1)).label("first").unwrap());
+        assert_eq!(statement.to_string(), "EQUALS W0 W1\nfirst: LESS_THAN W0 W1\n");
+        assert_eq!(statement.to_string().parse::<Statement>().unwrap(), statement);
+    }
+
+    #[test]
+    fn test_statement_expression_prove() {
+        let instance = String::from(include_str!("../tests/resources/or_expression.inst"));
+        let witness = String::from(include_str!("../tests/resources/or_expression.wtns"));
+        let gadgets = String::from(include_str!("../tests/resources/or_expression.gadgets"));
+        let blocks: Statement = include_str!("../tests/resources/or.gadgets").parse().unwrap();
+        assert_eq!(gadgets.parse::<Statement>().unwrap(), blocks);
+
+        let mut commitments = String::new();
+        let proof = prove("Expression", instance.clone(), witness, gadgets.clone(), &mut commitments).unwrap();
+        assert!(verify("Expression", instance, proof, commitments, blocks.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_statement_threshold() {
+        let statement = Statement::new().threshold(2, vec![
+            Statement::new().equals(Var::witness(0), Var::witness(1)),
+            Statement::new().equals(Var::witness(0), Var::witness(2)).label("second").unwrap(),
+            Statement::new().unequal(Var::witness(0), Var::witness(2))
+        ]).unwrap();
+
+        assert_eq!(statement.to_string(), "THRESHOLD 2\n[\n{\nEQUALS W0 W1\n}\n{\nsecond: EQUALS W0 W2\n}\n{\nUNEQUAL W0 W2\n}\n]\n");
+        assert_eq!(statement.to_string().parse::<Statement>().unwrap(), statement);
+        assert_eq!(statement.lines(), 12);
+        assert_eq!(statement.gadget_labels(0), vec![(7, String::from("second"))]);
+    }
+
+    #[test]
+    fn test_statement_threshold_prove() {
+        let instance = String::from(include_str!("../tests/resources/or3.inst"));
+        let witness = String::from(include_str!("../tests/resources/or3.wtns"));
+        let gadgets = String::from("THRESHOLD(2, EQUALS W0 W1, EQUALS W0 W2, UNEQUAL W0 W2)");
+
+        let mut commitments = String::new();
+        let proof = prove("Threshold", instance.clone(), witness.clone(), gadgets.clone(), &mut commitments).unwrap();
+        assert!(verify("Threshold", instance.clone(), proof, commitments, gadgets.clone()).unwrap());
+
+        // only two of the three branches hold
+        let gadgets = gadgets.replacen("2", "3", 1);
+        let mut commitments = String::new();
+        if let Ok(proof) = prove("Threshold", instance.clone(), witness, gadgets.clone(), &mut commitments) {
+            assert_ne!(verify("Threshold", instance, proof, commitments, gadgets), Ok(true));
+        }
+    }
+
+    #[test]
+    fn test_statement_labels() {
+        let statement = Statement::new()
+            .bound(Var::witness(1), Var::instance(0), Var::instance(1))
+            .label("age_check").unwrap()
+            .or(vec![
+                Statement::new().equals(Var::witness(0), Var::witness(1)),
+                Statement::new().less_than(Var::witness(0), Var::witness(1)).label("younger").unwrap()
+            ]);
+
+        assert_eq!(statement.to_string(), "age_check: BOUND W1 I0 I1\nOR\n[\n{\nEQUALS W0 W1\n}\n{\nyounger: LESS_THAN W0 W1\n}\n]\n");
+        assert_eq!(statement.to_string().parse::<Statement>().unwrap(), statement);
+        assert_eq!(statement.gadget_labels(0), vec![(0, String::from("age_check")), (7, String::from("younger"))]);
+    }
+
+    #[test]
+    fn test_statement_builder_errors() {
+        let or = Statement::new().or(vec![Statement::new().equals(Var::witness(0), Var::witness(1))]);
+        assert!(or.clone().label("either").is_err());
+        assert!(or.with_hash(HashFunction::Poseidon).is_err());
+        assert!(Statement::new().label("nothing").is_err());
+        assert!(Statement::new().threshold(2, vec![Statement::new().equals(Var::witness(0), Var::witness(1))]).is_err());
+        assert!(Statement::new().threshold(1, Vec::new()).is_err());
+        assert!(Statement::new().merkle_path(Var::instance(0), Var::witness(0), Var::witness(1), 0).is_err());
+        assert!(Statement::new().merkle_path(Var::instance(0), Var::witness(0), Var::witness(1), MAX_DEPTH + 1).is_err());
+        assert!(Statement::new().bound(Var::witness(0), Var::instance(0), Var::instance(1)).with_hash(HashFunction::Poseidon).is_err());
+    }
+
+    #[test]
+    fn test_statement_labels_prove() {
+        let instance = String::from(include_str!("../tests/resources/less_than.inst"));
+        let witness = String::from(include_str!("../tests/resources/less_than.wtns"));
+        let gadgets = String::from(include_str!("../tests/resources/less_than.gadgets")).replacen("LESS_THAN W2", "second: LESS_THAN W2", 1);
+
+        let mut commitments = String::new();
+        let proof = prove("Labels", instance.clone(), witness, gadgets.clone(), &mut commitments).unwrap();
+        assert!(commitments.contains("Dsecond-0-0"));
+        assert!(commitments.contains("D2-0-0"));
+        assert!(!commitments.contains("D1-0-0"));
+        assert!(verify("Labels", instance, proof, commitments, gadgets).unwrap());
+    }
+}
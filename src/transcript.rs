@@ -0,0 +1,64 @@
+use merlin::Transcript;
+
+use statement::Statement;
+use lalrpop::assignment_parser::Assignments;
+
+/// Domain separation of the proof transcript, applied identically by prover and verifier.
+///
+/// The statement and the instance are appended before any commitment is made, the commitment
+/// labels right before the proof is created or verified, once all commitments are known.
+pub trait GadgetTranscript {
+    /// append the normalized statement and all instance variables (sorted by name)
+    fn bind_statement(&mut self, statement: &Statement, assignments: &Assignments);
+
+    /// append the commitment labels in the order the commitments were made
+    fn bind_commitments(&mut self, labels: &[String]);
+}
+
+impl GadgetTranscript for Transcript {
+    fn bind_statement(&mut self, statement: &Statement, assignments: &Assignments) {
+        self.append_message(b"dom-sep", b"gadgets v1");
+        self.append_message(b"statement", statement.to_string().as_bytes());
+
+        let instance_vars = assignments.get_instance_vars();
+        self.append_u64(b"instance-len", instance_vars.len() as u64);
+        for (name, bytes) in instance_vars {
+            self.append_message(b"instance-name", name.as_bytes());
+            self.append_message(b"instance-value", &bytes);
+        }
+    }
+
+    fn bind_commitments(&mut self, labels: &[String]) {
+        self.append_u64(b"commitments-len", labels.len() as u64);
+        for label in labels {
+            self.append_message(b"commitment", label.as_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prove::prove;
+    use verify::verify;
+
+    #[test]
+    fn test_commitment_labels_bound() {
+        let instance = String::from(include_str!("../tests/resources/equality.inst"));
+        let witness = String::from(include_str!("../tests/resources/equality.wtns"));
+        let gadgets = String::from(include_str!("../tests/resources/equality.gadgets"));
+
+        let mut commitments = String::new();
+        let proof = prove("TranscriptBinding", instance.clone(), witness, gadgets.clone(), &mut commitments).unwrap();
+        assert!(verify("TranscriptBinding", instance.clone(), proof.clone(), commitments.clone(), gadgets.clone()).unwrap());
+
+        // W0 and W1 hold the same value, swapping their labels keeps the constraints satisfied
+        let swapped = commitments
+            .replacen("C0-0", "TMP", 1)
+            .replacen("C1-0", "C0-0", 1)
+            .replacen("TMP", "C1-0", 1);
+        assert!(!verify("TranscriptBinding", instance.clone(), proof.clone(), swapped, gadgets.clone()).unwrap());
+
+        let reordered = gadgets.replacen("EQUALS W0 W1", "EQUALS W1 W0", 1);
+        assert!(!verify("TranscriptBinding", instance, proof, commitments, reordered).unwrap());
+    }
+}
@@ -0,0 +1,303 @@
+use statement::{Statement, Clause};
+use lalrpop::ast::Var;
+use lalrpop::assignment_parser::{read_instance, read_witness};
+use error::GadgetError;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// bytes of an assignment that fit into a single scalar
+const SCALAR_SIZE: usize = 32;
+
+/// Name and length in bytes of every witness variable, a witness without its values.
+pub type WitnessShape = Vec<(String, usize)>;
+
+/// A problem found in a statement or its assignments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// line of the gadget in the text representation of the statement (1-based), none for the assignments
+    pub line: Option<usize>,
+    pub error: GadgetError
+}
+
+/// kinds of variables a gadget accepts at a position
+#[derive(Clone, Copy, PartialEq)]
+enum Accepts {
+    Instance,
+    Witness,
+    Both
+}
+
+/// length in bytes of every assigned variable
+struct Sizes {
+    instance: HashMap<String, usize>,
+    witness: HashMap<String, usize>
+}
+
+/// shape of the witness variables from string
+pub fn witness_shape(witness: &str) -> Result<WitnessShape, GadgetError> {
+    Ok(read_witness(witness)?.into_iter().map(|(name, bytes)| (name, bytes.len())).collect())
+}
+
+/// Check the statement against the instance and the shape of a witness, without building a constraint system.
+///
+/// Reports missing variables, variables of a kind the gadget does not accept, assignments
+/// exceeding the size of their gadget, compared variables of different sizes as well as
+/// duplicate labels and assignments. All problems are returned, ordered by their line.
+pub fn validate(statement: &Statement, instance: &str, witness_shape: &WitnessShape) -> Result<Vec<Diagnostic>, GadgetError> {
+    let instance = read_instance(instance)?;
+
+    let mut diagnostics: Vec<Diagnostic> = duplicates(instance.iter().map(|(name, _)| name)).into_iter()
+        .chain(duplicates(witness_shape.iter().map(|(name, _)| name)))
+        .map(|name| Diagnostic { line: None, error: GadgetError::DuplicateVariable(name) })
+        .collect();
+
+    let mut labels: Vec<String> = Vec::new();
+    for (index, label) in statement.gadget_labels(0) {
+        if labels.contains(&label) {
+            diagnostics.push(Diagnostic { line: Some(index + 1), error: GadgetError::DuplicateLabel(label) });
+        } else {
+            labels.push(label);
+        }
+    }
+
+    let sizes = Sizes {
+        instance: instance.into_iter().map(|(name, bytes)| (name, bytes.len())).collect(),
+        witness: witness_shape.iter().cloned().collect()
+    };
+    validate_clauses(statement, 0, &sizes, &statement.sha256_witnesses(), &mut diagnostics);
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    Ok(diagnostics)
+}
+
+/// names that occur more than once, reported at their second occurrence
+fn duplicates<'a, I: Iterator<Item = &'a String>>(names: I) -> Vec<String> {
+    let mut seen: Vec<&String> = Vec::new();
+    let mut duplicates = Vec::new();
+    for name in names {
+        if seen.contains(&name) {
+            duplicates.push(name.clone());
+        } else {
+            seen.push(name);
+        }
+    }
+    duplicates
+}
+
+/// check every clause of the statement starting at the given gadget index
+fn validate_clauses(statement: &Statement, index: usize, sizes: &Sizes, sha256: &[Var], diagnostics: &mut Vec<Diagnostic>) {
+    let mut index = index;
+    for clause in statement.clauses() {
+        let errors = check_clause(clause, sizes, sha256);
+        diagnostics.extend(errors.into_iter().map(|error| Diagnostic { line: Some(index + 1), error }));
+
+        // the first branch starts after the OR, [ and { lines
+        if let Some(branches) = clause.branches() {
+            let mut branch_index = index + 3;
+            for branch in branches {
+                validate_clauses(branch, branch_index, sizes, sha256, diagnostics);
+                branch_index += branch.lines() + 2;
+            }
+        }
+        index += clause.lines();
+    }
+}
+
+/// problems of a single gadget, the branches of an OR or THRESHOLD are checked on their own
+fn check_clause(clause: &Clause, sizes: &Sizes, sha256: &[Var]) -> Vec<GadgetError> {
+    let mut errors = Vec::new();
+    match clause {
+        Clause::Bound(var, min, max) => {
+            check_operand(var, Accepts::Witness, sizes, &mut errors);
+            check_var(min, Accepts::Instance, Some(SCALAR_SIZE), sizes, &mut errors);
+            check_var(max, Accepts::Instance, Some(SCALAR_SIZE), sizes, &mut errors);
+        },
+        Clause::Hash(image, preimage, _) | Clause::Sha256(image, preimage) => {
+            check_var(image, Accepts::Both, Some(SCALAR_SIZE), sizes, &mut errors);
+            check_var(preimage, Accepts::Witness, None, sizes, &mut errors);
+        },
+        Clause::Merkle(root, tree, _) => {
+            check_var(root, Accepts::Both, Some(SCALAR_SIZE), sizes, &mut errors);
+            let (instance_vars, witness_vars, _) = tree.flatten();
+            for leaf in instance_vars.iter().chain(witness_vars.iter()) {
+                check_var(leaf, Accepts::Both, None, sizes, &mut errors);
+            }
+        },
+        Clause::MerklePath(root, leaf, path, depth, _) => {
+            check_var(root, Accepts::Both, Some(SCALAR_SIZE), sizes, &mut errors);
+            check_var(leaf, Accepts::Witness, None, sizes, &mut errors);
+            if let Some(size) = check_var(path, Accepts::Witness, None, sizes, &mut errors) {
+                if scalars(size) != depth + 1 {
+                    errors.push(GadgetError::PathLength { name: path.to_string(), depth: *depth, actual: size });
+                }
+            }
+        },
+        Clause::LessThan(left, right) => {
+            check_operand(left, Accepts::Both, sizes, &mut errors);
+            check_operand(right, Accepts::Both, sizes, &mut errors);
+        },
+        Clause::Equality(left, right) | Clause::Inequality(left, right) => {
+            if left.is_expression() || right.is_expression() {
+                check_operand(left, Accepts::Both, sizes, &mut errors);
+                check_operand(right, Accepts::Both, sizes, &mut errors);
+            } else {
+                let left_size = check_var(left, Accepts::Witness, None, sizes, &mut errors);
+                let right_size = check_var(right, Accepts::Both, None, sizes, &mut errors);
+                if let (Some(left_size), Some(right_size)) = (left_size, right_size) {
+                    if scalars(left_size) != scalars(right_size) {
+                        errors.push(GadgetError::SizeMismatch(left.to_string(), right.to_string()));
+                    }
+                }
+            }
+        },
+        Clause::SetMembership(member, set, _) => {
+            for var in [&[member.clone()], &set[..]].concat().iter() {
+                check_var(var, Accepts::Both, None, sizes, &mut errors);
+            }
+        },
+        Clause::Or(_) | Clause::Threshold(..) => ()
+    }
+
+    // the witnesses of SHA256 are committed in chunks of 31 bytes, which no other gadget reads
+    match clause {
+        Clause::Sha256(..) | Clause::Or(_) | Clause::Threshold(..) => (),
+        clause => {
+            let mut vars = clause.vars();
+            vars.dedup();
+            for var in vars.into_iter().filter(|var| sha256.contains(var)) {
+                errors.push(GadgetError::ChunkConflict(var.to_string()));
+            }
+        }
+    }
+    errors
+}
+
+/// number of scalars an assignment of the given length is split into
+fn scalars(size: usize) -> usize {
+    (size + SCALAR_SIZE - 1) / SCALAR_SIZE
+}
+
+/// check a variable or every variable of an expression, which have to fit into a single scalar
+fn check_operand(var: &Var, accepts: Accepts, sizes: &Sizes, errors: &mut Vec<GadgetError>) {
+    match var {
+        Var::Expression(expression) => {
+            for var in expression.vars() {
+                check_var(&var, Accepts::Both, Some(SCALAR_SIZE), sizes, errors);
+            }
+        },
+        var => {
+            check_var(var, accepts, Some(SCALAR_SIZE), sizes, errors);
+        }
+    }
+}
+
+/// check the kind, assignment and length of a variable, returns its length if it is assigned
+fn check_var(var: &Var, accepts: Accepts, max: Option<usize>, sizes: &Sizes, errors: &mut Vec<GadgetError>) -> Option<usize> {
+    let (name, assigned) = match var {
+        Var::Instance(name) if accepts != Accepts::Witness => (name, &sizes.instance),
+        Var::Witness(name) if accepts != Accepts::Instance => (name, &sizes.witness),
+        var => {
+            let expected = match accepts {
+                Accepts::Instance => "an instance variable",
+                Accepts::Witness => "a witness variable",
+                Accepts::Both => "an instance or witness variable"
+            };
+            errors.push(GadgetError::InvalidVariableType(format!("{} is not {}", var, expected)));
+            return None;
+        }
+    };
+
+    match (assigned.get(name), max) {
+        (None, _) => {
+            errors.push(GadgetError::MissingVariable(name.clone()));
+            None
+        },
+        (Some(&size), Some(max)) if size > max => {
+            errors.push(GadgetError::SizeViolation { name: name.clone(), max, actual: size });
+            Some(size)
+        },
+        (Some(&size), _) => Some(size)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.error),
+            None => write!(f, "{}", self.error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_diagnostics() {
+        let instance = format!("I0 = 0x01\nI1 = 0x{}\nI0 = 0x02", "ab".repeat(33));
+        let shape: WitnessShape = vec![(String::from("W0"), 40), (String::from("W1"), 1), (String::from("W2"), 33)];
+        let statement: Statement = "BOUND W0 I0 I1\nEQUALS W1 W2\nOR(LESS_THAN W1 + W3 I0, HASH I2 W1)\n\
+            first: LESS_THAN W1 I0\nfirst: UNEQUAL W1 I0".parse().unwrap();
+
+        let diagnostic = |line: Option<usize>, error: GadgetError| Diagnostic { line, error };
+        assert_eq!(validate(&statement, &instance, &shape).unwrap(), vec![
+            diagnostic(None, GadgetError::DuplicateVariable(String::from("I0"))),
+            diagnostic(Some(1), GadgetError::SizeViolation { name: String::from("W0"), max: 32, actual: 40 }),
+            diagnostic(Some(1), GadgetError::SizeViolation { name: String::from("I1"), max: 32, actual: 33 }),
+            diagnostic(Some(2), GadgetError::SizeMismatch(String::from("W1"), String::from("W2"))),
+            diagnostic(Some(6), GadgetError::MissingVariable(String::from("W3"))),
+            diagnostic(Some(9), GadgetError::MissingVariable(String::from("I2"))),
+            diagnostic(Some(13), GadgetError::DuplicateLabel(String::from("first")))
+        ]);
+
+        // statements built through the API are not restricted by the grammar
+        let statement = Statement::new().bound(Var::instance(0), Var::witness(1), Var::instance(0));
+        assert_eq!(validate(&statement, "I0 = 0x01", &shape).unwrap(), vec![
+            diagnostic(Some(1), GadgetError::InvalidVariableType(String::from("I0 is not a witness variable"))),
+            diagnostic(Some(1), GadgetError::InvalidVariableType(String::from("W1 is not an instance variable")))
+        ]);
+    }
+
+    #[test]
+    fn test_validate_merkle_path() {
+        let shape: WitnessShape = vec![(String::from("W0"), 40), (String::from("W1"), 65), (String::from("W2"), 64)];
+        let statement: Statement = "MERKLE_PATH I0 W0 W1 2\nMERKLE_PATH I0 W0 W2 2".parse().unwrap();
+
+        assert_eq!(validate(&statement, "I0 = 0x01", &shape).unwrap(), vec![
+            Diagnostic { line: Some(2), error: GadgetError::PathLength { name: String::from("W2"), depth: 2, actual: 64 } }
+        ]);
+    }
+
+    #[test]
+    fn test_validate_sha256() {
+        let shape: WitnessShape = vec![(String::from("W0"), 40), (String::from("W1"), 32)];
+        let statement: Statement = "SHA256 W1 W0\nOR(HASH I0 W0, BOUND W1 I0 I0)".parse().unwrap();
+
+        assert_eq!(validate(&statement, "I0 = 0x01", &shape).unwrap(), vec![
+            Diagnostic { line: Some(5), error: GadgetError::ChunkConflict(String::from("W0")) },
+            Diagnostic { line: Some(8), error: GadgetError::ChunkConflict(String::from("W1")) }
+        ]);
+    }
+
+    #[test]
+    fn test_validate_valid() {
+        let resources = vec![
+            (include_str!("../tests/resources/bounds_check.gadgets"), include_str!("../tests/resources/bounds_check.inst"),
+                include_str!("../tests/resources/bounds_check.wtns")),
+            (include_str!("../tests/resources/merkle_tree.gadgets"), include_str!("../tests/resources/merkle_tree.inst"),
+                include_str!("../tests/resources/merkle_tree.wtns")),
+            (include_str!("../tests/resources/or5.gadgets"), include_str!("../tests/resources/or5.inst"),
+                include_str!("../tests/resources/or5.wtns")),
+            (include_str!("../tests/resources/set_membership.gadgets"), include_str!("../tests/resources/set_membership.inst"),
+                include_str!("../tests/resources/set_membership.wtns"))
+        ];
+
+        for (gadgets, instance, witness) in resources {
+            let statement: Statement = gadgets.parse().unwrap();
+            let shape = witness_shape(witness).unwrap();
+            assert_eq!(validate(&statement, instance, &shape).unwrap(), Vec::new());
+        }
+    }
+}
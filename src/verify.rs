@@ -6,28 +6,37 @@ extern crate math;
 
 use bulletproofs::r1cs::{Verifier, Variable, R1CSProof, LinearCombination, ConstraintSystem};
 use bulletproofs::{BulletproofGens, PedersenGens};
+use curve25519_dalek::scalar::Scalar;
 use merlin::Transcript;
 use gadget::Gadget;
 use merkle_tree::merkle_tree_gadget::MerkleTree256;
+use merkle_tree::merkle_path_gadget::{MerklePath, MAX_DEPTH};
 use bounds_check::bounds_check_gadget::BoundsCheck;
 use mimc_hash::mimc_hash_gadget::MimcHash256;
-use mimc_hash::mimc::mimc_hash;
+use poseidon_hash::poseidon_hash_gadget::PoseidonHash;
+use hash::{HashFunction, CircuitHash};
+use sha256_hash::sha256_gadget::{Sha256, Image};
 use equality::equality_gadget::Equality;
 use set_membership::set_membership_gadget::SetMembership;
 use less_than::less_than_gadget::LessThan;
 use inequality::inequality_gadget::Inequality;
 use conversions::{be_to_scalar, be_to_scalars};
 use lalrpop::ast::*;
+use statement::{Statement, Clause, Tree};
+use expression::Operand;
 use lalrpop::assignment_parser::*;
 use cs_buffer::{ConstraintSystemBuffer, VerifierBuffer, Operation};
-use or::or_conjunction::or;
+use evaluator::{Evaluator, evaluate};
+use or::or_conjunction::threshold;
+use error::GadgetError;
+use bundle::ProofBundle;
+use transcript::GadgetTranscript;
+use circuit::{CompiledCircuit, Layout};
+use generators::shared_generators;
 
-use std::iter::{Peekable, Enumerate};
-use std::panic;
-use self::math::round;
+use std::sync::Arc;
 
-// lalrpop parsers
-lalrpop_mod!(gadget_grammar, "/lalrpop/gadget_grammar.rs");
+use self::math::round;
 
 fn round_pow2(num: usize) -> usize {
     2_usize.pow(round::ceil((num as f64).log2(), 0) as u32)
@@ -39,9 +48,125 @@ pub fn verify(
     proof: Vec<u8>,
     commitments: String,
     gadgets: String
-) -> std::io::Result<bool> {
+) -> Result<bool, GadgetError> {
+    let statement: Statement = gadgets.parse()?;
+    verify_statement(name, instance, proof, commitments, &statement)
+}
+
+/// verify a proof bundle after checking it was created for the given label, gadgets and instance
+///
+/// The generator capacity recorded in the bundle has to match the one the verifier needs.
+pub fn verify_bundle(
+    name: &'static str,
+    instance: String,
+    bundle: &ProofBundle,
+    gadgets: String
+) -> Result<bool, GadgetError> {
+    let statement: Statement = gadgets.parse()?;
+    verify_statement_bundle(name, instance, bundle, &statement)
+}
+
+pub fn verify_statement_bundle(
+    name: &'static str,
+    instance: String,
+    bundle: &ProofBundle,
+    statement: &Statement
+) -> Result<bool, GadgetError> {
+    bundle.check(name, statement, &instance)?;
+
+    let (verified, layout) = verify_with(name, instance, bundle.proof.clone(), bundle.commitments.to_string(), statement, &shared_generators)?;
+    if layout.generators != bundle.generators {
+        return Err(GadgetError::BundleMismatch(format!("bundle records {} generators, the verifier needs {}", bundle.generators, layout.generators)));
+    }
+    Ok(verified)
+}
+
+pub fn verify_statement(
+    name: &'static str,
+    instance: String,
+    proof: Vec<u8>,
+    commitments: String,
+    statement: &Statement
+) -> Result<bool, GadgetError> {
+    let (verified, _) = verify_with(name, instance, proof, commitments, statement, &shared_generators)?;
+    Ok(verified)
+}
+
+/// Outcome of a batch verification, one result per proof in the order they were given.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchVerification {
+    pub results: Vec<Result<bool, GadgetError>>
+}
+
+impl BatchVerification {
+    pub fn all_verified(&self) -> bool {
+        self.results.iter().all(|result| result == &Ok(true))
+    }
+
+    /// indices of the proofs that were rejected or could not be checked
+    pub fn failed(&self) -> Vec<usize> {
+        self.results.iter().enumerate()
+            .filter(|(_, result)| result != &&Ok(true))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// verify many (instance, commitments, proof) tuples against the same gadgets
+pub fn verify_batch(
+    name: &'static str,
+    gadgets: String,
+    proofs: Vec<(String, String, Vec<u8>)>
+) -> Result<BatchVerification, GadgetError> {
+    let statement: Statement = gadgets.parse()?;
+    Ok(verify_statement_batch(name, &statement, proofs))
+}
+
+/// The statement is parsed once and the generators are shared between all proofs. The r1cs
+/// backend has no multi-proof verification, so every proof is still checked on its own.
+pub fn verify_statement_batch(
+    name: &'static str,
+    statement: &Statement,
+    proofs: Vec<(String, String, Vec<u8>)>
+) -> BatchVerification {
+    let results = match CompiledCircuit::new(name, statement.clone()) {
+        Ok(circuit) => proofs.into_iter()
+            .map(|(instance, commitments, proof)| circuit.verify(instance, commitments, proof))
+            .collect(),
+        Err(error) => proofs.iter().map(|_| Err(error.clone())).collect()
+    };
+
+    BatchVerification { results }
+}
+
+/// verify with generators of at least the requested capacity, returns the result and the layout of the verifier
+pub fn verify_with(
+    name: &'static str,
+    instance: String,
+    proof: Vec<u8>,
+    commitments: String,
+    statement: &Statement,
+    generators: &dyn Fn(usize) -> Arc<BulletproofGens>
+) -> Result<(bool, Layout), GadgetError> {
+    let assignments = Assignments::for_statement(statement)?;
+    verify_prepared(name, assignments, instance, proof, commitments, statement, generators)
+}
+
+/// verify with assignments prepared by `Assignments::for_statement` for the same statement
+pub fn verify_prepared(
+    name: &'static str,
+    mut assignments: Assignments,
+    instance: String,
+    proof: Vec<u8>,
+    commitments: String,
+    statement: &Statement,
+    generators: &dyn Fn(usize) -> Arc<BulletproofGens>
+) -> Result<(bool, Layout), GadgetError> {
+    assignments.parse_instance(instance)?;
+
     // ---------- CREATE VERIFIER ----------
     let mut verifier_transcript = Transcript::new(name.as_bytes());
+    verifier_transcript.bind_statement(statement, &assignments);
     let pc_gens = PedersenGens::default();
     let mut verifier = Verifier::new(&mut verifier_transcript);
 
@@ -50,26 +175,19 @@ pub fn verify(
     let buffer_verifier = Verifier::new(&mut buffer_transcript);
     let mut verifier_buffer = VerifierBuffer::new(buffer_verifier);
 
-    let proof = R1CSProof::from_bytes(&proof).unwrap();
-    let mut assignments = Assignments::new();
-    assignments.parse_instance(instance).expect("unable to parse provided instance");
-    assignments.parse_commitments(commitments, &mut verifier).expect("unable to parse provided commitments");
-    let mut iter = gadgets.lines().enumerate().into_iter().peekable();
-    while iter.peek().is_some() {
-        let (index, line) = iter.next().unwrap();
-        let line = line;
-
-        let local_initialization = vec![verifier_buffer.buffer().into_iter().map(|op| op.clone()).collect()];
-        parse_conjunction(&mut iter, &line, &assignments, &mut verifier_buffer, local_initialization);
-        parse_gadget(&line, &assignments, &mut verifier_buffer, index);
-    }
+    let proof = R1CSProof::from_bytes(&proof).map_err(|error| GadgetError::ProofDecoding(error.to_string()))?;
+    let labels = assignments.parse_commitments(commitments, &mut verifier)?;
+    verify_clauses(statement, 0, &assignments, &mut verifier_buffer, &Vec::new())?;
 
     assign_buffer(&mut verifier, &verifier_buffer);
 
     // ---------- VERIFY PROOF ----------
-    let bp_gens = BulletproofGens::new(round_pow2(verifier.get_num_vars()), 1);
+    verifier.transcript().bind_commitments(&labels);
+    let multipliers = verifier.get_num_vars();
+    let layout = Layout { commitments: labels, multipliers, generators: round_pow2(multipliers) };
+    let bp_gens = generators(layout.generators);
     let result = verifier.verify(&proof, &pc_gens, &bp_gens);
-    Ok(result.is_ok())
+    Ok((result.is_ok(), layout))
 }
 
 fn assign_buffer(main: &mut dyn ConstraintSystem, buffer: &VerifierBuffer) {
@@ -89,232 +207,316 @@ fn assign_buffer(main: &mut dyn ConstraintSystem, buffer: &VerifierBuffer) {
     }
 }
 
-fn parse_gadget(
-    line: &str,
+/// verify all clauses of the statement, starting at the given gadget index
+fn verify_clauses(
+    statement: &Statement,
+    index: usize,
     assignments: &Assignments,
     verifier: &mut VerifierBuffer,
-    index: usize
-) {
-    match get_gadget_op(&String::from(line)) {
-        GadgetOp::Bound => bounds_check_gadget(line, assignments, verifier, index),
-        GadgetOp::Hash => mimc_hash_gadget(line, assignments, verifier, index),
-        GadgetOp::Merkle => merkle_tree_gadget(line, assignments, verifier, index),
-        GadgetOp::Equality => equality_gadget(line, assignments, verifier),
-        GadgetOp::LessThan => less_than_gadget(line, assignments, verifier, index),
-        GadgetOp::Inequality => inequality_gadget(line, assignments, verifier, index),
-        GadgetOp::SetMembership => set_membership_gadget(line, assignments, verifier, index),
-        _ => {}
-    }
+    initialization: &[Vec<Operation>]
+) -> Result<(), GadgetError> {
+    let mut evaluator = VerifierEvaluator { assignments, verifier };
+    evaluate(&mut evaluator, statement, index, initialization)
 }
 
-fn parse_conjunction(
-    iter: &mut Peekable<Enumerate<std::str::Lines>>,
-    line: &str,
-    assignments: &Assignments,
-    verifier: &mut VerifierBuffer,
-    initialization: Vec<Vec<Operation>>
-) {
-    match get_gadget_op(&String::from(line)) {
-        GadgetOp::Or => or_conjunction(iter, assignments, verifier, initialization),
-        _ => {}
-    }
+/// evaluates the gadgets on the verifier, looking up the derived commitments of the prover
+struct VerifierEvaluator<'a, 'b> {
+    assignments: &'a Assignments,
+    verifier: &'a mut VerifierBuffer<'b>
 }
 
-fn get_gadget_op(line: &String) -> GadgetOp {
-    let gadget_op = line.split_whitespace().next().unwrap_or("");
-    let error = format!("unknown gadget: {}", &gadget_op);
-    gadget_op.parse::<GadgetOp>().expect(&error)
-}
+impl<'a, 'b> Evaluator for VerifierEvaluator<'a, 'b> {
+    fn gadget(&mut self, clause: &Clause, index: usize) -> Result<(), GadgetError> {
+        verify_gadget(clause, self.assignments, self.verifier, index)
+    }
 
-fn or_conjunction(
-    iter: &mut Peekable<Enumerate<std::str::Lines>>,
-    assignments: &Assignments,
-    verifier: &mut VerifierBuffer,
-    initialization: Vec<Vec<Operation>>
-) {
-    let mut or_transcript = Transcript::new(b"OrTranscript");
-    let or_verifier = Verifier::new(&mut or_transcript);
-    let mut verifier_buffer = VerifierBuffer::new(or_verifier);
-    verifier_buffer.initialize_from(initialization.clone());
-
-    if iter.peek().is_none() {
-        panic!("unexpected end of input");
+    fn threshold(
+        &mut self,
+        k: usize,
+        initialization: &[Vec<Operation>],
+        branches: &mut dyn FnMut(&mut dyn Evaluator) -> Result<(), GadgetError>
+    ) -> Result<(), GadgetError> {
+        let mut or_transcript = Transcript::new(b"OrTranscript");
+        let or_verifier = Verifier::new(&mut or_transcript);
+        let mut verifier_buffer = VerifierBuffer::new(or_verifier);
+        verifier_buffer.initialize_from(initialization.to_vec());
+
+        branches(&mut VerifierEvaluator { assignments: self.assignments, verifier: &mut verifier_buffer })?;
+
+        threshold(self.verifier, &verifier_buffer, k, None)
     }
 
-    while iter.peek().is_some() {
-        let (local_index, line) = iter.next().unwrap();
-        let gadget_op = get_gadget_op(&String::from(line));
-        if gadget_op.is_array_end() { break; }
-        if gadget_op.is_block_end() { verifier_buffer.rewind(); }
-        else {
-            let mut local_initialization: Vec<Vec<Operation>> = initialization.clone();
-            local_initialization.push(verifier_buffer.buffer().into_iter().map(|op| op.clone()).collect());
-            parse_conjunction(iter, &line, assignments, &mut verifier_buffer, local_initialization);
-            parse_gadget(&line, assignments, &mut verifier_buffer, local_index);
-        }
+    fn buffer(&self) -> &Vec<Operation> {
+        self.verifier.buffer()
+    }
+
+    fn rewind(&mut self) {
+        self.verifier.rewind();
     }
+}
 
-    or(verifier, &verifier_buffer);
+fn verify_gadget(
+    clause: &Clause,
+    assignments: &Assignments,
+    verifier: &mut VerifierBuffer,
+    index: usize
+) -> Result<(), GadgetError> {
+    match clause.clone() {
+        Clause::Bound(var, min, max) => bounds_check_gadget((var, min, max), assignments, verifier, index),
+        Clause::Hash(image, preimage, HashFunction::MiMC) => hash_gadget::<MimcHash256>((image, preimage), assignments, verifier, index),
+        Clause::Hash(image, preimage, HashFunction::Poseidon) => hash_gadget::<PoseidonHash>((image, preimage), assignments, verifier, index),
+        Clause::Sha256(image, preimage) => sha256_gadget((image, preimage), assignments, verifier),
+        Clause::Merkle(root, tree, HashFunction::MiMC) => merkle_tree_gadget::<MimcHash256>((root, tree), assignments, verifier, index),
+        Clause::Merkle(root, tree, HashFunction::Poseidon) => merkle_tree_gadget::<PoseidonHash>((root, tree), assignments, verifier, index),
+        Clause::MerklePath(root, leaf, path, depth, HashFunction::MiMC) =>
+            merkle_path_gadget::<MimcHash256>((root, leaf, path, depth), assignments, verifier, index),
+        Clause::MerklePath(root, leaf, path, depth, HashFunction::Poseidon) =>
+            merkle_path_gadget::<PoseidonHash>((root, leaf, path, depth), assignments, verifier, index),
+        Clause::Equality(left, right) => equality_gadget((left, right), assignments, verifier),
+        Clause::LessThan(left, right) => less_than_gadget((left, right), assignments, verifier, index),
+        Clause::Inequality(left, right) => inequality_gadget((left, right), assignments, verifier, index),
+        Clause::SetMembership(member, set, HashFunction::MiMC) => set_membership_gadget::<MimcHash256>((member, set), assignments, verifier, index),
+        Clause::SetMembership(member, set, HashFunction::Poseidon) => set_membership_gadget::<PoseidonHash>((member, set), assignments, verifier, index),
+        Clause::Or(_) | Clause::Threshold(..) => Ok(())
+    }
 }
 
 fn bounds_check_gadget(
-    line: &str,
+    args: (Var, Var, Var),
     assignments: &Assignments,
     verifier: &mut VerifierBuffer,
     index: usize
-) {
-    let bound_parser = gadget_grammar::BoundGadgetParser::new();
-    let (var, min, max) = bound_parser.parse(&line).unwrap();
+) -> Result<(), GadgetError> {
+    let (var, min, max) = args;
 
-    let var = assignments.get_commitment(var, 0);
-    let min: Vec<u8> = assignments.get_instance(min, Some(&assert_32));
-    let max: Vec<u8> = assignments.get_instance(max, Some(&assert_32));
+    let min: Vec<u8> = assignments.get_instance(min, Some(&assert_32))?;
+    let max: Vec<u8> = assignments.get_instance(max, Some(&assert_32))?;
 
-    let a = assignments.get_derived(index, 0, 0);
-    let b = assignments.get_derived(index, 1, 0);
+    let a = assignments.get_derived(index, 0, 0)?;
+    let b = assignments.get_derived(index, 1, 0)?;
 
     let gadget = BoundsCheck::new(&min, &max);
-    gadget.verify(verifier, &vec![var], &vec![a, b]);
+    if var.is_expression() {
+        let (lc, _) = scalar_operand(var, assignments, verifier)?;
+        gadget.verify(verifier, &Vec::new(), &vec![a, b]);
+
+        // an expression is no committed variable, tie it to the first derived witness (var - min)
+        verifier.constrain(lc - be_to_scalar(&min) - a);
+    } else {
+        let var = assignments.get_commitment(var, 0)?;
+        gadget.verify(verifier, &vec![var], &vec![a, b]);
+    }
+    Ok(())
 }
 
-fn mimc_hash_gadget(
-    line: &str,
+fn hash_gadget<H: CircuitHash>(
+    args: (Var, Var),
     assignments: &Assignments,
     verifier: &mut VerifierBuffer,
     index: usize
-) {
-    let hash_parser = gadget_grammar::HashGadgetParser::new();
-    let (image, preimage) = hash_parser.parse(&line).unwrap();
+) -> Result<(), GadgetError> {
+    let (image, preimage) = args;
 
     let image: LinearCombination = match image {
-        Var::Witness(_) => assignments.get_commitment(image, 0).into(),
-        Var::Instance(_) => be_to_scalar(&assignments.get_instance(image, Some(&assert_32))).into(),
-        _ => panic!("invalid state")
+        Var::Witness(_) => assignments.get_commitment(image, 0)?.into(),
+        Var::Instance(_) => be_to_scalar(&assignments.get_instance(image, Some(&assert_32))?).into(),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
     };
 
-    let preimage: Vec<Variable> = assignments.get_all_commitments(preimage);
+    let preimage: Vec<Variable> = assignments.get_all_commitments(preimage)?;
 
-    let derived1 = assignments.get_derived(index, 0, 0);
-    let derived2 = assignments.inquire_derived(index, 1, 0);
-    let derived_witnesses = if derived2.is_some() { vec![derived1, *derived2.unwrap()] } else { vec![derived1] };
+    let derived_witnesses = padding_commitments::<H>(assignments, index, 0, 0)?;
 
-    let gadget = MimcHash256::new(image);
+    let gadget = H::with_image(image);
     gadget.verify(verifier, &preimage, &derived_witnesses);
+    Ok(())
 }
 
-fn merkle_tree_gadget(
-    line: &str,
+fn sha256_gadget(
+    args: (Var, Var),
+    assignments: &Assignments,
+    verifier: &mut VerifierBuffer
+) -> Result<(), GadgetError> {
+    let (image, preimage) = args;
+
+    let image: Image = match image {
+        Var::Witness(_) => Image::Committed(assignments.get_all_commitments(image)?.into_iter().map(|var| var.into()).collect()),
+        Var::Instance(_) => Image::Public(assignments.get_instance(image, Some(&assert_32))?),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
+    };
+
+    let preimage: Vec<Variable> = assignments.get_all_commitments(preimage)?;
+
+    let gadget = Sha256::new(image, preimage.len(), None)?;
+    gadget.verify(verifier, &preimage, &Vec::new());
+    Ok(())
+}
+
+fn merkle_tree_gadget<H: CircuitHash>(
+    args: (Var, Tree),
     assignments: &Assignments,
     verifier: &mut VerifierBuffer,
     index: usize
-) {
-    let merkle_parser = gadget_grammar::MerkleGadgetParser::new();
-    let (root, instance_vars, witness_vars, pattern) = merkle_parser.parse(&line).unwrap();
+) -> Result<(), GadgetError> {
+    let (root, tree) = args;
+    let (instance_vars, witness_vars, pattern) = tree.flatten();
 
     let root: LinearCombination = match root {
-        Var::Witness(_) => assignments.get_commitment(root, 0).into(),
-        Var::Instance(_) => be_to_scalar(&assignments.get_instance(root, Some(&assert_32))).into(),
-        _ => panic!("invalid state")
+        Var::Witness(_) => assignments.get_commitment(root, 0)?.into(),
+        Var::Instance(_) => be_to_scalar(&assignments.get_instance(root, Some(&assert_32))?).into(),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
     };
 
     let instance_vars: Vec<LinearCombination> = instance_vars.into_iter()
-        .map(|var| hash_instance(var, &assignments)).collect();
+        .map(|var| hash_instance::<H>(var, &assignments)).collect::<Result<_, GadgetError>>()?;
 
     let mut hash_number = 0;
     let witness_vars: Vec<LinearCombination> = witness_vars.into_iter()
         .map(|var| {
-            let image_var = hash_witness(verifier, var, index, hash_number, &assignments);
+            let image_var = hash_witness::<H>(verifier, var, index, hash_number, &assignments)?;
             hash_number += 1;
-            image_var.into()
-        }).collect();
+            Ok(image_var.into())
+        }).collect::<Result<_, GadgetError>>()?;
 
-    let gadget = MerkleTree256::new(root.into(), instance_vars, witness_vars, pattern.clone());
+    let gadget = MerkleTree256::<H>::with_hash(root.into(), instance_vars, witness_vars, pattern.clone());
     gadget.verify(verifier, &Vec::new(), &Vec::new());
+    Ok(())
+}
+
+fn merkle_path_gadget<H: CircuitHash>(
+    args: (Var, Var, Var, usize),
+    assignments: &Assignments,
+    verifier: &mut VerifierBuffer,
+    index: usize
+) -> Result<(), GadgetError> {
+    let (root, leaf, path, depth) = args;
+
+    // the verifier only knows the number of commitments of the path, which hold up to 32 bytes each
+    if depth == 0 || depth > MAX_DEPTH {
+        let actual = 32 * assignments.get_all_commitments(path.clone())?.len();
+        return Err(GadgetError::PathLength { name: path.to_string(), depth, actual });
+    }
+
+    let root: LinearCombination = match root {
+        Var::Witness(_) => assignments.get_commitment(root, 0)?.into(),
+        Var::Instance(_) => be_to_scalar(&assignments.get_instance(root, Some(&assert_32))?).into(),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
+    };
+
+    let leaf_var = hash_witness::<H>(verifier, leaf, index, 1, &assignments)?;
+
+    // the index followed by the siblings from the leaf upwards, in reverse order of the commitments
+    let path_vars: Vec<Variable> = (0..depth + 1).rev()
+        .map(|chunk| assignments.get_commitment(path.clone(), chunk))
+        .collect::<Result<_, GadgetError>>()?;
+
+    let bits: Vec<Variable> = (0..depth)
+        .map(|level| assignments.get_derived(index, level, 0))
+        .collect::<Result<_, GadgetError>>()?;
+
+    let gadget = MerklePath::<H>::with_hash(root, leaf_var.into());
+    gadget.verify(verifier, &path_vars, &bits);
+    Ok(())
 }
 
 fn equality_gadget(
-    line: &str,
+    args: (Var, Var),
     assignments: &Assignments,
     verifier: &mut VerifierBuffer
-) {
-    let equality_parser = gadget_grammar::EqualityGadgetParser::new();
-    let (left, right) = equality_parser.parse(&line).unwrap();
+) -> Result<(), GadgetError> {
+    let (left, right) = args;
+
+    if left.is_expression() || right.is_expression() {
+        let (left, _) = scalar_operand(left, assignments, verifier)?;
+        let (right, _) = scalar_operand(right, assignments, verifier)?;
+        verifier.constrain(left - right);
+        return Ok(());
+    }
 
-    let left = assignments.get_all_commitments(left);
+    let left = assignments.get_all_commitments(left)?;
 
     let right: Vec<LinearCombination> = match right {
-        Var::Witness(_) => assignments.get_all_commitments(right).into_iter().map(|var| var.into()).collect(),
-        Var::Instance(_) => be_to_scalars(&assignments.get_instance(right, None)).into_iter().map(|scalar| scalar.into()).collect(),
-        _ => panic!("invalid state")
+        Var::Witness(_) => assignments.get_all_commitments(right)?.into_iter().map(|var| var.into()).collect(),
+        Var::Instance(_) => be_to_scalars(&assignments.get_instance(right, None)?).into_iter().map(|scalar| scalar.into()).collect(),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
     };
 
     let gadget = Equality::new(right);
     gadget.verify(verifier, &left, &Vec::new());
+    Ok(())
 }
 
 fn less_than_gadget(
-    line: &str,
+    args: (Var, Var),
     assignments: &Assignments,
     verifier: &mut VerifierBuffer,
     index: usize
-) {
-    let less_than_parser = gadget_grammar::LessThanGadgetParser::new();
-    let (left, right) = less_than_parser.parse(&line).unwrap();
+) -> Result<(), GadgetError> {
+    let (left, right) = args;
 
-    let left = assignments.get_commitment(left, 0);
-    let right = assignments.get_commitment(right, 0);
+    let (left, _) = scalar_operand(left, assignments, verifier)?;
+    let (right, _) = scalar_operand(right, assignments, verifier)?;
 
-    let delta = assignments.get_derived(index, 0, 0);
-    let delta_inv = assignments.get_derived(index, 1, 0);
+    let delta = assignments.get_derived(index, 0, 0)?;
+    let delta_inv = assignments.get_derived(index, 1, 0)?;
 
-    let gadget = LessThan::new(left.into(), None, right.into(), None);
+    let gadget = LessThan::new(left, None, right, None);
     gadget.verify(verifier, &Vec::new(), &vec![delta, delta_inv]);
+    Ok(())
 }
 
 fn inequality_gadget(
-    line: &str,
+    args: (Var, Var),
     assignments: &Assignments,
     verifier: &mut VerifierBuffer,
     index: usize
-) {
-    let inequality_parser = gadget_grammar::InequalityGadgetParser::new();
-    let (left, right) = inequality_parser.parse(&line).unwrap();
-
-    let left: Vec<Variable> = assignments.get_all_commitments(left);
-
-    let right_lc: Vec<LinearCombination> = match right {
-        Var::Witness(_) => assignments.get_all_commitments(right).into_iter().map(|var| var.into()).collect(),
-        Var::Instance(_) => be_to_scalars(&assignments.get_instance(right, None)).into_iter().map(|scalar| scalar.into()).collect(),
-        _ => panic!("invalid state")
+) -> Result<(), GadgetError> {
+    let (left, right) = args;
+
+    let (left, right_lc): (Vec<Variable>, Vec<LinearCombination>) = if left.is_expression() || right.is_expression() {
+        let (left, _) = scalar_operand(left, assignments, verifier)?;
+        let (right, _) = scalar_operand(right, assignments, verifier)?;
+
+        // the gadget expects the left hand side to be a variable
+        let (left_var, _, _) = verifier.multiply(left, Scalar::one().into());
+        (vec![left_var], vec![right])
+    } else {
+        let left = assignments.get_all_commitments(left)?;
+        let right_lc = match right {
+            Var::Witness(_) => assignments.get_all_commitments(right)?.into_iter().map(|var| var.into()).collect(),
+            Var::Instance(_) => be_to_scalars(&assignments.get_instance(right, None)?).into_iter().map(|scalar| scalar.into()).collect(),
+            _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
+        };
+        (left, right_lc)
     };
 
     let mut derived_witnesses: Vec<Variable> = Vec::new();
 
     // get delta and delta_inv values
     for i in 0..(left.len() * 2) {
-        derived_witnesses.push(assignments.get_derived(index, i, 0));
+        derived_witnesses.push(assignments.get_derived(index, i, 0)?);
     }
 
     // get sum_inv value
-    derived_witnesses.push(assignments.get_derived(index, left.len() * 2, 0));
+    derived_witnesses.push(assignments.get_derived(index, left.len() * 2, 0)?);
 
     let gadget = Inequality::new(right_lc, None);
     gadget.verify(verifier, &left, &derived_witnesses);
+    Ok(())
 }
 
-fn set_membership_gadget(
-    line: &str,
+fn set_membership_gadget<H: CircuitHash>(
+    args: (Var, Vec<Var>),
     assignments: &Assignments,
     verifier: &mut VerifierBuffer,
     index: usize
-) {
-    let set_membership_parser = gadget_grammar::SetMembershipGadgetParser::new();
-    let (member, set) = set_membership_parser.parse(&line).unwrap();
+) -> Result<(), GadgetError> {
+    let (member, set) = args;
 
     let member_lcs: Vec<LinearCombination> = match member {
-        Var::Witness(_) => assignments.get_all_commitments(member.clone()).into_iter().map(|var| var.into()).collect(),
-        Var::Instance(_) => be_to_scalars(&assignments.get_instance(member.clone(), None)).into_iter().map(|scalar| scalar.into()).collect(),
-        _ => panic!("invalid state")
+        Var::Witness(_) => assignments.get_all_commitments(member.clone())?.into_iter().map(|var| var.into()).collect(),
+        Var::Instance(_) => be_to_scalars(&assignments.get_instance(member.clone(), None)?).into_iter().map(|scalar| scalar.into()).collect(),
+        _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
     };
 
     let mut member_lc = member_lcs[0].clone();
@@ -328,7 +530,7 @@ fn set_membership_gadget(
         for element in set.clone() {
             match element {
                 Var::Witness(_) => {
-                    let witness = assignments.get_all_commitments(element.clone());
+                    let witness = assignments.get_all_commitments(element.clone())?;
                     if witness.len() == 1 {
                         witness_set_vars.push(witness[0]);
                     } else {
@@ -336,14 +538,14 @@ fn set_membership_gadget(
                     }
                 },
                 Var::Instance(_) => {
-                    let instance_lcs: Vec<LinearCombination> = be_to_scalars(&assignments.get_instance(element, None)).into_iter().map(|scalar| scalar.into()).collect();
+                    let instance_lcs: Vec<LinearCombination> = be_to_scalars(&assignments.get_instance(element, None)?).into_iter().map(|scalar| scalar.into()).collect();
                     if instance_lcs.len() == 1 {
                         instance_set_lcs.push(instance_lcs[0].clone());
                     } else {
                         apply_hashing = true;
                     }
                 },
-                _ => panic!("invalid state")
+                _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
             }
         }
     }
@@ -354,19 +556,19 @@ fn set_membership_gadget(
 
     // get one-hot vector
     for derived_pointer in 0..set.len() {
-        derived_witnesses.push(assignments.get_derived(index, derived_pointer, 0));
+        derived_witnesses.push(assignments.get_derived(index, derived_pointer, 0)?);
     }
 
     if apply_hashing {
         let mut hash_number = 1;
         let hashed_member_lc: LinearCombination = match member {
             Var::Witness(_) => {
-                let image_var = hash_witness(verifier, member, index, hash_number, &assignments);
+                let image_var = hash_witness::<H>(verifier, member, index, hash_number, &assignments)?;
                     hash_number += 1;
                 image_var.into()
             },
-            Var::Instance(_) => hash_instance(member, &assignments),
-            _ => panic!("invalid state")
+            Var::Instance(_) => hash_instance::<H>(member, &assignments)?,
+            _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
         };
 
         member_lc = hashed_member_lc;
@@ -377,49 +579,111 @@ fn set_membership_gadget(
         for element in set {
             match element {
                 Var::Witness(_) => {
-                    let image_var = hash_witness(verifier, element, index, hash_number, &assignments);
+                    let image_var = hash_witness::<H>(verifier, element, index, hash_number, &assignments)?;
                     hash_number += 1;
                     witness_set_vars.push(image_var);
                 },
                 Var::Instance(_) => {
-                    let image_lc = hash_instance(element, &assignments);
+                    let image_lc = hash_instance::<H>(element, &assignments)?;
                     instance_set_lcs.push(image_lc);
                 },
-                _ => panic!("invalid state")
+                _ => return Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
             }
         }
     }
 
     let gadget = SetMembership::new(member_lc, None, instance_set_lcs, None);
     gadget.verify(verifier, &witness_set_vars, &derived_witnesses);
+    Ok(())
 }
 
-fn hash_witness(
+/// linear combination of a variable or expression that fits into a single scalar, the
+/// multipliers of an expression are allocated in the buffer
+fn scalar_operand(var: Var, assignments: &Assignments, verifier: &mut VerifierBuffer) -> Result<Operand, GadgetError> {
+    let scalar = |var: &Var| -> Result<Operand, GadgetError> {
+        match var {
+            Var::Witness(_) => Ok((assignments.get_commitment(var.clone(), 0)?.into(), None)),
+            Var::Instance(_) => Ok((be_to_scalar(&assignments.get_instance(var.clone(), Some(&assert_32))?).into(), None)),
+            _ => Err(GadgetError::InvalidVariableType(String::from("expected instance or witness variable")))
+        }
+    };
+
+    match var {
+        Var::Expression(expression) => expression.lower(verifier, &scalar),
+        var => scalar(&var)
+    }
+}
+
+fn hash_witness<H: CircuitHash>(
     verifier: &mut VerifierBuffer,
     var: Var,
     index: usize,
     subroutine: usize,
     assignments: &Assignments
-) -> Variable {
-    let preimage: Vec<Variable> = assignments.get_all_commitments(var);
-    let image = assignments.get_derived(index, 0, subroutine);
+) -> Result<Variable, GadgetError> {
+    let preimage: Vec<Variable> = assignments.get_all_commitments(var)?;
+    let image = assignments.get_derived(index, 0, subroutine)?;
 
-    let derived1 = assignments.get_derived(index, 1, subroutine);
-    let derived2 = assignments.inquire_derived(index, 2, subroutine);
-    let derived_witnesses = if derived2.is_some() { vec![derived1, *derived2.unwrap()] } else { vec![derived1] };
+    let derived_witnesses = padding_commitments::<H>(assignments, index, 1, subroutine)?;
 
-    let gadget = MimcHash256::new(image.into());
+    let gadget = H::with_image(image.into());
     gadget.verify(verifier, &preimage, &derived_witnesses);
 
-    image
+    Ok(image)
 }
 
-fn hash_instance(
+fn hash_instance<H: CircuitHash>(
     var: Var,
     assignments: &Assignments
-) -> LinearCombination {
-    let instance_var: Vec<u8> = assignments.get_instance(var, None);
-    let image = mimc_hash(&instance_var);
+) -> Result<LinearCombination, GadgetError> {
+    let instance_var: Vec<u8> = assignments.get_instance(var, None)?;
+    let image = H::hash(&instance_var);
 
-    image.into()
+    Ok(image.into())
+}
+
+/// derived commitments of the padding starting at `offset`, the second block is only committed to if needed
+fn padding_commitments<H: CircuitHash>(
+    assignments: &Assignments,
+    index: usize,
+    offset: usize,
+    subroutine: usize
+) -> Result<Vec<Variable>, GadgetError> {
+    if !H::COMMITTED_PADDING {
+        return Ok(Vec::new());
+    }
+
+    let derived1 = assignments.get_derived(index, offset, subroutine)?;
+    let derived2 = assignments.inquire_derived(index, offset + 1, subroutine);
+    Ok(if derived2.is_some() { vec![derived1, *derived2.unwrap()] } else { vec![derived1] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prove::prove;
+
+    #[test]
+    fn test_verify_batch() {
+        let instance = String::from(include_str!("../tests/resources/equality.inst"));
+        let witness = String::from(include_str!("../tests/resources/equality.wtns"));
+        let gadgets = String::from(include_str!("../tests/resources/equality.gadgets"));
+
+        let mut commitments = String::new();
+        let proof = prove("BatchVerification", instance.clone(), witness.clone(), gadgets.clone(), &mut commitments).unwrap();
+        let mut other_commitments = String::new();
+        let other_proof = prove("OtherLabel", instance.clone(), witness, gadgets.clone(), &mut other_commitments).unwrap();
+
+        let batch = verify_batch("BatchVerification", gadgets, vec![
+            (instance.clone(), commitments.clone(), proof.clone()),
+            (instance.clone(), other_commitments, other_proof),
+            (instance.clone(), String::from("C0-0 = 0x01"), proof.clone()),
+            (instance, commitments, proof)
+        ]).unwrap();
+
+        assert!(!batch.all_verified());
+        assert_eq!(batch.failed(), vec![1, 2]);
+        assert_eq!(batch.results[1], Ok(false));
+        assert_eq!(batch.results[2], Err(GadgetError::MalformedCommitment(String::from("C0-0"))));
+    }
 }
@@ -5,7 +5,7 @@ extern crate curve25519_dalek;
 extern crate merlin;
 
 use bulletproofs::r1cs::{Prover, Verifier, Variable};
-use bulletproofs::{BulletproofGens, PedersenGens};
+use bulletproofs::PedersenGens;
 
 use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::scalar::Scalar;
@@ -18,12 +18,13 @@ use bulletproofs_gadgets::merkle_tree::merkle_tree_gadget::{MerkleTree256, Patte
 use bulletproofs_gadgets::bounds_check::bounds_check_gadget::BoundsCheck;
 use bulletproofs_gadgets::mimc_hash::mimc_hash_gadget::MimcHash256;
 use bulletproofs_gadgets::conversions::be_to_scalar;
+use bulletproofs_gadgets::generators::shared_generators;
 
 #[test]
 fn test_combine_gadgets() {
     // ---------- PROVER ----------
     let p_pc_gens = PedersenGens::default();
-    let p_bp_gens = BulletproofGens::new(8192, 1);
+    let p_bp_gens = shared_generators(8192);
     let mut prover_transcript = Transcript::new(b"CombinedGadgets");
     let mut prover = Prover::new(&p_pc_gens, &mut prover_transcript);
 
@@ -82,7 +83,7 @@ fn test_combine_gadgets() {
 
     // ---------- VERIFIER ----------
     let v_pc_gens = PedersenGens::default();
-    let v_bp_gens = BulletproofGens::new(8192, 1);
+    let v_bp_gens = shared_generators(8192);
     let mut verifier_transcript = Transcript::new(b"CombinedGadgets");
     let mut verifier = Verifier::new(&mut verifier_transcript);
     let witness_vars: Vec<Variable> = verifier_commit(&mut verifier, witness_commitments);